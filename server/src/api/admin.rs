@@ -0,0 +1,13 @@
+use crate::api::extract::Json;
+use crate::config::Config;
+use crate::SharedState;
+use axum::{extract::State, routing::get, Router};
+
+pub fn routes() -> Router<SharedState> {
+    Router::new().route("/admin/config", get(get_config))
+}
+
+/// The configuration the server is running with, after every override
+async fn get_config(State(state): State<SharedState>) -> Json<Config> {
+    Json(state.config.clone())
+}
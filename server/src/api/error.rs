@@ -0,0 +1,227 @@
+use crate::llm::{LlmCallError, LlmParseError};
+use crate::game::WorldPaused;
+use crate::npcs::FailingNpc;
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+/// Every way a request can fail, each mapped to a status code and a JSON problem body
+#[derive(Debug)]
+pub enum ApiError {
+    NotFound(String),
+    BadRequest(String),
+    MethodNotAllowed(String),
+    UnsupportedMediaType(String),
+    PayloadTooLarge(String),
+    Validation(Vec<String>),
+    Conflict(String),
+    LlmUnavailable {
+        npc: Option<String>,
+        message: String,
+    },
+    LlmInvalidResponse {
+        npc: Option<String>,
+        message: String,
+        llm_excerpt: String,
+    },
+    /// One or more NPCs failed individually; each gets its own problem entry
+    NpcFailures {
+        message: String,
+        failures: Vec<Problem>,
+    },
+    Internal {
+        npc: Option<String>,
+        message: String,
+    },
+}
+
+/// JSON body returned for every error
+#[derive(Debug, Clone, Serialize)]
+pub struct Problem {
+    pub kind: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub npc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub llm_excerpt: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<Problem>,
+}
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+impl ApiError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::NotFound(message.into())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::MethodNotAllowed(_) => StatusCode::METHOD_NOT_ALLOWED,
+            Self::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::LlmUnavailable { .. } | Self::LlmInvalidResponse { .. } => StatusCode::BAD_GATEWAY,
+            Self::NpcFailures { .. } => StatusCode::BAD_GATEWAY,
+            Self::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "not_found",
+            Self::BadRequest(_) => "bad_request",
+            Self::MethodNotAllowed(_) => "method_not_allowed",
+            Self::UnsupportedMediaType(_) => "unsupported_media_type",
+            Self::PayloadTooLarge(_) => "payload_too_large",
+            Self::Validation(_) => "validation_failed",
+            Self::Conflict(_) => "conflict",
+            Self::LlmUnavailable { .. } => "llm_unavailable",
+            Self::LlmInvalidResponse { .. } => "llm_invalid_response",
+            Self::NpcFailures { .. } => "npc_failures",
+            Self::Internal { .. } => "internal",
+        }
+    }
+
+    pub fn problem(&self) -> Problem {
+        let mut problem = Problem {
+            kind: self.kind(),
+            message: String::new(),
+            npc: None,
+            llm_excerpt: None,
+            details: Vec::new(),
+            failures: Vec::new(),
+        };
+
+        match self {
+            Self::NotFound(message)
+            | Self::BadRequest(message)
+            | Self::MethodNotAllowed(message)
+            | Self::UnsupportedMediaType(message)
+            | Self::PayloadTooLarge(message)
+            | Self::Conflict(message) => {
+                problem.message = message.clone();
+            }
+            Self::Validation(errors) => {
+                problem.message = "Request failed validation".to_string();
+                problem.details = errors.clone();
+            }
+            Self::LlmUnavailable { npc, message } | Self::Internal { npc, message } => {
+                problem.message = message.clone();
+                problem.npc = npc.clone();
+            }
+            Self::LlmInvalidResponse { npc, message, llm_excerpt } => {
+                problem.message = message.clone();
+                problem.npc = npc.clone();
+                problem.llm_excerpt = Some(llm_excerpt.clone());
+            }
+            Self::NpcFailures { message, failures } => {
+                problem.message = message.clone();
+                problem.failures = failures.clone();
+            }
+        }
+
+        problem
+    }
+}
+
+impl Problem {
+    /// Problem for an NPC whose failure was only recorded as text, e.g. in a memory update outcome
+    pub fn for_npc(npc: &str, message: &str, llm_excerpt: Option<&str>) -> Self {
+        let npc = Some(npc.to_string());
+        let message = message.to_string();
+        match llm_excerpt {
+            Some(excerpt) => ApiError::LlmInvalidResponse {
+                npc,
+                message,
+                llm_excerpt: excerpt.to_string(),
+            },
+            None => ApiError::Internal { npc, message },
+        }
+        .problem()
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.kind(), self.problem().message)
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    /// Classify an error from the game pipeline by the markers in its chain
+    fn from(error: anyhow::Error) -> Self {
+        let npc = error.downcast_ref::<FailingNpc>().map(|failing| failing.0.clone());
+        let message = format!("{error:#}");
+
+        if error.downcast_ref::<WorldPaused>().is_some() {
+            Self::Conflict(message)
+        } else if let Some(parse_error) = error.downcast_ref::<LlmParseError>() {
+            Self::LlmInvalidResponse {
+                npc,
+                message,
+                llm_excerpt: parse_error.excerpt(),
+            }
+        } else if error.downcast_ref::<LlmCallError>().is_some() {
+            Self::LlmUnavailable { npc, message }
+        } else {
+            Self::Internal { npc, message }
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        // Keep axum's status, so a missing Content-Type stays a 415
+        match rejection.status() {
+            StatusCode::UNPROCESSABLE_ENTITY => Self::Validation(vec![rejection.body_text()]),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => Self::UnsupportedMediaType(rejection.body_text()),
+            StatusCode::PAYLOAD_TOO_LARGE => Self::PayloadTooLarge(rejection.body_text()),
+            _ => Self::BadRequest(rejection.body_text()),
+        }
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::BadRequest(rejection.body_text())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            log::error!("{self}");
+        } else {
+            log::debug!("{self}");
+        }
+        (status, Json(self.problem())).into_response()
+    }
+}
+
+/// Answer unknown routes with the same problem body as everything else
+pub async fn not_found_fallback() -> ApiError {
+    ApiError::not_found("No such route")
+}
+
+/// Answer a known route called with the wrong method the same way; axum still
+/// adds the Allow header listing the methods it does take
+pub async fn method_not_allowed_fallback() -> ApiError {
+    ApiError::MethodNotAllowed("Method not allowed on this route".to_string())
+}
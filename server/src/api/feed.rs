@@ -0,0 +1,38 @@
+use crate::SharedState;
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+    Router,
+};
+use futures::stream::{self, Stream};
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
+
+pub fn routes() -> Router<SharedState> {
+    Router::new().route("/turn/events", get(turn_events))
+}
+
+/// Server-sent events for everything happening in this world's turns from now on,
+/// including NPC intents as the LLM writes them
+async fn turn_events(State(state): State<SharedState>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state.game_manager.feed.subscribe();
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let sse = Event::default()
+                        .event(event.kind())
+                        .json_data(&event)
+                        .unwrap_or_else(|_| Event::default().event(event.kind()));
+                    return Some((Ok(sse), receiver));
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    log::warn!("Turn event listener fell behind, skipped {missed} events");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,40 @@
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::extract::{Json, Path};
+use crate::npcs::gossip::{is_valid_rumor_id, list_rumors, trace_rumor, Rumor, RumorTrace};
+use crate::npcs::knowledge::{load_knowledge, KnowledgeBase};
+use crate::SharedState;
+use axum::{extract::State, routing::get, Router};
+
+pub fn routes() -> Router<SharedState> {
+    Router::new()
+        .route("/npcs/{name}/knowledge", get(get_knowledge))
+        .route("/rumors", get(get_rumors))
+        .route("/rumors/{id}", get(get_rumor_trace))
+}
+
+async fn get_knowledge(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<KnowledgeBase>> {
+    if !state.game_manager.get_state().npcs.contains_key(&name) {
+        return Err(ApiError::not_found(format!("Unknown NPC: {name}")));
+    }
+    Ok(Json(load_knowledge(&state.game_manager.data_dir, &name)?))
+}
+
+async fn get_rumors(State(state): State<SharedState>) -> ApiResult<Json<Vec<Rumor>>> {
+    Ok(Json(list_rumors(&state.game_manager.data_dir)?))
+}
+
+async fn get_rumor_trace(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<RumorTrace>> {
+    if !is_valid_rumor_id(&id) {
+        return Err(ApiError::BadRequest(format!("Invalid rumor id: {id}")));
+    }
+    let game_state = state.game_manager.get_state();
+    trace_rumor(&state.game_manager.data_dir, &id, game_state.npcs.keys())?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Unknown rumor: {id}")))
+}
@@ -0,0 +1,345 @@
+use crate::SharedState;
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::extract::{Json, Path, Query};
+use crate::npcs::memory::{Memory, MemorySystem, RelationshipMemory};
+use crate::npcs::memory_history::{MemoryDiff, MemoryVersion, MemoryVersionSummary, diff_versions};
+use crate::npcs::memory_store::{
+    list_memory_versions, load_memory_version, load_npc_memories, save_npc_memories,
+};
+use crate::utils::merge_patch;
+use axum::{
+    Router,
+    extract::State,
+    routing::{delete, get, post, put},
+};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct NewSelfEvent {
+    pub event: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    pub from: u32,
+    /// Defaults to the latest version
+    pub to: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevertRequest {
+    pub version: u32,
+}
+
+pub fn routes() -> Router<SharedState> {
+    Router::new()
+        .route(
+            "/npcs/{name}/memories",
+            get(get_memories)
+                .put(replace_memories)
+                .patch(patch_memories),
+        )
+        .route("/npcs/{name}/memories/self/events", post(add_self_event))
+        .route(
+            "/npcs/{name}/memories/self/events/{index}",
+            delete(remove_self_event),
+        )
+        .route(
+            "/npcs/{name}/memories/relationships/{other}",
+            put(put_relationship).delete(remove_relationship),
+        )
+        .route(
+            "/npcs/{name}/memories/relationships/{other}/memories",
+            post(add_relationship_memory),
+        )
+        .route(
+            "/npcs/{name}/memories/relationships/{other}/memories/{index}",
+            delete(remove_relationship_memory),
+        )
+        .route(
+            "/npcs/{name}/memories/relationships/{other}/memories/{index}/pin",
+            put(pin_relationship_memory).delete(unpin_relationship_memory),
+        )
+        .route("/npcs/{name}/memories/versions", get(list_versions))
+        .route("/npcs/{name}/memories/versions/{version}", get(get_version))
+        .route("/npcs/{name}/memories/diff", get(diff_memories))
+        .route("/npcs/{name}/memories/revert", post(revert_memories))
+}
+
+fn ensure_npc_exists(state: &SharedState, name: &str) -> ApiResult<()> {
+    if state.game_manager.get_state().npcs.contains_key(name) {
+        Ok(())
+    } else {
+        Err(ApiError::not_found(format!("Unknown NPC: {name}")))
+    }
+}
+
+/// Load, modify, validate and save an NPC's memories while holding the turn lock,
+/// so the edit can't interleave with a memory update from a running turn
+async fn edit_memories<F>(
+    state: &SharedState,
+    name: &str,
+    reason: &str,
+    edit: F,
+) -> ApiResult<Json<MemorySystem>>
+where
+    F: FnOnce(&mut MemorySystem) -> ApiResult<()>,
+{
+    ensure_npc_exists(state, name)?;
+    let _turn_guard = state.game_manager.turn_lock.lock().await;
+
+    let mut memories = load_npc_memories(&state.game_manager.data_dir, name)?;
+    edit(&mut memories)?;
+    memories.validate(name).map_err(ApiError::Validation)?;
+    let turn = state.game_manager.current_turn();
+    let version = save_npc_memories(
+        &state.game_manager.data_dir,
+        name,
+        &memories,
+        turn,
+        reason,
+        &state.game_manager.memory_policy,
+    )?;
+
+    log::info!(
+        "🧠 [Memory API][{}] {} (version {})",
+        name.to_uppercase(),
+        reason,
+        version.version
+    );
+    Ok(Json(memories))
+}
+
+fn relationship_mut<'a>(
+    memories: &'a mut MemorySystem,
+    other: &str,
+) -> ApiResult<&'a mut RelationshipMemory> {
+    memories
+        .relationships
+        .get_mut(other)
+        .ok_or_else(|| ApiError::not_found(format!("No relationship with {other}")))
+}
+
+fn relationship_memory_mut<'a>(
+    memories: &'a mut MemorySystem,
+    other: &str,
+    index: usize,
+) -> ApiResult<&'a mut Memory> {
+    relationship_mut(memories, other)?
+        .recent_memories
+        .get_mut(index)
+        .ok_or_else(|| ApiError::not_found(format!("No memory at index {index} for {other}")))
+}
+
+async fn get_memories(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<MemorySystem>> {
+    ensure_npc_exists(&state, &name)?;
+    let _turn_guard = state.game_manager.turn_lock.lock().await;
+    Ok(Json(load_npc_memories(
+        &state.game_manager.data_dir,
+        &name,
+    )?))
+}
+
+async fn replace_memories(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(replacement): Json<MemorySystem>,
+) -> ApiResult<Json<MemorySystem>> {
+    edit_memories(&state, &name, "api: replace memories", |memories| {
+        *memories = replacement;
+        Ok(())
+    })
+    .await
+}
+
+async fn patch_memories(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(patch): Json<serde_json::Value>,
+) -> ApiResult<Json<MemorySystem>> {
+    edit_memories(&state, &name, "api: patch memories", |memories| {
+        let mut merged = serde_json::to_value(&*memories).map_err(anyhow::Error::from)?;
+        merge_patch(&mut merged, &patch);
+        *memories = serde_json::from_value(merged).map_err(|e| {
+            ApiError::Validation(vec![format!("Patched memories are invalid: {e}")])
+        })?;
+        Ok(())
+    })
+    .await
+}
+
+async fn add_self_event(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(new_event): Json<NewSelfEvent>,
+) -> ApiResult<Json<MemorySystem>> {
+    edit_memories(&state, &name, "api: add self event", |memories| {
+        memories.self_memories.recent_events.push(new_event.event);
+        Ok(())
+    })
+    .await
+}
+
+async fn remove_self_event(
+    State(state): State<SharedState>,
+    Path((name, index)): Path<(String, usize)>,
+) -> ApiResult<Json<MemorySystem>> {
+    edit_memories(&state, &name, "api: remove self event", |memories| {
+        let events = &mut memories.self_memories.recent_events;
+        if index >= events.len() {
+            return Err(ApiError::not_found(format!(
+                "No self event at index {index}"
+            )));
+        }
+        events.remove(index);
+        Ok(())
+    })
+    .await
+}
+
+async fn put_relationship(
+    State(state): State<SharedState>,
+    Path((name, other)): Path<(String, String)>,
+    Json(relationship): Json<RelationshipMemory>,
+) -> ApiResult<Json<MemorySystem>> {
+    edit_memories(&state, &name, "api: set relationship", |memories| {
+        memories.relationships.insert(other, relationship);
+        Ok(())
+    })
+    .await
+}
+
+async fn remove_relationship(
+    State(state): State<SharedState>,
+    Path((name, other)): Path<(String, String)>,
+) -> ApiResult<Json<MemorySystem>> {
+    edit_memories(&state, &name, "api: remove relationship", |memories| {
+        memories
+            .relationships
+            .remove(&other)
+            .map(|_| ())
+            .ok_or_else(|| ApiError::not_found(format!("No relationship with {other}")))
+    })
+    .await
+}
+
+async fn add_relationship_memory(
+    State(state): State<SharedState>,
+    Path((name, other)): Path<(String, String)>,
+    Json(memory): Json<Memory>,
+) -> ApiResult<Json<MemorySystem>> {
+    edit_memories(&state, &name, "api: add relationship memory", |memories| {
+        relationship_mut(memories, &other)?
+            .recent_memories
+            .push(memory);
+        Ok(())
+    })
+    .await
+}
+
+async fn remove_relationship_memory(
+    State(state): State<SharedState>,
+    Path((name, other, index)): Path<(String, String, usize)>,
+) -> ApiResult<Json<MemorySystem>> {
+    edit_memories(
+        &state,
+        &name,
+        "api: remove relationship memory",
+        |memories| {
+            relationship_memory_mut(memories, &other, index)?;
+            relationship_mut(memories, &other)?
+                .recent_memories
+                .remove(index);
+            Ok(())
+        },
+    )
+    .await
+}
+
+async fn pin_relationship_memory(
+    State(state): State<SharedState>,
+    Path((name, other, index)): Path<(String, String, usize)>,
+) -> ApiResult<Json<MemorySystem>> {
+    edit_memories(&state, &name, "api: pin relationship memory", |memories| {
+        relationship_memory_mut(memories, &other, index)?.pinned = true;
+        Ok(())
+    })
+    .await
+}
+
+async fn unpin_relationship_memory(
+    State(state): State<SharedState>,
+    Path((name, other, index)): Path<(String, String, usize)>,
+) -> ApiResult<Json<MemorySystem>> {
+    edit_memories(
+        &state,
+        &name,
+        "api: unpin relationship memory",
+        |memories| {
+            relationship_memory_mut(memories, &other, index)?.pinned = false;
+            Ok(())
+        },
+    )
+    .await
+}
+
+fn find_version(state: &SharedState, name: &str, version: u32) -> ApiResult<MemoryVersion> {
+    load_memory_version(&state.game_manager.data_dir, name, version)?
+        .ok_or_else(|| ApiError::not_found(format!("No memory version {version} for {name}")))
+}
+
+async fn list_versions(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<Vec<MemoryVersionSummary>>> {
+    ensure_npc_exists(&state, &name)?;
+    Ok(Json(list_memory_versions(
+        &state.game_manager.data_dir,
+        &name,
+    )?))
+}
+
+async fn get_version(
+    State(state): State<SharedState>,
+    Path((name, version)): Path<(String, u32)>,
+) -> ApiResult<Json<MemoryVersion>> {
+    ensure_npc_exists(&state, &name)?;
+    find_version(&state, &name, version).map(Json)
+}
+
+async fn diff_memories(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Query(query): Query<DiffQuery>,
+) -> ApiResult<Json<MemoryDiff>> {
+    ensure_npc_exists(&state, &name)?;
+
+    let to = match query.to {
+        Some(to) => to,
+        None => list_memory_versions(&state.game_manager.data_dir, &name)?
+            .last()
+            .map(|latest| latest.version)
+            .ok_or_else(|| ApiError::not_found(format!("No memory versions for {name}")))?,
+    };
+
+    let from = find_version(&state, &name, query.from)?;
+    let to = find_version(&state, &name, to)?;
+    Ok(Json(diff_versions(&from, &to)))
+}
+
+async fn revert_memories(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(request): Json<RevertRequest>,
+) -> ApiResult<Json<MemorySystem>> {
+    let target = find_version(&state, &name, request.version)?;
+    let reason = format!("api: revert to version {}", request.version);
+    edit_memories(&state, &name, &reason, |memories| {
+        *memories = target.memories;
+        Ok(())
+    })
+    .await
+}
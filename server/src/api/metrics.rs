@@ -0,0 +1,55 @@
+use crate::api::extract::Json;
+use crate::llm::UsageReport;
+use crate::metrics::metrics;
+use crate::SharedState;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use std::time::Instant;
+
+pub fn routes() -> Router<SharedState> {
+    Router::new().route("/metrics/usage", get(get_usage))
+}
+
+/// Tokens, latency and cost of this world's LLM calls, by turn, NPC, role and model
+async fn get_usage(State(state): State<SharedState>) -> Json<UsageReport> {
+    Json(state.game_manager.usage.report())
+}
+
+/// The server's metrics in Prometheus text format
+pub async fn prometheus() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics().render(),
+    )
+}
+
+/// Count and time every request, labelled by its route pattern rather than the
+/// raw path so NPC names and ids don't each get their own series
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+
+    let metrics = metrics();
+    metrics
+        .http_requests
+        .with_label_values(&[method.as_str(), route.as_str(), response.status().as_str()])
+        .inc();
+    metrics
+        .http_request_duration
+        .with_label_values(&[method.as_str(), route.as_str()])
+        .observe(started.elapsed().as_secs_f64());
+    response
+}
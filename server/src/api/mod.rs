@@ -0,0 +1,12 @@
+pub mod admin;
+pub mod error;
+pub mod extract;
+pub mod feed;
+pub mod knowledge;
+pub mod memories;
+pub mod metrics;
+pub mod player;
+pub mod world;
+pub mod worlds;
+
+pub use error::{ApiError, ApiResult, Problem};
@@ -0,0 +1,79 @@
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::extract::Json;
+use crate::types::{Intent, JoinPlayerRequest, Player, PlayerIntentRequest};
+use crate::SharedState;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::post,
+    Router,
+};
+
+pub fn routes() -> Router<SharedState> {
+    Router::new()
+        .route("/player", post(join).get(get_player))
+        .route("/player/intent", post(submit_intent))
+}
+
+fn current_player(state: &SharedState) -> ApiResult<Player> {
+    state
+        .game_manager
+        .player()
+        .ok_or_else(|| ApiError::not_found("No player has joined the world"))
+}
+
+async fn join(
+    State(state): State<SharedState>,
+    Json(request): Json<JoinPlayerRequest>,
+) -> ApiResult<(StatusCode, Json<Player>)> {
+    let name = request.name.trim().to_lowercase();
+    if name.is_empty() {
+        return Err(ApiError::Validation(vec!["Player name must not be empty".to_string()]));
+    }
+
+    // Wait out a running turn, so a rollback can't take the new player with it
+    let _turn_guard = state.game_manager.turn_lock.lock().await;
+    if state.game_manager.get_state().npcs.contains_key(&name) {
+        return Err(ApiError::Conflict(format!("{name} is already an NPC")));
+    }
+    if let Some(existing) = state.game_manager.player() {
+        return Err(ApiError::Conflict(format!("{} is already playing", existing.name)));
+    }
+
+    let player = Player {
+        name,
+        location: request.location,
+        activity: "just arrived".to_string(),
+        active_contract: None,
+        narration: None,
+        last_observation: None,
+    };
+    state.game_manager.set_player(player.clone());
+
+    log::info!("🧍 [Player][{}] Joined the world at {:?}", player.name.to_uppercase(), player.location);
+    Ok((StatusCode::CREATED, Json(player)))
+}
+
+async fn get_player(State(state): State<SharedState>) -> ApiResult<Json<Player>> {
+    current_player(&state).map(Json)
+}
+
+/// Queue what the player does next; the running or next turn picks it up
+async fn submit_intent(
+    State(state): State<SharedState>,
+    Json(request): Json<PlayerIntentRequest>,
+) -> ApiResult<(StatusCode, Json<Intent>)> {
+    let player = current_player(&state)?;
+    if request.action.trim().is_empty() {
+        return Err(ApiError::Validation(vec!["action must not be empty".to_string()]));
+    }
+
+    let intent = Intent {
+        npc: player.name,
+        thought: request.thought,
+        action: request.action,
+        dialogue: request.dialogue,
+    };
+    state.game_manager.player_input.submit(intent.clone());
+    Ok((StatusCode::ACCEPTED, Json(intent)))
+}
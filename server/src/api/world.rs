@@ -0,0 +1,48 @@
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::extract::Json;
+use crate::game::events::{WorldEvent, WorldEventsOverview};
+use crate::types::Location;
+use crate::SharedState;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct InterventionRequest {
+    pub description: String,
+    #[serde(default)]
+    pub location: Option<Location>,
+}
+
+pub fn routes() -> Router<SharedState> {
+    Router::new().route("/world/events", get(get_events).post(intervene))
+}
+
+async fn get_events(State(state): State<SharedState>) -> ApiResult<Json<WorldEventsOverview>> {
+    Ok(Json(state.game_manager.world_events.overview(&state.game_manager.data_dir)?))
+}
+
+/// Make something happen on the next turn; the GM has to work it into the story
+async fn intervene(
+    State(state): State<SharedState>,
+    Json(request): Json<InterventionRequest>,
+) -> ApiResult<(StatusCode, Json<WorldEvent>)> {
+    if request.description.trim().is_empty() {
+        return Err(ApiError::Validation(vec!["description must not be empty".to_string()]));
+    }
+
+    // Wait out a running turn, so a rollback can't take the intervention with it
+    let _turn_guard = state.game_manager.turn_lock.lock().await;
+    let next_turn = state.game_manager.current_turn() + 1;
+    let event = state
+        .game_manager
+        .world_events
+        .intervene(request.description, request.location, next_turn);
+
+    log::info!("🌩️ [GM Intervention][{}] Queued for turn {}: {}", event.id, next_turn, event.description);
+    Ok((StatusCode::ACCEPTED, Json(event)))
+}
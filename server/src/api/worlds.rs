@@ -0,0 +1,370 @@
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::extract::{Json, Path};
+use crate::data_dir::DataDir;
+use crate::game::transaction::write_file;
+use crate::game::GameStateManager;
+use crate::prompts::{PromptBuilder, PromptLoader};
+use crate::{AppState, SharedState};
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    response::Response,
+    routing::{any, get, post},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use tower::ServiceExt;
+
+/// The world the server starts with, served both at the top level and under /worlds/default
+pub const DEFAULT_WORLD: &str = "default";
+
+/// Kept in each world's folder so the world is found again after a restart
+const WORLD_FILE: &str = "world.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WorldOrigin {
+    template: Option<String>,  // None for the default world
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+struct World {
+    state: SharedState,
+    router: Router,
+    origin: WorldOrigin,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorldSummary {
+    pub id: String,
+    pub template: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub paused: bool,
+    pub turn: u64,
+    pub npcs: Vec<String>,
+    pub data_dir: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWorldRequest {
+    pub id: String,
+    #[serde(default)]
+    pub template: Option<String>,  // A world to copy, the default world if unset
+}
+
+/// Every world this server hosts. Each has its own game state, NPCs, data directory
+/// and turn lock; they only share the LLM client.
+pub struct WorldRegistry {
+    worlds_dir: PathBuf,
+    worlds: RwLock<BTreeMap<String, World>>,
+    creating: Mutex<HashSet<String>>,  // Ids of worlds still being copied
+}
+
+/// Holds a world id while the world is being created, releasing it when dropped
+struct Reservation<'a> {
+    creating: &'a Mutex<HashSet<String>>,
+    id: String,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.creating.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl World {
+    fn open(base: &AppState, data_dir: DataDir, origin: WorldOrigin) -> Result<Self> {
+        let mut config = base.config.clone();
+        config.paths.data_dir = data_dir.root().to_path_buf();
+
+        let npcs = data_dir
+            .npc_names()
+            .with_context(|| format!("Failed to list NPCs in {:?}", data_dir.root()))?;
+        let state = Arc::new(AppState {
+            game_manager: GameStateManager::from_config(&config).with_npcs(&npcs),
+            config,
+            llm_client: Arc::clone(&base.llm_client),
+            prompt_builder: PromptBuilder::new(PromptLoader::new(data_dir)),
+        });
+
+        Ok(Self {
+            router: crate::world_router(Arc::clone(&state)),
+            state,
+            origin,
+        })
+    }
+
+    fn summary(&self, id: &str) -> WorldSummary {
+        let game_state = self.state.game_manager.get_state();
+        let mut npcs: Vec<String> = game_state.npcs.keys().cloned().collect();
+        npcs.sort();
+        WorldSummary {
+            id: id.to_string(),
+            template: self.origin.template.clone(),
+            created_at: self.origin.created_at,
+            paused: self.state.game_manager.is_paused(),
+            turn: game_state.turn,
+            npcs,
+            data_dir: self.state.game_manager.data_dir.root().to_path_buf(),
+        }
+    }
+}
+
+impl WorldRegistry {
+    /// Register the default world, then every world left in the worlds directory
+    pub fn new(default: SharedState) -> Self {
+        let worlds_dir = default.config.paths.worlds_dir.clone();
+        let mut worlds = BTreeMap::new();
+
+        if let Ok(entries) = std::fs::read_dir(&worlds_dir) {
+            for entry in entries.flatten() {
+                let id = entry.file_name().to_string_lossy().to_string();
+                match reopen(&default, &entry.path()) {
+                    Ok(world) => {
+                        log::info!("🌍 [Worlds][{}] Reopened world from {:?}", id, entry.path());
+                        worlds.insert(id, world);
+                    }
+                    Err(e) => log::warn!("🌍 [Worlds][{}] Skipping {:?}: {e:#}", id, entry.path()),
+                }
+            }
+        }
+
+        worlds.insert(
+            DEFAULT_WORLD.to_string(),
+            World {
+                router: crate::world_router(Arc::clone(&default)),
+                state: default,
+                origin: WorldOrigin {
+                    template: None,
+                    created_at: Utc::now(),
+                },
+            },
+        );
+
+        Self {
+            worlds_dir,
+            worlds: RwLock::new(worlds),
+            creating: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn get(&self, id: &str) -> ApiResult<World> {
+        self.worlds
+            .read()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| ApiError::not_found(format!("Unknown world: {id}")))
+    }
+
+    fn list(&self) -> Vec<WorldSummary> {
+        self.worlds
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(id, world)| world.summary(id))
+            .collect()
+    }
+
+    /// Reserve the id, copy the template while holding its turn lock (so no turn
+    /// is half-written into the copy), then open the copy as a new world
+    async fn create(&self, request: CreateWorldRequest) -> ApiResult<WorldSummary> {
+        let id = request.id.trim().to_lowercase();
+        validate_world_id(&id)?;
+        let template_id = request.template.unwrap_or_else(|| DEFAULT_WORLD.to_string());
+
+        let (template, base, _reservation) = {
+            let worlds = self.worlds.read().unwrap();
+            let mut creating = self.creating.lock().unwrap();
+            if worlds.contains_key(&id) || creating.contains(&id) {
+                return Err(ApiError::Conflict(format!("World {id} already exists")));
+            }
+            let template = worlds
+                .get(&template_id)
+                .cloned()
+                .ok_or_else(|| ApiError::not_found(format!("Unknown template world: {template_id}")))?;
+            creating.insert(id.clone());
+            let reservation = Reservation {
+                creating: &self.creating,
+                id: id.clone(),
+            };
+            (template, Arc::clone(&worlds[DEFAULT_WORLD].state), reservation)
+        };
+
+        let target = self.worlds_dir.join(&id);
+        if target.exists() {
+            return Err(ApiError::Conflict(format!("{:?} already exists on disk", target)));
+        }
+
+        let origin = WorldOrigin {
+            template: Some(template_id.clone()),
+            created_at: Utc::now(),
+        };
+        let world = {
+            let template_manager = &template.state.game_manager;
+            let _turn_guard = template_manager.turn_lock.lock().await;
+            let source = template_manager.data_dir.clone();
+            let copy_target = target.clone();
+            let copied = tokio::task::spawn_blocking(move || source.copy_template_to(&copy_target))
+                .await
+                .context("World copy was interrupted")
+                .and_then(|copied| copied.with_context(|| format!("Failed to copy world {template_id} to {:?}", target)))
+                .and_then(|data_dir| settle(&base, data_dir, origin));
+            match copied {
+                Ok(world) => world,
+                Err(e) => {
+                    // Don't leave a half-made world behind to block the id
+                    let _ = std::fs::remove_dir_all(&target);
+                    return Err(e.into());
+                }
+            }
+        };
+
+        let summary = world.summary(&id);
+        self.worlds.write().unwrap().insert(id.clone(), world);
+
+        log::info!("🌍 [Worlds][{}] Created from {}", id, template_id);
+        Ok(summary)
+    }
+
+    /// Stop routing to the world, let any running turn finish, then remove its files.
+    /// If they can't all be removed the world is routed to again, left paused.
+    async fn delete(&self, id: &str) -> ApiResult<()> {
+        if id == DEFAULT_WORLD {
+            return Err(ApiError::Conflict("The default world can't be deleted".to_string()));
+        }
+        let world = self
+            .worlds
+            .write()
+            .unwrap()
+            .remove(id)
+            .ok_or_else(|| ApiError::not_found(format!("Unknown world: {id}")))?;
+
+        let game_manager = &world.state.game_manager;
+        game_manager.pause();
+        let _turn_guard = game_manager.turn_lock.lock().await;
+        let removed = std::fs::remove_dir_all(game_manager.data_dir.root())
+            .with_context(|| format!("Failed to remove {:?}", game_manager.data_dir.root()));
+        if let Err(e) = removed {
+            // Keep what's left reachable, paused, so the delete can be retried
+            self.worlds.write().unwrap().insert(id.to_string(), world.clone());
+            return Err(e.into());
+        }
+
+        log::info!("🌍 [Worlds][{}] Deleted", id);
+        Ok(())
+    }
+}
+
+/// Record where a freshly copied world came from, then open it
+fn settle(base: &AppState, data_dir: DataDir, origin: WorldOrigin) -> Result<World> {
+    let origin_file = data_dir.root().join(WORLD_FILE);
+    write_file(&origin_file, serde_json::to_string_pretty(&origin)?)
+        .with_context(|| format!("Failed to write {:?}", origin_file))?;
+    World::open(base, data_dir, origin)
+}
+
+fn reopen(default: &AppState, path: &std::path::Path) -> Result<World> {
+    let origin_file = path.join(WORLD_FILE);
+    let content = std::fs::read_to_string(&origin_file)
+        .with_context(|| format!("Failed to read {:?}", origin_file))?;
+    let origin = serde_json::from_str(&content).with_context(|| format!("Invalid {:?}", origin_file))?;
+    World::open(default, DataDir::new(path), origin)
+}
+
+fn validate_world_id(id: &str) -> ApiResult<()> {
+    let mut problems = Vec::new();
+    if id.is_empty() || id.len() > 64 {
+        problems.push("World id must be 1 to 64 characters long".to_string());
+    }
+    if !id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_') {
+        problems.push("World id may only contain a-z, 0-9, - and _".to_string());
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::Validation(problems))
+    }
+}
+
+pub fn routes(registry: Arc<WorldRegistry>) -> Router {
+    Router::new()
+        .route("/worlds", get(list_worlds).post(create_world))
+        .route("/worlds/{id}", get(get_world).delete(delete_world))
+        .route("/worlds/{id}/pause", post(pause_world))
+        .route("/worlds/{id}/resume", post(resume_world))
+        .route("/worlds/{id}/{*rest}", any(forward_to_world))
+        .with_state(registry)
+}
+
+async fn list_worlds(State(registry): State<Arc<WorldRegistry>>) -> Json<Vec<WorldSummary>> {
+    Json(registry.list())
+}
+
+async fn create_world(
+    State(registry): State<Arc<WorldRegistry>>,
+    Json(request): Json<CreateWorldRequest>,
+) -> ApiResult<(StatusCode, Json<WorldSummary>)> {
+    Ok((StatusCode::CREATED, Json(registry.create(request).await?)))
+}
+
+async fn get_world(
+    State(registry): State<Arc<WorldRegistry>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<WorldSummary>> {
+    Ok(Json(registry.get(&id)?.summary(&id)))
+}
+
+async fn delete_world(
+    State(registry): State<Arc<WorldRegistry>>,
+    Path(id): Path<String>,
+) -> ApiResult<StatusCode> {
+    registry.delete(&id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Running turns finish; the next one is refused until the world is resumed
+async fn pause_world(
+    State(registry): State<Arc<WorldRegistry>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<WorldSummary>> {
+    let world = registry.get(&id)?;
+    world.state.game_manager.pause();
+    log::info!("⏸️  [Worlds][{}] Paused", id);
+    Ok(Json(world.summary(&id)))
+}
+
+async fn resume_world(
+    State(registry): State<Arc<WorldRegistry>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<WorldSummary>> {
+    let world = registry.get(&id)?;
+    world.state.game_manager.resume();
+    log::info!("▶️  [Worlds][{}] Resumed", id);
+    Ok(Json(world.summary(&id)))
+}
+
+/// Serve `/worlds/{id}/...` with the world's own routes, exactly as the default
+/// world serves them at the top level
+async fn forward_to_world(
+    State(registry): State<Arc<WorldRegistry>>,
+    Path((id, rest)): Path<(String, String)>,
+    mut request: Request,
+) -> ApiResult<Response> {
+    let world = registry.get(&id)?;
+    let uri = match request.uri().query() {
+        Some(query) => format!("/{rest}?{query}"),
+        None => format!("/{rest}"),
+    };
+    *request.uri_mut() = uri
+        .parse()
+        .map_err(|_| ApiError::BadRequest(format!("Invalid path: {uri}")))?;
+
+    let response = world.router.oneshot(request).await;
+    Ok(response.unwrap_or_else(|never| match never {}))
+}
@@ -1,11 +1,11 @@
-use server::{llm::{ClaudeClient, OllamaClient}, LlmClient};
+use server::{llm::{ClaudeClient, LlmOptions, OllamaClient}, LlmClient};
 use std::sync::Arc;
 
 #[tokio::main]
 async fn main() {
     // Load .env file
     dotenv::dotenv().ok();
-    env_logger::init();
+    server::logging::init_logger(std::env::var("RUST_LOG").ok().as_deref(), Default::default());
     
     println!("Testing LLM client directly...");
     
@@ -35,12 +35,13 @@ async fn main() {
     // Test simple text response
     println!("\n1. Testing simple text response...");
     let result = client.query(
-        "Just respond with: hello".to_string(),
-        std::path::Path::new(".")
+        "Just respond with: hello".into(),
+        std::path::Path::new("."),
+        &LlmOptions::default()
     ).await;
     
     match result {
-        Ok(response) => println!("✅ Success! Response: {}", response),
+        Ok(response) => println!("✅ Success! Response: {}", response.text),
         Err(e) => println!("❌ Error: {}", e),
     }
     
@@ -56,12 +57,14 @@ async fn main() {
     };
     
     let result = client.query(
-        json_prompt.to_string(),
-        std::path::Path::new(".")
+        json_prompt.into(),
+        std::path::Path::new("."),
+        &LlmOptions::default()
     ).await;
     
     match result {
         Ok(response) => {
+            let response = response.text;
             println!("✅ Raw response: {}", response);
             // Try to parse as JSON
             match serde_json::from_str::<serde_json::Value>(&response) {
@@ -1,11 +1,11 @@
-use server::{llm::OllamaClient, LlmClient, GmResponse};
+use server::{llm::{LlmOptions, OllamaClient}, LlmClient, GmResponse};
 use std::path::Path;
 
 #[tokio::main]
 async fn main() {
     // Load .env file
     dotenv::dotenv().ok();
-    env_logger::init();
+    server::logging::init_logger(std::env::var("RUST_LOG").ok().as_deref(), Default::default());
     
     println!("Testing Ollama with actual game prompts...\n");
     
@@ -71,8 +71,10 @@ IMPORTANT: For dialogue fields, use null (not "null" or "None") when the charact
 
     println!("Sending game-like prompt to Ollama...\n");
     
-    match client.query(game_prompt.to_string(), Path::new("/tmp")).await {
+    match client.query(game_prompt.into(), Path::new("/tmp"), &LlmOptions::default()).await {
         Ok(response) => {
+            println!("Usage: {:?}\n", response.usage);
+            let response = response.text;
             println!("Raw response:\n{}\n", response);
             
             // Try to parse as JSON
@@ -85,13 +87,10 @@ IMPORTANT: For dialogue fields, use null (not "null" or "None") when the charact
                         for (i, contract) in contracts.iter().enumerate() {
                             if let Some(details) = contract["transcript_entry"]["details"].as_object() {
                                 for (npc, action) in details {
-                                    if let Some(dialogue) = action.get("dialogue") {
-                                        if dialogue.is_string() {
-                                            let dialogue_str = dialogue.as_str().unwrap();
-                                            if dialogue_str == "None" || dialogue_str == "null" {
-                                                println!("⚠️  Contract {} NPC {}: dialogue should be null, not \"{}\"", i, npc, dialogue_str);
-                                            }
-                                        }
+                                    if let Some(dialogue_str) = action.get("dialogue").and_then(|d| d.as_str())
+                                        && (dialogue_str == "None" || dialogue_str == "null")
+                                    {
+                                        println!("⚠️  Contract {} NPC {}: dialogue should be null, not \"{}\"", i, npc, dialogue_str);
                                     }
                                 }
                             }
@@ -0,0 +1,496 @@
+use anyhow::{bail, Context, Result};
+use crate::llm::{role_defaults, LlmOptions, LlmRole};
+use crate::game::turn::TurnMode;
+use crate::logging::LogFormat;
+use crate::npcs::ContractResponses;
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// Read from the directory the server is started in, if it exists
+pub const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+pub const PROVIDERS: [&str; 2] = ["claude", "ollama"];
+
+/// Everything the server can be configured with. Built in layers: defaults, then
+/// the TOML config file, then environment variables, then command line flags.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub llm: LlmConfig,
+    pub memory: MemoryPolicy,
+    pub turn: TurnConfig,
+    pub paths: PathsConfig,
+    pub logging: LoggingConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmConfig {
+    pub provider: Option<String>,  // "claude" or "ollama"
+    pub model: Option<String>,     // Provider default if unset
+    pub ollama: OllamaConfig,
+    pub prices: BTreeMap<String, ModelPrice>,  // Keyed by model name, for usage costs
+    pub limits: BTreeMap<String, LlmLimits>,   // Keyed by provider, defaults if missing
+    pub cache: LlmCacheConfig,
+    pub sampling: SamplingConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OllamaConfig {
+    pub url: String,
+    pub top_p: f32,  // For calls [llm.sampling] leaves it unset for
+    pub timeout_secs: u64,
+}
+
+/// What a model charges, in US dollars per million tokens
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelPrice {
+    pub prompt_per_million: f64,
+    pub completion_per_million: f64,
+}
+
+/// How hard a provider may be pushed. Every world shares the same limits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmLimits {
+    pub max_in_flight: usize,              // Calls sent and not yet answered
+    pub requests_per_minute: Option<u32>,  // Unlimited if unset
+    pub tokens_per_minute: Option<u64>,    // Prompt and completion tokens together
+}
+
+/// How each kind of call is sampled, over the built-in role defaults. The most
+/// specific setting wins: an NPC's own options, then its role's, then `seed`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SamplingConfig {
+    pub seed: Option<u64>,  // Every call, for reproducible runs where the provider allows
+    pub gm: LlmOptions,     // Resolution and repairs
+    pub intent: LlmOptions,
+    pub memory: LlmOptions,
+    pub npcs: BTreeMap<String, LlmOptions>,  // An NPC's intents, over `intent`
+}
+
+/// Replaying identical prompts from disk, for dry runs and prompt iteration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmCacheConfig {
+    pub enabled: bool,
+    pub dir: PathBuf,
+    pub ttl_secs: u64,     // Responses older than this are asked for again
+    pub max_size_mb: u64,  // The oldest responses go first once the cache is bigger
+}
+
+/// How much NPCs remember and how many remember at once
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MemoryPolicy {
+    pub max_recent_events: usize,          // Personal events kept before the oldest fades
+    pub max_relationship_memories: usize,  // Memories kept per relationship, pinned ones aside
+    pub max_concurrent_updates: usize,     // Memory updates waiting on the LLM at the same time
+    pub max_memory_versions: usize,        // Saved versions kept per NPC before the oldest are pruned
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TurnConfig {
+    pub duration_minutes: u32,
+    pub player_intent_timeout_secs: u64,
+    pub mode: TurnMode,  // Whether NPCs start their next intent while others' memories update
+    pub contract_responses: ContractResponses,  // Whether NPCs in an interaction take turns answering
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathsConfig {
+    pub data_dir: PathBuf,
+    pub worlds_dir: PathBuf,  // Where worlds created through the API keep their data
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    pub level: Option<String>,  // A RUST_LOG style filter, e.g. "server=debug"
+    pub format: LogFormat,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0:3000".to_string(),
+        }
+    }
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:11434".to_string(),
+            top_p: 0.9,
+            timeout_secs: 60,
+        }
+    }
+}
+
+impl Default for LlmLimits {
+    fn default() -> Self {
+        Self {
+            max_in_flight: 4,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+        }
+    }
+}
+
+impl Default for LlmCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: PathBuf::from("../llm-cache"),
+            ttl_secs: 24 * 60 * 60,
+            max_size_mb: 100,
+        }
+    }
+}
+
+impl SamplingConfig {
+    /// The options for one call made in `role`, for `npc` if it's an NPC's own call
+    pub fn options_for(&self, role: LlmRole, npc: Option<&str>) -> LlmOptions {
+        let configured = match role {
+            LlmRole::Gm | LlmRole::GmRepair => &self.gm,
+            LlmRole::Intent => &self.intent,
+            LlmRole::Memory => &self.memory,
+        };
+        let npc_options = match (role, npc) {
+            (LlmRole::Intent, Some(npc)) => self.npcs.get(npc),
+            _ => None,
+        };
+
+        let seeded = LlmOptions {
+            seed: self.seed,
+            ..LlmOptions::default()
+        };
+        let options = configured.or(&role_defaults(role)).or(&seeded);
+        match npc_options {
+            Some(npc_options) => npc_options.or(&options),
+            None => options,
+        }
+    }
+}
+
+impl LlmConfig {
+    /// The limits for `provider`, or the defaults if it has none configured
+    pub fn limits_for(&self, provider: &str) -> LlmLimits {
+        self.limits.get(provider).cloned().unwrap_or_default()
+    }
+}
+
+impl Default for MemoryPolicy {
+    fn default() -> Self {
+        Self {
+            max_recent_events: 10,
+            max_relationship_memories: 10,
+            max_concurrent_updates: 4,
+            max_memory_versions: 100,
+        }
+    }
+}
+
+impl Default for TurnConfig {
+    fn default() -> Self {
+        Self {
+            duration_minutes: crate::game::clock::DEFAULT_TURN_MINUTES,
+            player_intent_timeout_secs: crate::game::player::DEFAULT_PLAYER_INTENT_TIMEOUT.as_secs(),
+            mode: TurnMode::default(),
+            contract_responses: ContractResponses::default(),
+        }
+    }
+}
+
+impl Default for PathsConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("../data"),
+            worlds_dir: PathBuf::from("../worlds"),
+        }
+    }
+}
+
+/// Command line flags, each of which can also be set through the environment variable
+/// named next to it. Anything set here wins over the config file.
+#[derive(Debug, Default, Parser)]
+#[command(name = "server", about = "The Two Animals game server")]
+pub struct Overrides {
+    /// Config file to read (defaults to ./config.toml if it exists)
+    #[arg(long, env = "CONFIG_FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Address to listen on, e.g. 0.0.0.0:3000
+    #[arg(long, env = "BIND_ADDR")]
+    pub bind: Option<String>,
+
+    /// LLM provider: claude or ollama
+    #[arg(long, env = "LLM_PROVIDER")]
+    pub provider: Option<String>,
+
+    /// Model to ask the provider for
+    #[arg(long, env = "LLM_MODEL")]
+    pub model: Option<String>,
+
+    /// Where Ollama is listening
+    #[arg(long, env = "OLLAMA_URL")]
+    pub ollama_url: Option<String>,
+
+    /// Answer prompts seen before from the response cache: true or false
+    #[arg(long, env = "LLM_CACHE")]
+    pub llm_cache: Option<bool>,
+
+    /// Directory with prompts, NPCs, contracts and everything else the world keeps
+    #[arg(long, env = "DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// In-world minutes that pass each turn
+    #[arg(long, env = "TURN_DURATION_MINUTES")]
+    pub turn_minutes: Option<u32>,
+
+    /// How long each turn waits for the player's intent
+    #[arg(long, env = "PLAYER_INTENT_TIMEOUT_SECS")]
+    pub player_timeout_secs: Option<u64>,
+
+    /// How runs of turns handle memory updates: pipelined or strict
+    #[arg(long, env = "TURN_MODE", value_enum)]
+    pub turn_mode: Option<TurnMode>,
+
+    /// Directory that holds one data directory per extra world
+    #[arg(long, env = "WORLDS_DIR")]
+    pub worlds_dir: Option<PathBuf>,
+
+    /// Log filter, e.g. "server=debug"
+    #[arg(long, env = "RUST_LOG")]
+    pub log_level: Option<String>,
+
+    /// Log output: human or json
+    #[arg(long, env = "LOG_FORMAT", value_enum)]
+    pub log_format: Option<LogFormat>,
+}
+
+impl Config {
+    /// Build the configuration from the config file and overrides, and check it
+    pub fn load(overrides: &Overrides) -> Result<Self> {
+        let config = match &overrides.config {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Self::from_file(Path::new(DEFAULT_CONFIG_FILE))?,
+            None => Self::default(),
+        };
+        let config = config.with_overrides(overrides);
+
+        let problems = config.validate();
+        if !problems.is_empty() {
+            bail!("Invalid configuration:\n  - {}", problems.join("\n  - "));
+        }
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {:?}", path))?;
+        Self::from_toml(&content).with_context(|| format!("Invalid config file {:?}", path))
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn with_overrides(mut self, overrides: &Overrides) -> Self {
+        if let Some(bind) = &overrides.bind {
+            self.server.bind = bind.clone();
+        }
+        if let Some(provider) = &overrides.provider {
+            self.llm.provider = Some(provider.clone());
+        }
+        if let Some(model) = &overrides.model {
+            self.llm.model = Some(model.clone());
+        }
+        if let Some(enabled) = overrides.llm_cache {
+            self.llm.cache.enabled = enabled;
+        }
+        if let Some(url) = &overrides.ollama_url {
+            self.llm.ollama.url = url.clone();
+        }
+        if let Some(data_dir) = &overrides.data_dir {
+            self.paths.data_dir = data_dir.clone();
+        }
+        if let Some(worlds_dir) = &overrides.worlds_dir {
+            self.paths.worlds_dir = worlds_dir.clone();
+        }
+        if let Some(minutes) = overrides.turn_minutes {
+            self.turn.duration_minutes = minutes;
+        }
+        if let Some(secs) = overrides.player_timeout_secs {
+            self.turn.player_intent_timeout_secs = secs;
+        }
+        if let Some(mode) = overrides.turn_mode {
+            self.turn.mode = mode;
+        }
+        if let Some(level) = &overrides.log_level {
+            self.logging.level = Some(level.clone());
+        }
+        if let Some(format) = overrides.log_format {
+            self.logging.format = format;
+        }
+        self
+    }
+
+    /// Every problem with the configuration, so they can all be fixed in one go
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.server.bind.parse::<SocketAddr>().is_err() {
+            problems.push(format!("server.bind: \"{}\" is not an address like 0.0.0.0:3000", self.server.bind));
+        }
+
+        match self.llm.provider.as_deref() {
+            None => problems.push(format!(
+                "llm.provider: no LLM provider configured, choose one of {} (or run ./bin/setup-llm.sh)",
+                PROVIDERS.join(", ")
+            )),
+            Some(provider) if !PROVIDERS.contains(&provider) => problems.push(format!(
+                "llm.provider: unknown provider \"{provider}\", choose one of {}",
+                PROVIDERS.join(", ")
+            )),
+            Some(_) => {}
+        }
+
+        let ollama = &self.llm.ollama;
+        if !ollama.url.starts_with("http://") && !ollama.url.starts_with("https://") {
+            problems.push(format!("llm.ollama.url: \"{}\" must start with http:// or https://", ollama.url));
+        }
+        if !(ollama.top_p > 0.0 && ollama.top_p <= 1.0) {
+            problems.push(format!("llm.ollama.top_p: {} is outside 0.0 (exclusive) to 1.0", ollama.top_p));
+        }
+        if ollama.timeout_secs == 0 {
+            problems.push("llm.ollama.timeout_secs: must be at least 1".to_string());
+        }
+
+        for (model, price) in &self.llm.prices {
+            if price.prompt_per_million < 0.0 || price.completion_per_million < 0.0 {
+                problems.push(format!("llm.prices.\"{model}\": prices can't be negative"));
+            }
+        }
+
+        for (provider, limits) in &self.llm.limits {
+            if !PROVIDERS.contains(&provider.as_str()) {
+                problems.push(format!(
+                    "llm.limits.{provider}: unknown provider, choose one of {}",
+                    PROVIDERS.join(", ")
+                ));
+            }
+            if limits.max_in_flight == 0 {
+                problems.push(format!("llm.limits.{provider}.max_in_flight: must be at least 1"));
+            }
+            if limits.requests_per_minute == Some(0) {
+                problems.push(format!("llm.limits.{provider}.requests_per_minute: must be at least 1, or unset"));
+            }
+            if limits.tokens_per_minute == Some(0) {
+                problems.push(format!("llm.limits.{provider}.tokens_per_minute: must be at least 1, or unset"));
+            }
+        }
+
+        let sampling = &self.llm.sampling;
+        problems.extend(sampling.gm.validate("llm.sampling.gm"));
+        problems.extend(sampling.intent.validate("llm.sampling.intent"));
+        problems.extend(sampling.memory.validate("llm.sampling.memory"));
+        for (npc, options) in &sampling.npcs {
+            problems.extend(options.validate(&format!("llm.sampling.npcs.{npc}")));
+        }
+
+        let cache = &self.llm.cache;
+        if cache.ttl_secs == 0 {
+            problems.push("llm.cache.ttl_secs: must be at least 1".to_string());
+        }
+        if cache.max_size_mb == 0 {
+            problems.push("llm.cache.max_size_mb: must be at least 1".to_string());
+        }
+        if cache.enabled && cache.dir.exists() && !cache.dir.is_dir() {
+            problems.push(format!("llm.cache.dir: {:?} is not a directory", cache.dir));
+        }
+
+        for (key, value) in [
+            ("memory.max_recent_events", self.memory.max_recent_events),
+            ("memory.max_relationship_memories", self.memory.max_relationship_memories),
+            ("memory.max_concurrent_updates", self.memory.max_concurrent_updates),
+            ("memory.max_memory_versions", self.memory.max_memory_versions),
+        ] {
+            if value == 0 {
+                problems.push(format!("{key}: must be at least 1"));
+            }
+        }
+
+        if self.turn.duration_minutes == 0 {
+            problems.push("turn.duration_minutes: must be at least 1".to_string());
+        }
+
+        if !self.paths.data_dir.is_dir() {
+            problems.push(format!("paths.data_dir: {:?} is not a directory", self.paths.data_dir));
+        }
+        if self.paths.worlds_dir.exists() && !self.paths.worlds_dir.is_dir() {
+            problems.push(format!("paths.worlds_dir: {:?} is not a directory", self.paths.worlds_dir));
+        }
+        // New worlds are copied from the data folder, so a worlds folder inside it would copy into itself
+        if resolved(&self.paths.worlds_dir).starts_with(resolved(&self.paths.data_dir)) {
+            problems.push(format!(
+                "paths.worlds_dir: {:?} is inside paths.data_dir {:?}",
+                self.paths.worlds_dir, self.paths.data_dir
+            ));
+        }
+
+        if let Some(level) = &self.logging.level
+            && let Err(e) = tracing_subscriber::EnvFilter::try_new(level)
+        {
+            problems.push(format!("logging.level: \"{level}\" is not a valid filter: {e}"));
+        }
+
+        problems
+    }
+}
+
+/// `path` made absolute with `.` and `..` worked out, and symlinks followed as far
+/// as it exists
+fn resolved(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut lexical = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                lexical.pop();
+            }
+            other => lexical.push(other),
+        }
+    }
+    let mut missing = Vec::new();
+    let mut existing = lexical.as_path();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return missing.iter().rev().fold(canonical, |path, name| path.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return lexical,
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::{Path, PathBuf};
+
+/// What a world writes while it runs, as opposed to what it is designed with.
+/// Left behind when a data directory is used as a template.
+const RUNTIME_ENTRIES: [&str; 5] = ["contracts", "rumors", "memory_history", "memories.json", "knowledge.json"];
+
+/// The root of everything a world keeps on disk: prompts, NPC folders with their
+/// memories and knowledge, contract transcripts, rumors and the event schedule.
+/// Every path into the data folder is built from here.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataDir {
+    root: PathBuf,
+}
+
+impl DataDir {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn prompts_dir(&self) -> PathBuf {
+        self.root.join("prompts")
+    }
+
+    pub fn npc_dir(&self, npc_name: &str) -> PathBuf {
+        self.root.join("npcs").join(npc_name)
+    }
+
+    pub fn contracts_dir(&self) -> PathBuf {
+        self.root.join("contracts")
+    }
+
+    pub fn contract_path(&self, contract_id: &str) -> PathBuf {
+        self.contracts_dir().join(format!("{contract_id}.json"))
+    }
+
+    pub fn rumors_dir(&self) -> PathBuf {
+        self.root.join("rumors")
+    }
+
+    pub fn world_events_path(&self) -> PathBuf {
+        self.root.join("world_events.json")
+    }
+
+    /// Every NPC with a folder here, in name order
+    pub fn npc_names(&self) -> std::io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(self.root.join("npcs"))? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Copy this world's design (prompts, personalities, initial memories, the event
+    /// schedule) into `target`, leaving out everything it has accumulated at runtime
+    pub fn copy_template_to(&self, target: &Path) -> std::io::Result<DataDir> {
+        copy_dir(&self.root, target)?;
+        Ok(DataDir::new(target))
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if RUNTIME_ENTRIES.iter().any(|runtime| name == *runtime) {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &to.join(&name))?;
+        } else {
+            std::fs::copy(entry.path(), to.join(&name))?;
+        }
+    }
+    Ok(())
+}
+
+/// `../data`, where the data folder sits when the server is started from `server/`
+impl Default for DataDir {
+    fn default() -> Self {
+        Self::new("../data")
+    }
+}
+
+impl From<PathBuf> for DataDir {
+    fn from(root: PathBuf) -> Self {
+        Self::new(root)
+    }
+}
+
+impl From<&Path> for DataDir {
+    fn from(root: &Path) -> Self {
+        Self::new(root)
+    }
+}
@@ -0,0 +1,138 @@
+use chrono::{DateTime, TimeZone, Timelike, Utc};
+use serde::Serialize;
+
+/// In-world minutes that pass with each turn unless configured otherwise
+pub const DEFAULT_TURN_MINUTES: u32 = 30;
+
+/// Chance per in-world hour that the weather turns
+const WEATHER_CHANGE_PER_HOUR: f64 = 0.15;
+
+/// In-world time, time of day and weather. Advanced once per turn, and the
+/// only source of truth for when things happened.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorldClock {
+    pub time: DateTime<Utc>,
+    pub turn_duration_minutes: u32,
+    pub time_of_day: TimeOfDay,
+    pub weather: Weather,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeOfDay {
+    Dawn,
+    Morning,
+    Afternoon,
+    Dusk,
+    Night,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Weather {
+    Clear,
+    Cloudy,
+    Fog,
+    Rain,
+    Storm,
+}
+
+impl TimeOfDay {
+    pub fn at_hour(hour: u32) -> Self {
+        match hour {
+            5..=6 => Self::Dawn,
+            7..=11 => Self::Morning,
+            12..=16 => Self::Afternoon,
+            17..=19 => Self::Dusk,
+            _ => Self::Night,
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::Dawn => "dawn, the sky turning pale",
+            Self::Morning => "morning",
+            Self::Afternoon => "afternoon",
+            Self::Dusk => "dusk, the light fading",
+            Self::Night => "night, dark under the trees",
+        }
+    }
+}
+
+impl Weather {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::Clear => "the sky is clear",
+            Self::Cloudy => "clouds hang overhead",
+            Self::Fog => "fog clings to the ground",
+            Self::Rain => "rain is falling",
+            Self::Storm => "a storm is raging",
+        }
+    }
+
+    /// Where the weather goes when it turns, picked by `roll` in 0.0..1.0
+    fn turn(self, roll: f64) -> Self {
+        let options: &[(Weather, f64)] = match self {
+            Self::Clear => &[(Self::Cloudy, 0.7), (Self::Fog, 0.3)],
+            Self::Cloudy => &[(Self::Clear, 0.5), (Self::Rain, 0.4), (Self::Fog, 0.1)],
+            Self::Fog => &[(Self::Clear, 0.6), (Self::Cloudy, 0.4)],
+            Self::Rain => &[(Self::Cloudy, 0.6), (Self::Storm, 0.3), (Self::Clear, 0.1)],
+            Self::Storm => &[(Self::Rain, 0.8), (Self::Cloudy, 0.2)],
+        };
+
+        let mut cumulative = 0.0;
+        for (weather, chance) in options {
+            cumulative += chance;
+            if roll < cumulative {
+                return *weather;
+            }
+        }
+        options[options.len() - 1].0
+    }
+}
+
+impl WorldClock {
+    /// The world starts at dawn on a clear spring day
+    pub fn new(turn_duration_minutes: u32) -> Self {
+        let time = Utc.with_ymd_and_hms(2025, 4, 1, 6, 0, 0).unwrap();
+        Self {
+            time,
+            turn_duration_minutes,
+            time_of_day: TimeOfDay::at_hour(time.hour()),
+            weather: Weather::Clear,
+        }
+    }
+
+    /// Move time forward by one turn and let the weather drift. The weather only
+    /// depends on the turn number, so replaying a world replays its weather.
+    pub fn advance(&mut self, turn: u64) {
+        self.time += chrono::Duration::minutes(self.turn_duration_minutes as i64);
+        self.time_of_day = TimeOfDay::at_hour(self.time.hour());
+
+        let hours = self.turn_duration_minutes as f64 / 60.0;
+        let change_chance = (WEATHER_CHANGE_PER_HOUR * hours).min(1.0);
+        if roll(turn, 0) < change_chance {
+            self.weather = self.weather.turn(roll(turn, 1));
+        }
+    }
+
+    /// "It is morning and rain is falling."
+    pub fn describe(&self) -> String {
+        format!("It is {} and {}.", self.time_of_day.describe(), self.weather.describe())
+    }
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self::new(DEFAULT_TURN_MINUTES)
+    }
+}
+
+/// Deterministic number in 0.0..1.0 for a turn (splitmix64)
+fn roll(turn: u64, salt: u64) -> f64 {
+    let mut z = turn.wrapping_mul(2).wrapping_add(salt).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -1,20 +1,41 @@
+use crate::data_dir::DataDir;
+use crate::game::transaction::write_file;
 use crate::types::{Contract, TranscriptEntry};
 use anyhow::Result;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-pub struct ContractManager;
+/// Tells apart contracts created within the same millisecond
+static CONTRACT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Reads and writes contract transcripts under a world's data directory
+pub struct ContractManager {
+    data_dir: DataDir,
+}
 
 impl ContractManager {
+    pub fn new(data_dir: DataDir) -> Self {
+        Self { data_dir }
+    }
+
     pub fn create_contract(
+        &self,
         participants: Vec<String>,
         initial_entry: Option<TranscriptEntry>,
     ) -> Result<Contract> {
         let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S_%3f").to_string();
-        let contract_id = format!("conv_{timestamp}");
+        let contract_id = loop {
+            let sequence = CONTRACT_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+            let id = format!("conv_{timestamp}_{sequence}");
+            // Transcripts outlive their contracts, and another server may share the directory
+            if !self.data_dir.contract_path(&id).exists() {
+                break id;
+            }
+        };
         
         let contract = Contract {
             id: contract_id.clone(),
             participants,
-            transcript_file: format!("../data/contracts/{contract_id}.json"),
+            transcript_file: self.data_dir.contract_path(&contract_id).to_string_lossy().to_string(),
         };
         
         // Create contract file with initial entry if provided
@@ -23,15 +44,16 @@ impl ContractManager {
             let json = serde_json::to_string_pretty(&transcript)?;
             
             // Create contracts directory if it doesn't exist
-            std::fs::create_dir_all("../data/contracts").ok();
+            std::fs::create_dir_all(self.data_dir.contracts_dir()).ok();
             
-            std::fs::write(&contract.transcript_file, json)?;
+            write_file(&contract.transcript_file, json)?;
         }
         
         Ok(contract)
     }
     
     pub fn update_contract(
+        &self,
         contract: &Contract,
         entry: TranscriptEntry,
     ) -> Result<()> {
@@ -46,15 +68,15 @@ impl ContractManager {
         
         // Write back
         let json = serde_json::to_string_pretty(&transcript)?;
-        std::fs::write(&contract.transcript_file, json)?;
+        std::fs::create_dir_all(self.data_dir.contracts_dir()).ok();
+        write_file(&contract.transcript_file, json)?;
         
         Ok(())
     }
     
-    pub fn read_contract_transcript(contract_id: &str) -> Result<Vec<TranscriptEntry>> {
-        let contract_path = format!("../data/contracts/{contract_id}.json");
-        let contents = std::fs::read_to_string(&contract_path)?;
+    pub fn read_contract_transcript(&self, contract_id: &str) -> Result<Vec<TranscriptEntry>> {
+        let contents = std::fs::read_to_string(self.data_dir.contract_path(contract_id))?;
         let transcript: Vec<TranscriptEntry> = serde_json::from_str(&contents)?;
         Ok(transcript)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,216 @@
+use crate::data_dir::DataDir;
+use crate::game::GameStateManager;
+use crate::npcs::memory_store::load_npc_memories;
+use crate::types::{GameState, Location};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// Something that happens in the world regardless of what the NPCs intend.
+/// The GM must treat it as fact when resolving the turn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorldEvent {
+    pub id: String,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,  // Where it happens, or None for the whole world
+}
+
+/// An entry of the designer-written schedule in `data/world_events.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledEvent {
+    #[serde(flatten)]
+    pub event: WorldEvent,
+    #[serde(default)]
+    pub conditions: Vec<EventCondition>,  // All must hold for the event to fire
+    #[serde(default = "default_once")]
+    pub once: bool,                       // False to fire every turn the conditions hold
+}
+
+fn default_once() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventCondition {
+    Turn {
+        #[serde(default)]
+        at: Option<u64>,
+        #[serde(default)]
+        from: Option<u64>,
+        #[serde(default)]
+        until: Option<u64>,
+        #[serde(default)]
+        every: Option<u64>,
+    },
+    /// How many creatures (NPCs and the player) are at a location
+    Occupancy {
+        location: Location,
+        #[serde(default)]
+        min: Option<usize>,
+        #[serde(default)]
+        max: Option<usize>,
+    },
+    /// How `npc` feels about `other`, from their relationship memory
+    Relationship {
+        npc: String,
+        other: String,
+        #[serde(default)]
+        min_bond: Option<f32>,
+        #[serde(default)]
+        max_bond: Option<f32>,
+        #[serde(default)]
+        min_sentiment: Option<f32>,
+        #[serde(default)]
+        max_sentiment: Option<f32>,
+    },
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventSchedule {
+    pub events: Vec<ScheduledEvent>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FiredEvent {
+    pub turn: u64,
+    #[serde(flatten)]
+    pub event: WorldEvent,
+}
+
+/// Interventions waiting for the next turn and everything that has fired so far
+#[derive(Default)]
+pub struct WorldEvents {
+    inner: Mutex<WorldEventsInner>,
+}
+
+#[derive(Clone, Default)]
+struct WorldEventsInner {
+    pending: Vec<WorldEvent>,
+    fired_once: HashSet<String>,
+    history: Vec<FiredEvent>,
+    interventions: usize,
+}
+
+/// Everything the world events hold, kept to roll back a failed turn
+pub struct WorldEventsSnapshot(WorldEventsInner);
+
+#[derive(Debug, Serialize)]
+pub struct WorldEventsOverview {
+    pub schedule: Vec<ScheduledEvent>,
+    pub pending: Vec<WorldEvent>,
+    pub history: Vec<FiredEvent>,
+}
+
+/// Load the schedule fresh each time, so designers can edit it while the world runs
+pub fn load_schedule(data_dir: &DataDir) -> Result<EventSchedule> {
+    let path = data_dir.world_events_path();
+    if !path.exists() {
+        return Ok(EventSchedule::default());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    serde_json::from_str(&content).with_context(|| format!("Invalid world event schedule {:?}", path))
+}
+
+impl WorldEvents {
+    /// Queue a GM intervention for the next turn and return it with its id
+    pub fn intervene(&self, description: String, location: Option<Location>, turn: u64) -> WorldEvent {
+        let mut inner = self.inner.lock().unwrap();
+        inner.interventions += 1;
+        let event = WorldEvent {
+            id: format!("intervention_t{turn}_{}", inner.interventions),
+            description,
+            location,
+        };
+        inner.pending.push(event.clone());
+        event
+    }
+
+    pub fn snapshot(&self) -> WorldEventsSnapshot {
+        WorldEventsSnapshot(self.inner.lock().unwrap().clone())
+    }
+
+    pub fn restore(&self, snapshot: WorldEventsSnapshot) {
+        *self.inner.lock().unwrap() = snapshot.0;
+    }
+
+    pub fn overview(&self, data_dir: &DataDir) -> Result<WorldEventsOverview> {
+        let inner = self.inner.lock().unwrap();
+        Ok(WorldEventsOverview {
+            schedule: load_schedule(data_dir)?.events,
+            pending: inner.pending.clone(),
+            history: inner.history.clone(),
+        })
+    }
+}
+
+/// Every event that happens this turn: queued interventions first, then scheduled
+/// events whose conditions hold. Marks them as fired.
+pub fn take_due_events(game_manager: &GameStateManager) -> Vec<WorldEvent> {
+    let game_state = game_manager.get_state();
+    let schedule = load_schedule(&game_manager.data_dir).unwrap_or_else(|e| {
+        log::error!("Skipping world event schedule: {e:#}");
+        EventSchedule::default()
+    });
+
+    let mut inner = game_manager.world_events.inner.lock().unwrap();
+    let mut due: Vec<WorldEvent> = std::mem::take(&mut inner.pending);
+
+    for scheduled in schedule.events {
+        if scheduled.once && inner.fired_once.contains(&scheduled.event.id) {
+            continue;
+        }
+        if scheduled.conditions.iter().all(|c| condition_holds(c, &game_state, &game_manager.data_dir)) {
+            if scheduled.once {
+                inner.fired_once.insert(scheduled.event.id.clone());
+            }
+            due.push(scheduled.event);
+        }
+    }
+
+    for event in &due {
+        log::info!("🌩️ [World Event][{}] {}", event.id, event.description);
+        inner.history.push(FiredEvent {
+            turn: game_state.turn,
+            event: event.clone(),
+        });
+    }
+    due
+}
+
+fn condition_holds(condition: &EventCondition, game_state: &GameState, data_dir: &DataDir) -> bool {
+    let within = |value: f32, min: Option<f32>, max: Option<f32>| {
+        min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+    };
+
+    match condition {
+        EventCondition::Turn { at, from, until, every } => {
+            let turn = game_state.turn;
+            at.is_none_or(|at| turn == at)
+                && from.is_none_or(|from| turn >= from)
+                && until.is_none_or(|until| turn <= until)
+                && every.is_none_or(|every| every > 0 && turn.is_multiple_of(every))
+        }
+        EventCondition::Occupancy { location, min, max } => {
+            let npcs = game_state.npcs.values().filter(|npc| &npc.location == location).count();
+            let player = game_state.player.iter().filter(|p| &p.location == location).count();
+            let occupants = npcs + player;
+            min.is_none_or(|min| occupants >= min) && max.is_none_or(|max| occupants <= max)
+        }
+        EventCondition::Relationship { npc, other, min_bond, max_bond, min_sentiment, max_sentiment } => {
+            let memories = match load_npc_memories(data_dir, npc) {
+                Ok(memories) => memories,
+                Err(e) => {
+                    log::error!("Can't check relationship condition for {npc}: {e}");
+                    return false;
+                }
+            };
+            memories.relationships.get(other).is_some_and(|rel| {
+                within(rel.overall_bond, *min_bond, *max_bond)
+                    && within(rel.current_sentiment, *min_sentiment, *max_sentiment)
+            })
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// How many events a slow listener can fall behind before it misses some
+const FEED_CAPACITY: usize = 1024;
+
+/// Something happening in a turn, as it happens
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeedEvent {
+    TurnStarted { turn: u64 },
+    /// A new piece of an NPC's intent, while the LLM is still writing it
+    IntentDelta { turn: u64, npc: String, text: String },
+    IntentReady { turn: u64, npc: String, thought: String, action: String },
+    IntentFailed { turn: u64, npc: String, error: String },
+    TurnCompleted { turn: u64 },
+    TurnFailed { turn: u64, error: String },
+}
+
+impl FeedEvent {
+    /// The SSE event name
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::TurnStarted { .. } => "turn_started",
+            Self::IntentDelta { .. } => "intent_delta",
+            Self::IntentReady { .. } => "intent_ready",
+            Self::IntentFailed { .. } => "intent_failed",
+            Self::TurnCompleted { .. } => "turn_completed",
+            Self::TurnFailed { .. } => "turn_failed",
+        }
+    }
+}
+
+/// Live events of one world, for anyone listening. Nothing is kept for late listeners.
+pub struct Feed {
+    sender: broadcast::Sender<FeedEvent>,
+}
+
+impl Feed {
+    pub fn publish(&self, event: FeedEvent) {
+        // Nobody listening is fine
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<FeedEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for Feed {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(FEED_CAPACITY).0,
+        }
+    }
+}
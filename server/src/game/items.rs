@@ -0,0 +1,239 @@
+use crate::types::{GameState, Location};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A physical object in the world: a fish, a stone, a handful of berries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Item {
+    pub id: String,
+    pub kind: String,
+    pub description: String,
+    pub holder: ItemHolder,
+    pub state: String,  // e.g. "fresh", "half-eaten", "cracked"
+    #[serde(default)]
+    pub history: Vec<ItemEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ItemHolder {
+    Location { location: Location },
+    Carried { by: String },
+    Consumed,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemAction {
+    PickUp,
+    Drop,
+    Give,
+    Take,
+    Consume,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemEvent {
+    pub turn: u64,
+    pub action: ItemAction,
+    pub actor: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+/// An item changing hands, as reported by the GM
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemTransfer {
+    pub item: String,
+    pub action: ItemAction,
+    pub actor: String,
+    #[serde(default)]
+    pub target: Option<String>,     // Who receives a gift, or who an item is taken from
+    #[serde(default)]
+    pub new_state: Option<String>,  // e.g. "half-eaten"
+}
+
+/// A transfer the server refused because it contradicts the world
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedTransfer {
+    #[serde(flatten)]
+    pub transfer: ItemTransfer,
+    pub reason: String,
+}
+
+// What the GM sees of an item; the history stays on the server
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemSummary {
+    pub kind: String,
+    pub description: String,
+    pub holder: ItemHolder,
+    pub state: String,
+}
+
+impl Item {
+    fn new(id: &str, kind: &str, description: &str, location: Location, state: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            kind: kind.to_string(),
+            description: description.to_string(),
+            holder: ItemHolder::Location { location },
+            state: state.to_string(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn summary(&self) -> ItemSummary {
+        ItemSummary {
+            kind: self.kind.clone(),
+            description: self.description.clone(),
+            holder: self.holder.clone(),
+            state: self.state.clone(),
+        }
+    }
+
+    /// Describe the item the way it appears in NPC prompts
+    pub fn describe(&self) -> String {
+        format!("{} ({}, {})", self.description, self.id, self.state)
+    }
+}
+
+/// What the forest holds when the world starts
+pub fn initial_items() -> HashMap<String, Item> {
+    [
+        Item::new("fish_1", "fish", "A silver trout flapping in the shallows", Location::ForestClearing, "fresh"),
+        Item::new("stone_1", "stone", "A smooth, flat river stone", Location::ForestClearing, "dry"),
+        Item::new("berries_1", "berries", "A bush heavy with ripe blackberries", Location::DeepForest, "ripe"),
+    ]
+    .into_iter()
+    .map(|item| (item.id.clone(), item))
+    .collect()
+}
+
+/// Items the character is carrying
+pub fn carried_by<'a>(items: &'a HashMap<String, Item>, name: &str) -> Vec<&'a Item> {
+    let mut carried: Vec<&Item> = items
+        .values()
+        .filter(|item| matches!(&item.holder, ItemHolder::Carried { by } if by == name))
+        .collect();
+    carried.sort_by(|a, b| a.id.cmp(&b.id));
+    carried
+}
+
+/// Items lying around at a location
+pub fn lying_at<'a>(items: &'a HashMap<String, Item>, location: &Location) -> Vec<&'a Item> {
+    let mut lying: Vec<&Item> = items
+        .values()
+        .filter(|item| matches!(&item.holder, ItemHolder::Location { location: at } if at == location))
+        .collect();
+    lying.sort_by(|a, b| a.id.cmp(&b.id));
+    lying
+}
+
+/// Where an NPC or the player is
+fn location_of(state: &GameState, name: &str) -> Option<Location> {
+    state
+        .npcs
+        .get(name)
+        .map(|npc| npc.location.clone())
+        .or_else(|| state.player.as_ref().filter(|p| p.name == name).map(|p| p.location.clone()))
+}
+
+/// Two characters (or a character and a place) count as together if they were at the
+/// same location at the start or at the end of the turn, since the GM resolves movement
+/// and handling items in the same breath
+fn together(start: &GameState, end: &GameState, a: &str, b: &Location) -> bool {
+    [start, end].iter().any(|state| location_of(state, a).as_ref() == Some(b))
+}
+
+fn check_transfer(transfer: &ItemTransfer, start: &GameState, end: &GameState) -> Result<ItemHolder, String> {
+    let actor = transfer.actor.as_str();
+    if location_of(end, actor).is_none() {
+        return Err(format!("Unknown character {actor}"));
+    }
+    let item = end
+        .items
+        .get(&transfer.item)
+        .ok_or_else(|| format!("Unknown item {}", transfer.item))?;
+    let carried_by_actor = matches!(&item.holder, ItemHolder::Carried { by } if by == actor);
+
+    match transfer.action {
+        ItemAction::PickUp => match &item.holder {
+            ItemHolder::Location { location } if together(start, end, actor, location) => {
+                Ok(ItemHolder::Carried { by: actor.to_string() })
+            }
+            ItemHolder::Location { location } => Err(format!("{} is at {:?}, out of {actor}'s reach", item.id, location)),
+            _ => Err(format!("{} isn't lying anywhere to be picked up", item.id)),
+        },
+        ItemAction::Drop => {
+            if !carried_by_actor {
+                return Err(format!("{actor} isn't carrying {}", item.id));
+            }
+            let location = location_of(end, actor).unwrap_or(Location::ForestClearing);
+            Ok(ItemHolder::Location { location })
+        }
+        ItemAction::Give | ItemAction::Take => {
+            let target = transfer
+                .target
+                .as_deref()
+                .ok_or_else(|| "Giving or taking needs a target".to_string())?;
+            let target_location = location_of(end, target).ok_or_else(|| format!("Unknown character {target}"))?;
+            if !together(start, end, actor, &target_location) {
+                return Err(format!("{actor} and {target} aren't in the same place"));
+            }
+            let (from, to) = match transfer.action {
+                ItemAction::Give => (actor, target),
+                _ => (target, actor),
+            };
+            if !matches!(&item.holder, ItemHolder::Carried { by } if by == from) {
+                return Err(format!("{from} isn't carrying {}", item.id));
+            }
+            Ok(ItemHolder::Carried { by: to.to_string() })
+        }
+        ItemAction::Consume => match &item.holder {
+            ItemHolder::Carried { .. } if carried_by_actor => Ok(ItemHolder::Consumed),
+            ItemHolder::Location { location } if together(start, end, actor, location) => Ok(ItemHolder::Consumed),
+            ItemHolder::Consumed => Err(format!("{} is already gone", item.id)),
+            _ => Err(format!("{actor} can't reach {}", item.id)),
+        },
+    }
+}
+
+/// Apply the GM's item transfers in order, refusing any that contradict where
+/// characters and items actually are. `start` is the world before the turn.
+pub fn apply_transfers(
+    state: &mut GameState,
+    start: &GameState,
+    transfers: &[ItemTransfer],
+    turn: u64,
+) -> Vec<RejectedTransfer> {
+    let mut rejected = Vec::new();
+
+    for transfer in transfers {
+        match check_transfer(transfer, start, state) {
+            Ok(holder) => {
+                let target = transfer.target.as_deref().map(|t| format!(" → {}", t.to_uppercase())).unwrap_or_default();
+                log::info!("  🎒 [{}] {:?} {}{}", transfer.actor.to_uppercase(), transfer.action, transfer.item, target);
+                let item = state.items.get_mut(&transfer.item).expect("checked above");
+                item.holder = holder;
+                if let Some(new_state) = &transfer.new_state {
+                    item.state = new_state.clone();
+                }
+                item.history.push(ItemEvent {
+                    turn,
+                    action: transfer.action,
+                    actor: transfer.actor.clone(),
+                    target: transfer.target.clone(),
+                });
+            }
+            Err(reason) => {
+                log::warn!("⚠️  [Items] Refused {:?} of {} by {}: {}", transfer.action, transfer.item, transfer.actor, reason);
+                rejected.push(RejectedTransfer {
+                    transfer: transfer.clone(),
+                    reason,
+                });
+            }
+        }
+    }
+
+    rejected
+}
@@ -1,5 +1,11 @@
+pub mod clock;
 pub mod contracts;
+pub mod events;
+pub mod feed;
+pub mod items;
+pub mod player;
 pub mod state;
+pub mod transaction;
 pub mod turn;
 
-pub use state::GameStateManager;
\ No newline at end of file
+pub use state::{GameStateManager, WorldPaused};
\ No newline at end of file
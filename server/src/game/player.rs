@@ -0,0 +1,49 @@
+use crate::types::Intent;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// How long a turn waits for the player to act before resolving without them
+pub const DEFAULT_PLAYER_INTENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Hands the player's intent from the API over to the turn engine
+#[derive(Default)]
+pub struct PlayerInput {
+    pending: Mutex<Option<Intent>>,
+    submitted: Notify,
+}
+
+impl PlayerInput {
+    /// Queue the player's intent for the running or next turn, replacing any earlier one
+    pub fn submit(&self, intent: Intent) {
+        *self.pending.lock().unwrap() = Some(intent);
+        self.submitted.notify_one();
+    }
+
+    /// Hand back an intent a failed turn took, unless the player has already
+    /// submitted a newer one
+    pub fn put_back(&self, intent: Intent) {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.is_none() {
+            *pending = Some(intent);
+            self.submitted.notify_one();
+        }
+    }
+
+    pub fn take(&self) -> Option<Intent> {
+        self.pending.lock().unwrap().take()
+    }
+
+    /// Wait until the player has submitted an intent, giving up after `timeout`
+    pub async fn wait(&self, timeout: Duration) -> Option<Intent> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if let Some(intent) = self.take() {
+                return Some(intent);
+            }
+            if tokio::time::timeout_at(deadline, self.submitted.notified()).await.is_err() {
+                return self.take();
+            }
+        }
+    }
+}
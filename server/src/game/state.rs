@@ -1,13 +1,61 @@
-use crate::types::{Contract, GameState, Location, Npc};
-use std::collections::HashMap;
+use crate::config::{Config, MemoryPolicy, ModelPrice, SamplingConfig};
+use crate::data_dir::DataDir;
+use crate::game::clock::WorldClock;
+use crate::game::contracts::ContractManager;
+use crate::game::events::WorldEvents;
+use crate::game::feed::Feed;
+use crate::game::items::{initial_items, apply_transfers, ItemTransfer, RejectedTransfer};
+use crate::game::player::{PlayerInput, DEFAULT_PLAYER_INTENT_TIMEOUT};
+use crate::game::turn::TurnMode;
+use crate::llm::UsageLedger;
+use crate::npcs::ContractResponses;
+use crate::types::{Contract, GameState, Location, Npc, Observation, Player};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Error marker for a turn asked of a world that is paused
+#[derive(Debug, Clone)]
+pub struct WorldPaused;
+
+impl std::fmt::Display for WorldPaused {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The world is paused")
+    }
+}
+
+impl std::error::Error for WorldPaused {}
 
 pub struct GameStateManager {
     pub state: Arc<Mutex<GameState>>,
+    /// Held for the duration of a turn or any other write to NPC memories,
+    /// so edits made through the API never race with the memory phase
+    pub turn_lock: tokio::sync::Mutex<()>,
+    pub player_input: PlayerInput,
+    /// How long each turn waits for the player's intent once they've joined
+    pub player_intent_timeout: Duration,
+    pub world_events: WorldEvents,
+    /// Where this world keeps its NPC memories, contracts and everything else on disk
+    pub data_dir: DataDir,
+    pub memory_policy: MemoryPolicy,
+    /// Whether runs of turns overlap each turn's memory phase with the next
+    pub turn_mode: TurnMode,
+    /// Whether NPCs in an interaction answer one after another or all at once
+    pub contract_responses: ContractResponses,
+    /// How this world's LLM calls are sampled, by role and NPC
+    pub sampling: SamplingConfig,
+    /// What this world's LLM calls have cost so far
+    pub usage: UsageLedger,
+    /// What's happening in this world's turns, as it happens
+    pub feed: Feed,
+    /// A paused world refuses to run turns until it's resumed
+    paused: AtomicBool,
 }
 
 impl GameStateManager {
     pub fn new() -> Self {
+        let data_dir = DataDir::default();
         let mut npcs = HashMap::new();
         
         npcs.insert(
@@ -16,9 +64,10 @@ impl GameStateManager {
                 name: "bear".to_string(),
                 location: Location::ForestClearing,
                 activity: "resting".to_string(),
-                folder_path: "../data/npcs/bear".to_string(),
+                folder_path: data_dir.npc_dir("bear").to_string_lossy().to_string(),
                 active_contract: None,
                 next_prompt: None,
+                last_observation: None,
             },
         );
         
@@ -28,53 +77,231 @@ impl GameStateManager {
                 name: "wolf".to_string(),
                 location: Location::ForestClearing,
                 activity: "patrolling".to_string(),
-                folder_path: "../data/npcs/wolf".to_string(),
+                folder_path: data_dir.npc_dir("wolf").to_string_lossy().to_string(),
                 active_contract: None,
                 next_prompt: None,
+                last_observation: None,
             },
         );
         
         let contracts = HashMap::new();
-        let game_state = GameState { npcs, contracts };
+        let game_state = GameState {
+            turn: 0,
+            npcs,
+            contracts,
+            player: None,
+            items: initial_items(),
+            clock: WorldClock::default(),
+        };
         
         Self {
             state: Arc::new(Mutex::new(game_state)),
+            turn_lock: tokio::sync::Mutex::new(()),
+            player_input: PlayerInput::default(),
+            player_intent_timeout: DEFAULT_PLAYER_INTENT_TIMEOUT,
+            world_events: WorldEvents::default(),
+            data_dir,
+            memory_policy: MemoryPolicy::default(),
+            turn_mode: TurnMode::default(),
+            contract_responses: ContractResponses::default(),
+            sampling: SamplingConfig::default(),
+            usage: UsageLedger::default(),
+            feed: Feed::default(),
+            paused: AtomicBool::new(false),
+        }
+    }
+    
+    /// A world set up the way the configuration asks for
+    pub fn from_config(config: &Config) -> Self {
+        Self::new()
+            .with_data_dir(config.paths.data_dir.clone())
+            .with_turn_duration_minutes(config.turn.duration_minutes)
+            .with_player_intent_timeout(Duration::from_secs(config.turn.player_intent_timeout_secs))
+            .with_memory_policy(config.memory.clone())
+            .with_turn_mode(config.turn.mode)
+            .with_contract_responses(config.turn.contract_responses)
+            .with_usage_prices(config.llm.prices.clone())
+            .with_sampling(config.llm.sampling.clone())
+    }
+    
+    pub fn with_turn_duration_minutes(self, minutes: u32) -> Self {
+        self.state.lock().unwrap().clock = WorldClock::new(minutes);
+        self
+    }
+    
+    /// Keep this world's files under `data_dir` instead of `../data`
+    pub fn with_data_dir(mut self, data_dir: impl Into<DataDir>) -> Self {
+        self.data_dir = data_dir.into();
+        for npc in self.state.lock().unwrap().npcs.values_mut() {
+            npc.folder_path = self.data_dir.npc_dir(&npc.name).to_string_lossy().to_string();
         }
+        self
+    }
+    
+    /// Populate the world with exactly these NPCs. The bear and the wolf keep their
+    /// usual starting spots; anyone else starts out resting in the clearing.
+    pub fn with_npcs(self, names: &[String]) -> Self {
+        {
+            let mut game = self.state.lock().unwrap();
+            game.npcs.retain(|name, _| names.contains(name));
+            for name in names {
+                game.npcs.entry(name.clone()).or_insert_with(|| Npc {
+                    name: name.clone(),
+                    location: Location::ForestClearing,
+                    activity: "resting".to_string(),
+                    folder_path: self.data_dir.npc_dir(name).to_string_lossy().to_string(),
+                    active_contract: None,
+                    next_prompt: None,
+                    last_observation: None,
+                });
+            }
+        }
+        self
+    }
+    
+    pub fn with_player_intent_timeout(mut self, timeout: Duration) -> Self {
+        self.player_intent_timeout = timeout;
+        self
+    }
+    
+    pub fn with_memory_policy(mut self, policy: MemoryPolicy) -> Self {
+        self.memory_policy = policy;
+        self
+    }
+    
+    pub fn with_turn_mode(mut self, mode: TurnMode) -> Self {
+        self.turn_mode = mode;
+        self
+    }
+    
+    pub fn with_contract_responses(mut self, responses: ContractResponses) -> Self {
+        self.contract_responses = responses;
+        self
+    }
+    
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+    
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+    
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+    
+    pub fn with_usage_prices(mut self, prices: BTreeMap<String, ModelPrice>) -> Self {
+        self.usage = UsageLedger::new(prices);
+        self
+    }
+    
+    pub fn with_sampling(mut self, sampling: SamplingConfig) -> Self {
+        self.sampling = sampling;
+        self
+    }
+    
+    pub fn contract_manager(&self) -> ContractManager {
+        ContractManager::new(self.data_dir.clone())
     }
     
     pub fn get_state(&self) -> GameState {
         self.state.lock().unwrap().clone()
     }
     
-    pub fn update_npc_location(&self, npc_name: &str, location: Location, activity: String) {
+    pub fn current_turn(&self) -> u64 {
+        self.state.lock().unwrap().turn
+    }
+    
+    pub fn clock(&self) -> WorldClock {
+        self.state.lock().unwrap().clock.clone()
+    }
+    
+    /// Advance to the next turn, moving the world clock with it, and return its number
+    pub fn begin_turn(&self) -> u64 {
+        let mut game = self.state.lock().unwrap();
+        game.turn += 1;
+        let turn = game.turn;
+        game.clock.advance(turn);
+        turn
+    }
+    
+    pub fn player(&self) -> Option<Player> {
+        self.state.lock().unwrap().player.clone()
+    }
+    
+    pub fn set_player(&self, player: Player) {
+        self.state.lock().unwrap().player = Some(player);
+    }
+    
+    /// Apply a batch of changes to a staged copy of the game state, and swap it in
+    /// only if every change succeeded. Nobody ever sees the batch half-applied.
+    pub fn stage<T>(&self, apply: impl FnOnce(&mut GameState) -> anyhow::Result<T>) -> anyhow::Result<T> {
         let mut game = self.state.lock().unwrap();
-        if let Some(npc) = game.npcs.get_mut(npc_name) {
+        let mut staged = game.clone();
+        let result = apply(&mut staged)?;
+        *game = staged;
+        Ok(result)
+    }
+}
+
+// The setters below are keyed by name and apply to the player as well,
+// since the GM addresses the player exactly like any NPC
+impl GameState {
+    pub fn update_npc_location(&mut self, npc_name: &str, location: Location, activity: String) {
+        if let Some(npc) = self.npcs.get_mut(npc_name) {
             npc.location = location;
             npc.activity = activity;
+        } else if let Some(player) = self.player.as_mut().filter(|p| p.name == npc_name) {
+            player.location = location;
+            player.activity = activity;
         }
     }
     
-    pub fn set_npc_contract(&self, npc_name: &str, contract_id: Option<String>) {
-        let mut game = self.state.lock().unwrap();
-        if let Some(npc) = game.npcs.get_mut(npc_name) {
+    pub fn npc_contract(&self, npc_name: &str) -> Option<&String> {
+        match self.npcs.get(npc_name) {
+            Some(npc) => npc.active_contract.as_ref(),
+            None => self.player.as_ref().filter(|p| p.name == npc_name)?.active_contract.as_ref(),
+        }
+    }
+
+    pub fn set_npc_contract(&mut self, npc_name: &str, contract_id: Option<String>) {
+        if let Some(npc) = self.npcs.get_mut(npc_name) {
             npc.active_contract = contract_id;
+        } else if let Some(player) = self.player.as_mut().filter(|p| p.name == npc_name) {
+            player.active_contract = contract_id;
         }
     }
     
-    pub fn set_npc_prompt(&self, npc_name: &str, prompt: String) {
-        let mut game = self.state.lock().unwrap();
-        if let Some(npc) = game.npcs.get_mut(npc_name) {
+    pub fn set_npc_prompt(&mut self, npc_name: &str, prompt: String) {
+        if let Some(npc) = self.npcs.get_mut(npc_name) {
             npc.next_prompt = Some(prompt);
+        } else if let Some(player) = self.player.as_mut().filter(|p| p.name == npc_name) {
+            player.narration = Some(prompt);
         }
     }
     
-    pub fn add_contract(&self, contract: Contract) {
-        let mut game = self.state.lock().unwrap();
-        game.contracts.insert(contract.id.clone(), contract);
+    pub fn set_npc_observation(&mut self, npc_name: &str, observation: Observation) {
+        if let Some(npc) = self.npcs.get_mut(npc_name) {
+            npc.last_observation = Some(observation);
+        } else if let Some(player) = self.player.as_mut().filter(|p| p.name == npc_name) {
+            player.last_observation = Some(observation);
+        }
+    }
+    
+    /// Validate and apply item transfers against the world as it was at `start` and is now
+    pub fn apply_item_transfers(&mut self, start: &GameState, transfers: &[ItemTransfer]) -> Vec<RejectedTransfer> {
+        let turn = self.turn;
+        apply_transfers(self, start, transfers, turn)
     }
     
-    pub fn get_contract(&self, contract_id: &str) -> Option<Contract> {
-        let game = self.state.lock().unwrap();
-        game.contracts.get(contract_id).cloned()
+    pub fn add_contract(&mut self, contract: Contract) {
+        self.contracts.insert(contract.id.clone(), contract);
+    }
+}
+
+impl Default for GameStateManager {
+    fn default() -> Self {
+        Self::new()
     }
 }
\ No newline at end of file
@@ -0,0 +1,109 @@
+use crate::game::events::WorldEventsSnapshot;
+use crate::game::GameStateManager;
+use crate::types::GameState;
+use anyhow::Result;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+tokio::task_local! {
+    static JOURNAL: RefCell<FileJournal>;
+}
+
+/// What every file written during a transaction looked like before it was first
+/// touched, so a failed turn can put them all back
+#[derive(Default)]
+struct FileJournal {
+    originals: Vec<(PathBuf, Option<Vec<u8>>)>,  // None if the file didn't exist yet
+    seen: HashSet<PathBuf>,
+}
+
+impl FileJournal {
+    fn record(&mut self, path: &Path) {
+        if !self.seen.insert(path.to_path_buf()) {
+            return;
+        }
+        self.originals.push((path.to_path_buf(), std::fs::read(path).ok()));
+    }
+
+    fn restore(self) {
+        for (path, original) in self.originals.into_iter().rev() {
+            let result = match original {
+                Some(contents) => std::fs::write(&path, contents),
+                None => std::fs::remove_file(&path).or_else(|e| match e.kind() {
+                    std::io::ErrorKind::NotFound => Ok(()),
+                    _ => Err(e),
+                }),
+            };
+            if let Err(e) = result {
+                log::error!("Failed to restore {:?}: {e}", path);
+            }
+        }
+    }
+}
+
+/// Write a data file in one step (via a temporary file), journaling it first if a
+/// transaction is running. Every write to the data directory goes through here.
+pub fn write_file(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let _ = JOURNAL.try_with(|journal| journal.borrow_mut().record(path));
+
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    std::fs::write(&temp_path, contents)?;
+    std::fs::rename(&temp_path, path)
+}
+
+/// Delete a data file, journaling it first if a transaction is running so a
+/// rollback brings it back
+pub fn remove_file(path: impl AsRef<Path>) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let _ = JOURNAL.try_with(|journal| journal.borrow_mut().record(path));
+    std::fs::remove_file(path)
+}
+
+/// The in-memory world as it was when a transaction began
+struct Snapshot {
+    state: GameState,
+    world_events: WorldEventsSnapshot,
+}
+
+impl Snapshot {
+    fn take(game_manager: &GameStateManager) -> Self {
+        Self {
+            state: game_manager.get_state(),
+            world_events: game_manager.world_events.snapshot(),
+        }
+    }
+
+    fn restore(self, game_manager: &GameStateManager) {
+        *game_manager.state.lock().unwrap() = self.state;
+        game_manager.world_events.restore(self.world_events);
+    }
+}
+
+/// Run `work` so that its changes to the world land all together or not at all.
+/// If it fails, the game state, world events and every file it wrote through
+/// `write_file` are put back the way they were, leaving the world at a clean
+/// turn boundary. Callers hold the turn lock, as does every endpoint that changes
+/// the game state or world events, so nothing else writes meanwhile.
+pub async fn atomically<T>(
+    game_manager: &GameStateManager,
+    work: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let snapshot = Snapshot::take(game_manager);
+
+    JOURNAL
+        .scope(RefCell::new(FileJournal::default()), async move {
+            let result = work.await;
+            if let Err(e) = &result {
+                log::warn!("↩️  [Rollback][System] Undoing the turn: {e:#}");
+                JOURNAL.with(|journal| journal.take().restore());
+                snapshot.restore(game_manager);
+            }
+            result
+        })
+        .await
+}
@@ -1,18 +1,245 @@
 use crate::llm::LlmClient;
-use crate::game::GameStateManager;
+use crate::metrics::metrics;
+use crate::game::events::take_due_events;
+use crate::game::transaction::atomically;
+use crate::game::feed::FeedEvent;
+use crate::game::{GameStateManager, WorldPaused};
 use crate::gm::resolve_intents;
-use crate::npcs::{collect_intents, update_memories};
+use crate::npcs::{collect_intents, update_memories_as_of, PendingMemories};
 use crate::prompts::PromptBuilder;
-use crate::types::{GmResponse, MemoryUpdateInput};
+use crate::types::{GameState, GmResponse, Intent, MemoryUpdateInput, MemoryUpdateOutcome, TurnResult};
+use crate::utils::wrap_text;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tracing::{field, Instrument};
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
+
+/// How a run of turns treats the memory phase
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TurnMode {
+    /// Each NPC starts on its next intent as soon as its own memories are written,
+    /// while the others' are still being updated
+    #[default]
+    Pipelined,
+    /// Every memory is written before the next turn begins
+    Strict,
+}
+
+/// What a run of turns got through, and what stopped it early if anything did
+pub struct TurnRun {
+    pub turns_executed: u32,
+    pub last_result: Option<TurnResult>,
+    pub failure: Option<anyhow::Error>,
+}
+
+/// What's left of a turn once it's resolved: writing what each NPC made of it
+/// into their memories, stamped with the turn and time it happened
+pub struct MemoryPhase {
+    turn: u64,
+    world_time: DateTime<Utc>,
+    inputs: Vec<MemoryUpdateInput>,
+    span: tracing::Span,  // The turn's own, so the phase is traced under it whenever it runs
+}
 
 pub async fn execute_turn(
     game_manager: &GameStateManager,
     llm_client: Arc<dyn LlmClient>,
     prompt_builder: &PromptBuilder,
-) -> Result<GmResponse> {
-    log::info!("\n{}\n🎮 [Turn Execution][System] Starting new turn\n{}", "=".repeat(60), "-".repeat(60));
+) -> Result<TurnResult> {
+    let _turn_guard = game_manager.turn_lock.lock().await;
+    if game_manager.is_paused() {
+        return Err(WorldPaused.into());
+    }
+
+    // A turn that fails anywhere leaves no trace: not in the game state, the
+    // contract files nor any NPC's memories, and the turn number stays put
+    transact_turn(game_manager, run_turn(game_manager, llm_client, prompt_builder)).await
+}
+
+/// Run up to `repeat` turns, or until one fails if `repeat` is None, waiting
+/// `delay` between them. How the memory phase fits in is up to the world's
+/// `turn_mode`; see `execute_pipelined_turns` for what pipelining guarantees.
+pub async fn execute_turns(
+    game_manager: &GameStateManager,
+    llm_client: Arc<dyn LlmClient>,
+    prompt_builder: &PromptBuilder,
+    repeat: Option<u32>,
+    delay: Duration,
+) -> TurnRun {
+    match game_manager.turn_mode {
+        TurnMode::Pipelined => execute_pipelined_turns(game_manager, llm_client, prompt_builder, repeat, delay).await,
+        TurnMode::Strict => execute_strict_turns(game_manager, llm_client, prompt_builder, repeat, delay).await,
+    }
+}
+
+async fn execute_strict_turns(
+    game_manager: &GameStateManager,
+    llm_client: Arc<dyn LlmClient>,
+    prompt_builder: &PromptBuilder,
+    repeat: Option<u32>,
+    delay: Duration,
+) -> TurnRun {
+    let mut run = TurnRun { turns_executed: 0, last_result: None, failure: None };
+    while repeat.is_none_or(|repeat| run.turns_executed < repeat) {
+        if run.turns_executed > 0 {
+            tokio::time::sleep(delay).await;
+        }
+        match execute_turn(game_manager, Arc::clone(&llm_client), prompt_builder).await {
+            Ok(result) => {
+                run.turns_executed += 1;
+                log::info!("\n✅ [Turn Complete][System] Turn {} completed\n{}\n", result.turn, "=".repeat(60));
+                run.last_result = Some(result);
+            }
+            Err(e) => {
+                run.failure = Some(e);
+                break;
+            }
+        }
+    }
+    run
+}
+
+/// Run turns with each one's memory phase overlapping the next turn. This gives
+/// the same world as running them strictly, because:
+///
+/// - An NPC's intent prompt only reads its own memories and knowledge, which only
+///   its own memory update writes, and its intent waits for that update
+/// - The GM reads other NPCs' memories for gossip, so it waits for every update
+/// - The next turn's transaction doesn't journal those writes, so rolling it back
+///   never undoes memories of a turn that completed, and a failed memory update
+///   never fails a turn, as in strict mode
+/// - The turn and in-world time a memory is stamped with are taken when its turn
+///   resolves, before the next one moves the clock on
+/// - The turn lock is held for each turn and the memory phase it overlaps, and
+///   released between them (and during `delay`), so API edits get in between
+///   turns instead of waiting for the run to end
+///
+/// What it gives up: memories aren't part of their own turn's transaction either.
+/// A turn's state and contracts commit together, its memories afterwards and on
+/// their own, where strict mode commits all three together.
+async fn execute_pipelined_turns(
+    game_manager: &GameStateManager,
+    llm_client: Arc<dyn LlmClient>,
+    prompt_builder: &PromptBuilder,
+    repeat: Option<u32>,
+    delay: Duration,
+) -> TurnRun {
+    let mut run = TurnRun { turns_executed: 0, last_result: None, failure: None };
+    let mut memory_phase: Option<MemoryPhase> = None;
+    if repeat == Some(0) {
+        return run;
+    }
+
+    loop {
+        if run.turns_executed > 0 {
+            tokio::time::sleep(delay).await;
+        }
+        let _turn_guard = game_manager.turn_lock.lock().await;
+
+        // The world may have been deleted while the lock was free
+        if memory_phase.is_some() && !game_manager.data_dir.root().exists() {
+            log::warn!("⚠️  [Memory Phase][System] {:?} is gone, dropping the last turn's memories", game_manager.data_dir.root());
+            memory_phase = None;
+        }
+
+        if game_manager.is_paused() {
+            run.failure = Some(WorldPaused.into());
+        } else {
+            let pending = memory_phase.as_ref().map(MemoryPhase::pending).unwrap_or_default();
+            let previous_memories = async {
+                match memory_phase.take() {
+                    Some(phase) => phase.run(game_manager, Arc::clone(&llm_client), prompt_builder, &pending).await,
+                    None => Vec::new(),
+                }
+            };
+            let next_turn = transact_turn(
+                game_manager,
+                resolve_turn(game_manager, Arc::clone(&llm_client), prompt_builder, &pending),
+            );
+            let (memory_outcomes, resolved) = futures::future::join(previous_memories, next_turn).await;
+            if let Some(previous) = &mut run.last_result {
+                previous.memory_updates = memory_outcomes;
+            }
+
+            match resolved {
+                Ok((result, phase)) => {
+                    run.turns_executed += 1;
+                    log::info!("\n✅ [Turn Complete][System] Turn {} resolved, memories pending\n{}\n", result.turn, "=".repeat(60));
+                    run.last_result = Some(result);
+                    memory_phase = Some(phase);
+                }
+                Err(e) => run.failure = Some(e),
+            }
+        }
+
+        let finished = run.failure.is_some() || repeat.is_some_and(|repeat| run.turns_executed >= repeat);
+        if finished {
+            // The last turn's memories have nothing left to overlap with, and are
+            // written before the lock is let go
+            if let Some(phase) = memory_phase.take() {
+                let pending = phase.pending();
+                let memory_outcomes = phase.run(game_manager, llm_client, prompt_builder, &pending).await;
+                if let Some(last_result) = &mut run.last_result {
+                    last_result.memory_updates = memory_outcomes;
+                }
+            }
+            return run;
+        }
+    }
+}
+
+/// Run a turn's work as a transaction under its own span, and report how it went
+/// on the feed and in the metrics
+async fn transact_turn<T>(
+    game_manager: &GameStateManager,
+    work: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let started = std::time::Instant::now();
+    let span = tracing::info_span!("turn", turn = field::Empty, data_dir = %game_manager.data_dir.root().display());
+    let result = atomically(game_manager, work).instrument(span).await;
+
+    game_manager.feed.publish(match &result {
+        Ok(_) => FeedEvent::TurnCompleted { turn: game_manager.current_turn() },
+        // Rolled back, so the turn that failed is the one after the current
+        Err(e) => FeedEvent::TurnFailed { turn: game_manager.current_turn() + 1, error: format!("{e:#}") },
+    });
+
+    let outcome = if result.is_ok() { "completed" } else { "failed" };
+    metrics().turns.with_label_values(&[outcome]).inc();
+    metrics().turn_duration.with_label_values(&[outcome]).observe(started.elapsed().as_secs_f64());
+    result
+}
+
+async fn run_turn(
+    game_manager: &GameStateManager,
+    llm_client: Arc<dyn LlmClient>,
+    prompt_builder: &PromptBuilder,
+) -> Result<TurnResult> {
+    let (mut result, memory_phase) =
+        resolve_turn(game_manager, Arc::clone(&llm_client), prompt_builder, &PendingMemories::default()).await?;
+    let pending = memory_phase.pending();
+    result.memory_updates = memory_phase.run(game_manager, llm_client, prompt_builder, &pending).await;
+    Ok(result)
+}
+
+/// Everything in a turn up to the memory phase: intents, then the GM. Intents wait
+/// on `pending` NPC by NPC, and the GM on all of it.
+async fn resolve_turn(
+    game_manager: &GameStateManager,
+    llm_client: Arc<dyn LlmClient>,
+    prompt_builder: &PromptBuilder,
+    pending: &PendingMemories,
+) -> Result<(TurnResult, MemoryPhase)> {
+    let turn = game_manager.begin_turn();
+    tracing::Span::current().record("turn", turn);
+    game_manager.feed.publish(FeedEvent::TurnStarted { turn });
+
+    log::info!("\n{}\n🎮 [Turn Execution][System] Starting turn {}\n{}", "=".repeat(60), turn, "-".repeat(60));
     
     // Log active contracts if any
     let game_state = game_manager.get_state();
@@ -25,26 +252,120 @@ pub async fn execute_turn(
         log::info!("");
     }
 
-    // First collect intents
-    let intents = collect_intents(game_manager, Arc::clone(&llm_client), prompt_builder).await;
+    // First collect intents, waiting for the player alongside the NPCs if one has joined
+    let (collection, player_intent) = futures::future::join(
+        collect_intents(game_manager, Arc::clone(&llm_client), prompt_builder, pending),
+        wait_for_player_intent(game_manager),
+    )
+    .instrument(tracing::info_span!("intents"))
+    .await;
+    let dropped_intents = collection.failure_reports();
+    for failure in &dropped_intents {
+        log::warn!("⚠️  [Intent Summary][System] No intent from {}: {}", failure.npc, failure.error);
+    }
+    let mut intents = collection.intents;
+    intents.extend(player_intent.clone());
+    if intents.is_empty()
+        && let Some(first_failure) = collection.failures.into_iter().next()
+    {
+        return Err(first_failure.context("No NPC produced an intent this turn"));
+    }
 
     let intent_count = intents.len();
     log::info!("📝 [Intent Summary][System] Collected {intent_count} NPC intents\n");
 
-    // Then resolve them
-    let gm_response = resolve_intents(
+    // Then resolve them, along with anything the world throws at the NPCs
+    pending.wait_for_all().await;
+    let world_events = take_due_events(game_manager);
+    let gm_response = match resolve_intents(
         game_manager, 
         intents.clone(), 
+        world_events.clone(),
         Arc::clone(&llm_client), 
         prompt_builder
-    ).await?;
+    ).instrument(tracing::info_span!("gm")).await {
+        Ok(gm_response) => gm_response,
+        Err(e) => {
+            // The turn is rolled back, so the player's intent waits for the next one
+            if let Some(intent) = player_intent {
+                game_manager.player_input.put_back(intent);
+            }
+            return Err(e);
+        }
+    };
+
+    let memory_phase = MemoryPhase {
+        turn,
+        world_time: game_manager.clock().time,
+        inputs: build_memory_updates(&intents, &gm_response, game_manager)?,
+        span: tracing::Span::current(),
+    };
+    // Only what the GM said to the player this turn; an old narration isn't news
+    let player_narration = game_manager
+        .player()
+        .and_then(|player| gm_response.next_prompts.get(&player.name).cloned());
+
+    let result = TurnResult {
+        turn,
+        gm_response,
+        world_events,
+        dropped_intents,
+        memory_updates: Vec::new(),
+        player_narration,
+    };
+    Ok((result, memory_phase))
+}
+
+impl MemoryPhase {
+    /// The NPCs whose memories this phase will write
+    pub fn pending(&self) -> PendingMemories {
+        PendingMemories::new(self.inputs.iter().map(|input| input.npc_name.clone()))
+    }
+
+    /// Update memories based on what happened, clearing each NPC from `pending` as it's done
+    pub async fn run(
+        self,
+        game_manager: &GameStateManager,
+        llm_client: Arc<dyn LlmClient>,
+        prompt_builder: &PromptBuilder,
+        pending: &PendingMemories,
+    ) -> Vec<MemoryUpdateOutcome> {
+        let span = tracing::info_span!(parent: &self.span, "memories");
+        log::info!("\n{}\n🧠 [Memory Phase][System] Updating NPC memories based on turn {} events\n{}", "-".repeat(60), self.turn, "-".repeat(60));
+        let memory_outcomes = update_memories_as_of(self.inputs, self.turn, self.world_time, game_manager, llm_client, prompt_builder, pending)
+            .instrument(span)
+            .await;
 
-    // Update memories based on what happened
-    log::info!("\n{}\n🧠 [Memory Phase][System] Updating NPC memories based on turn events\n{}", "-".repeat(60), "-".repeat(60));
-    let memory_updates = build_memory_updates(&intents, &gm_response, game_manager)?;
-    update_memories(memory_updates, llm_client, prompt_builder).await?;
+        let failed: Vec<&str> = memory_outcomes
+            .iter()
+            .filter(|outcome| outcome.error.is_some())
+            .map(|outcome| outcome.npc.as_str())
+            .collect();
+        if !failed.is_empty() {
+            log::warn!("⚠️  [Memory Phase][System] Memory update failed for: {}", failed.join(", "));
+        }
+        memory_outcomes
+    }
+}
+
+/// The player's intent for this turn, or None if no player has joined or they
+/// didn't act before the timeout, in which case the turn goes on without them
+async fn wait_for_player_intent(game_manager: &GameStateManager) -> Option<Intent> {
+    let player = game_manager.player()?;
+    let timeout = game_manager.player_intent_timeout;
 
-    Ok(gm_response)
+    log::info!("⏳ [Player][{}] Waiting up to {}s for the player to act...", player.name.to_uppercase(), timeout.as_secs());
+    match game_manager.player_input.wait(timeout).await {
+        Some(intent) => {
+            let wrapped_action = wrap_text(&intent.action, 70, "     ");
+            log::info!("  🧍 [Intent][{}]\n{}", player.name.to_uppercase(), wrapped_action);
+            Some(intent)
+        }
+        None => {
+            log::warn!("⚠️  [Player][{}] No intent before the timeout, the turn goes on without them", player.name.to_uppercase());
+            None
+        }
+    }
 }
 
 fn build_memory_updates(
@@ -57,29 +378,73 @@ fn build_memory_updates(
     // Get current game state to know who's where
     let game_state = game_manager.get_state();
     
-    // Create memory update for each NPC that acted
-    for intent in intents {
-        // Find other NPCs at the same location
-        let npc_location = game_state.npcs
-            .get(&intent.npc)
-            .map(|npc| npc.location.clone())
-            .unwrap_or(crate::types::Location::ForestClearing);
-            
-        let other_npcs_present: Vec<String> = game_state.npcs
+    // An interaction that ended this turn is looked back on as a whole
+    let ended_contract = |npc: &str| {
+        gm_response.ended_contracts
             .iter()
-            .filter(|(name, npc)| {
-                name.as_str() != intent.npc.as_str() && npc.location == npc_location
-            })
-            .map(|(name, _)| name.clone())
-            .collect();
-        
+            .find(|contract| contract.participants.iter().any(|p| p == npc))
+            .map(|contract| contract.id.clone())
+    };
+    
+    // Create memory update for each NPC that acted; the player keeps no memories
+    for intent in intents.iter().filter(|intent| game_state.npcs.contains_key(&intent.npc)) {
         memory_updates.push(MemoryUpdateInput {
             npc_name: intent.npc.clone(),
-            intent: intent.clone(),
-            reality: gm_response.reality.clone(),
-            other_npcs_present,
+            intent: Some(intent.clone()),
+            reality: perceived_reality(&intent.npc, gm_response),
+            other_npcs_present: others_present(&intent.npc, &game_state),
+            ended_contract: ended_contract(&intent.npc),
         });
     }
     
+    // Everyone in an interaction that ended looks back on it, even if they didn't act this turn
+    for contract in &gm_response.ended_contracts {
+        for participant in &contract.participants {
+            if !game_state.npcs.contains_key(participant) || intents.iter().any(|intent| &intent.npc == participant) {
+                continue;
+            }
+            log::info!("📜 [Memory Phase][{}] No intent this turn, but {} ended, so it's summarized anyway", participant.to_uppercase(), contract.id);
+            memory_updates.push(MemoryUpdateInput {
+                npc_name: participant.clone(),
+                intent: None,
+                reality: perceived_reality(participant, gm_response),
+                other_npcs_present: others_present(participant, &game_state),
+                ended_contract: Some(contract.id.clone()),
+            });
+        }
+    }
+    
     Ok(memory_updates)
+}
+
+/// Other NPCs (and the player) at the same location as `npc_name`
+fn others_present(npc_name: &str, game_state: &GameState) -> Vec<String> {
+    let Some(location) = game_state.npcs.get(npc_name).map(|npc| &npc.location) else {
+        return Vec::new();
+    };
+    let mut others: Vec<String> = game_state.npcs
+        .iter()
+        .filter(|(name, npc)| name.as_str() != npc_name && &npc.location == location)
+        .map(|(name, _)| name.clone())
+        .collect();
+    if let Some(player) = game_state.player.as_ref().filter(|p| &p.location == location) {
+        others.push(player.name.clone());
+    }
+    others
+}
+
+/// What an NPC experienced this turn. NPCs never see the GM's omniscient reality,
+/// only their own observations, or failing that the GM's prompt written for them.
+fn perceived_reality(npc_name: &str, gm_response: &GmResponse) -> String {
+    if let Some(observation) = gm_response.observations.get(npc_name)
+        && !observation.is_empty()
+    {
+        return observation.describe();
+    }
+
+    log::warn!("GM gave no observations for {npc_name}, falling back to their next prompt");
+    gm_response.next_prompts
+        .get(npc_name)
+        .cloned()
+        .unwrap_or_else(|| "Nothing out of the ordinary reached your senses.".to_string())
 }
\ No newline at end of file
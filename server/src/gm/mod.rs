@@ -1,3 +1,4 @@
 pub mod resolution;
+pub mod validation;
 
 pub use resolution::resolve_intents;
\ No newline at end of file
@@ -1,14 +1,24 @@
-use crate::llm::{parser, LlmClient};
-use crate::game::{contracts::ContractManager, GameStateManager};
+use crate::llm::{parser, traced_query, LlmCallError, LlmClient, LlmRole};
+use crate::data_dir::DataDir;
+use crate::game::{contracts::ContractManager, events::WorldEvent, items::ItemHolder, GameStateManager};
+use crate::gm::validation;
+use crate::metrics::metrics;
 use crate::prompts::PromptBuilder;
-use crate::types::{CurrentState, GmInput, GmResponse, Intent};
+use crate::npcs::gossip;
+use crate::npcs::knowledge::load_knowledge;
+use crate::types::{CurrentState, GameState, GmInput, GmResponse, Intent, TranscriptEntry};
 use crate::utils::wrap_text;
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
 use std::sync::Arc;
 
+/// How many times the GM gets to fix a response that contradicts the world
+const MAX_GM_REPAIR_ATTEMPTS: usize = 2;
+
 pub async fn resolve_intents(
     game_manager: &GameStateManager,
     intents: Vec<Intent>,
+    world_events: Vec<WorldEvent>,
     llm_client: Arc<dyn LlmClient>,
     prompt_builder: &PromptBuilder,
 ) -> Result<GmResponse> {
@@ -18,13 +28,34 @@ pub async fn resolve_intents(
     // Get current game state
     let game_state = game_manager.get_state();
 
+    // Tell the GM what each NPC knows, so it can recognise retold information
+    let mut knowledge = HashMap::new();
+    for name in game_state.npcs.keys() {
+        match load_knowledge(&game_manager.data_dir, name) {
+            Ok(kb) => {
+                knowledge.insert(name.clone(), kb.known_facts());
+            }
+            Err(e) => log::error!("Failed to load knowledge for {name}: {e}"),
+        }
+    }
+
     // Prepare input for GM
     let gm_input = GmInput {
         current_state: CurrentState {
+            clock: game_state.clock.clone(),
             npcs: game_state.npcs.clone(),
+            player: game_state.player.clone(),
+            items: game_state
+                .items
+                .values()
+                .filter(|item| item.holder != ItemHolder::Consumed)
+                .map(|item| (item.id.clone(), item.summary()))
+                .collect(),
             active_contracts: game_state.contracts.clone(),
         },
-        intents,
+        intents: intents.clone(),
+        world_events,
+        knowledge,
     };
 
     let input_json = serde_json::to_string_pretty(&gm_input)?;
@@ -37,20 +68,83 @@ pub async fn resolve_intents(
 
     // Call GM - use current directory (server directory)
     log::info!("\n🎲 [GM Resolution][GM] Resolving simultaneous actions...");
-    let response = llm_client
-        .query(prompt, std::path::Path::new("."))
-        .await?;
+    let options = game_manager.sampling.options_for(LlmRole::Gm, None);
+    let response = traced_query(&*llm_client, prompt.clone(), std::path::Path::new("."), &options, LlmRole::Gm, None)
+        .await
+        .context(LlmCallError)?;
+    game_manager.usage.record(game_state.turn, LlmRole::Gm, None, &response.usage);
 
-    log::debug!("GM raw response: {response}");
+    log::debug!("GM raw response: {}", response.text);
 
     // Parse response
-    let gm_response: GmResponse = parser::extract_json(&response)?;
+    let mut gm_response: GmResponse = parser::extract_json(&response.text)?;
+    llm_client.accept(&response);
+    let mut response = response.text;
+
+    // Send responses that contradict the world back to the GM to fix
+    for attempt in 1..=MAX_GM_REPAIR_ATTEMPTS {
+        let problems = validation::check(&gm_response, &game_state, &intents);
+        if problems.is_empty() {
+            break;
+        }
+        log::warn!("⚠️  [GM Resolution][GM] Response has {} problem(s), asking for a fix ({attempt}/{MAX_GM_REPAIR_ATTEMPTS})", problems.len());
+        for problem in &problems {
+            log::warn!("  - {problem}");
+        }
+
+        let repair_prompt = prompt_builder.build_gm_repair_prompt(&prompt, &response, &problems);
+        let repair_options = game_manager.sampling.options_for(LlmRole::GmRepair, None);
+        let repaired = traced_query(&*llm_client, repair_prompt, std::path::Path::new("."), &repair_options, LlmRole::GmRepair, None)
+            .await
+            .context(LlmCallError)?;
+        game_manager.usage.record(game_state.turn, LlmRole::GmRepair, None, &repaired.usage);
+        log::debug!("GM repaired response: {}", repaired.text);
+
+        match parser::extract_json(&repaired.text) {
+            Ok(parsed) => {
+                llm_client.accept(&repaired);
+                gm_response = parsed;
+                response = repaired.text;
+            }
+            Err(e) => {
+                log::warn!("GM repair attempt was unreadable, keeping the previous response: {e}");
+                break;
+            }
+        }
+    }
+
+    // Whatever is still wrong gets dropped, and reported as warnings
+    gm_response.warnings = validation::correct(&mut gm_response, &game_state, &intents);
+    metrics().gm_corrections.inc_by(gm_response.warnings.len() as u64);
+    for warning in &gm_response.warnings {
+        log::warn!("⚠️  [GM Resolution][GM] Dropped: {warning}");
+    }
     let wrapped_reality = wrap_text(&gm_response.reality, 70, "  ");
     log::info!("🎭 [GM Reality][GM]\n{}\n", wrapped_reality);
 
+    // Apply everything at once: if any part fails (say a contract file can't be
+    // written) the game state is left exactly as it was before the turn
+    let contracts = game_manager.contract_manager();
+    game_manager.stage(|staged| {
+        apply_resolution(staged, &game_state, &mut gm_response, &contracts, &game_manager.data_dir)
+    })?;
+    metrics().active_contracts.set(game_manager.get_state().contracts.len() as i64);
+
+    Ok(gm_response)
+}
+
+/// Apply a validated GM response to the staged game state. `start` is the world
+/// before the turn, which item transfers are checked against.
+fn apply_resolution(
+    state: &mut GameState,
+    start: &GameState,
+    gm_response: &mut GmResponse,
+    contracts: &ContractManager,
+    data_dir: &DataDir,
+) -> Result<()> {
     // Apply state changes
     for change in &gm_response.state_changes {
-        game_manager.update_npc_location(
+        state.update_npc_location(
             &change.npc,
             change.location.clone(),
             change.activity.clone(),
@@ -61,22 +155,28 @@ pub async fn resolve_intents(
         log::info!("  📍 [{}] {:?} - {}", npc.to_uppercase(), location, activity);
     }
 
-    // Handle contract updates
-    for contract_update in &gm_response.contracts {
+    // Move items around, refusing transfers that contradict the world
+    gm_response.rejected_item_transfers = state.apply_item_transfers(start, &gm_response.item_transfers);
+
+    // Handle contract updates, ending contracts first so their participants are
+    // free to start new ones in the same response
+    let mut ended_contracts = Vec::new();
+    let (ends, others): (Vec<_>, Vec<_>) = gm_response.contracts.iter().partition(|c| c.action == "end");
+    for contract_update in ends.into_iter().chain(others) {
         match contract_update.action.as_str() {
             "create" => {
-                let contract = ContractManager::create_contract(
+                let contract = contracts.create_contract(
                     contract_update.participants.clone(),
                     contract_update.transcript_entry.clone(),
                 )?;
                 
                 // Update NPCs' active_contract field
                 for participant in &contract_update.participants {
-                    game_manager.set_npc_contract(participant, Some(contract.id.clone()));
+                    state.set_npc_contract(participant, Some(contract.id.clone()));
                 }
                 
                 // Add to game state
-                game_manager.add_contract(contract.clone());
+                state.add_contract(contract.clone());
                 
                 let participants = &contract.participants;
                 log::info!("  📝 [Contract] New interaction: {} ↔ {}", participants[0], participants.get(1).unwrap_or(&"self".to_string()));
@@ -84,26 +184,36 @@ pub async fn resolve_intents(
                 // Log the contract reality if we have a transcript entry
                 if let Some(entry) = &contract_update.transcript_entry {
                     log::info!("     Reality: {}", entry.reality);
+                    spread_shared_information(data_dir, state, entry, participants);
                 }
             }
             "update" => {
-                if let Some(contract) = game_manager.get_contract(&contract_update.id) {
-                    if let Some(entry) = &contract_update.transcript_entry {
-                        ContractManager::update_contract(&contract, entry.clone())?;
-                        let id = &contract_update.id;
-                        log::info!("  📝 [Contract] Update: {}", id);
-                        log::info!("     Reality: {}", entry.reality);
-                    }
+                if let Some(contract) = state.contracts.get(&contract_update.id).cloned()
+                    && let Some(entry) = &contract_update.transcript_entry
+                {
+                    contracts.update_contract(&contract, entry.clone())?;
+                    let id = &contract_update.id;
+                    log::info!("  📝 [Contract] Update: {}", id);
+                    log::info!("     Reality: {}", entry.reality);
+                    spread_shared_information(data_dir, state, entry, &contract.participants);
                 }
             }
             "end" => {
-                // Remove active_contract from NPCs
-                if let Some(contract) = game_manager.get_contract(&contract_update.id) {
+                // Archive the contract: its transcript stays on disk for the
+                // participants to summarize, and they're free again
+                if let Some(contract) = state.contracts.remove(&contract_update.id) {
+                    if let Some(entry) = &contract_update.transcript_entry {
+                        contracts.update_contract(&contract, entry.clone())?;
+                        spread_shared_information(data_dir, state, entry, &contract.participants);
+                    }
                     for participant in &contract.participants {
-                        game_manager.set_npc_contract(participant, None);
+                        if state.npc_contract(participant) == Some(&contract.id) {
+                            state.set_npc_contract(participant, None);
+                        }
                     }
                     let id = &contract_update.id;
                     log::info!("  ✅ [Contract] Interaction ended: {id}");
+                    ended_contracts.push(contract);
                 }
             }
             _ => {
@@ -113,11 +223,39 @@ pub async fn resolve_intents(
         }
     }
 
+    gm_response.ended_contracts = ended_contracts;
+
     // Store next prompts from GM
     for (npc_name, prompt) in &gm_response.next_prompts {
-        game_manager.set_npc_prompt(npc_name, prompt.clone());
+        state.set_npc_prompt(npc_name, prompt.clone());
         log::debug!("Stored prompt for {npc_name}");
     }
 
-    Ok(gm_response)
+    // Store what each NPC perceived, so their next intent only sees their own view
+    for (npc_name, observation) in &gm_response.observations {
+        state.set_npc_observation(npc_name, observation.clone());
+        log::debug!("Stored observation for {npc_name}");
+    }
+
+    Ok(())
+}
+
+/// Pass on any information the GM saw being shared in a contract interaction.
+/// Only NPCs keep knowledge, so the player neither records nor originates rumors.
+fn spread_shared_information(data_dir: &DataDir, game_state: &GameState, entry: &TranscriptEntry, participants: &[String]) {
+    let is_npc = |name: &String| game_state.npcs.contains_key(name);
+    let npc_participants: Vec<String> = participants.iter().filter(|p| is_npc(p)).cloned().collect();
+
+    for shared in entry.shared_information.iter().filter(|s| is_npc(&s.speaker)) {
+        let mut shared = shared.clone();
+        if !shared.listeners.is_empty() {
+            shared.listeners.retain(|listener| is_npc(listener));
+            if shared.listeners.is_empty() {
+                continue;
+            }
+        }
+        if let Err(e) = gossip::spread(data_dir, &shared, &npc_participants, game_state.turn) {
+            log::error!("Failed to spread information from {}: {e}", shared.speaker);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,129 @@
+use crate::npcs::gossip::is_valid_rumor_id;
+use crate::types::{GameState, GmResponse, Intent};
+use std::collections::{HashMap, HashSet};
+
+/// Strip everything from a GM response that contradicts the current world, and
+/// describe each problem found. Problems that can't be stripped (an acting NPC the
+/// GM forgot about) are only described.
+pub fn correct(response: &mut GmResponse, state: &GameState, intents: &[Intent]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let known: HashSet<&str> = state
+        .npcs
+        .keys()
+        .map(String::as_str)
+        .chain(state.player.as_ref().map(|p| p.name.as_str()))
+        .collect();
+
+    // Only characters that exist can move, be prompted or perceive anything
+    response.state_changes.retain(|change| {
+        let ok = known.contains(change.npc.as_str());
+        if !ok {
+            problems.push(format!("state_changes: unknown character \"{}\"", change.npc));
+        }
+        ok
+    });
+    retain_known(&mut response.next_prompts, &known, "next_prompts", &mut problems);
+    retain_known(&mut response.observations, &known, "observations", &mut problems);
+
+    // Every character who acted must hear back about it
+    for intent in intents.iter().filter(|i| known.contains(i.npc.as_str())) {
+        if !response.next_prompts.contains_key(&intent.npc) && !response.observations.contains_key(&intent.npc) {
+            problems.push(format!(
+                "{} acted this turn but has neither a next_prompts nor an observations entry",
+                intent.npc
+            ));
+        }
+    }
+
+    // Contracts must refer to real contracts and never put anyone in two at once
+    let ending: HashSet<String> = response
+        .contracts
+        .iter()
+        .filter(|c| c.action == "end")
+        .map(|c| c.id.clone())
+        .collect();
+    let mut busy: HashMap<String, String> = state
+        .contracts
+        .values()
+        .filter(|contract| !ending.contains(&contract.id))
+        .flat_map(|contract| contract.participants.iter().map(|p| (p.clone(), contract.id.clone())))
+        .collect();
+
+    response.contracts.retain(|update| {
+        let problem = match update.action.as_str() {
+            "update" | "end" if !state.contracts.contains_key(&update.id) => {
+                Some(format!("contracts: cannot {} unknown contract \"{}\"", update.action, update.id))
+            }
+            "update" if ending.contains(&update.id) => Some(format!(
+                "contracts: cannot update \"{}\" as it ends in the same response; put the entry in the end instead",
+                update.id
+            )),
+            "update" | "end" => None,
+            "create" => {
+                if update.participants.is_empty() {
+                    Some("contracts: a new contract needs participants".to_string())
+                } else if let Some(unknown) = update.participants.iter().find(|p| !known.contains(p.as_str())) {
+                    Some(format!("contracts: unknown participant \"{unknown}\""))
+                } else if let Some(twice) = update
+                    .participants
+                    .iter()
+                    .enumerate()
+                    .find_map(|(i, p)| update.participants[..i].contains(p).then_some(p))
+                {
+                    Some(format!("contracts: \"{twice}\" is listed twice in a new contract"))
+                } else if let Some((name, other)) = update
+                    .participants
+                    .iter()
+                    .find_map(|p| busy.get(p).map(|other| (p, other)))
+                {
+                    Some(format!("contracts: {name} can't join a new contract while in \"{other}\""))
+                } else {
+                    for participant in &update.participants {
+                        busy.insert(participant.clone(), format!("new contract {}", update.id));
+                    }
+                    None
+                }
+            }
+            other => Some(format!("contracts: unknown action \"{other}\"")),
+        };
+        if let Some(problem) = &problem {
+            problems.push(problem.clone());
+        }
+        problem.is_none()
+    });
+
+    // A retold rumor must be named the way rumors are, or it is treated as new information
+    for shared in response
+        .contracts
+        .iter_mut()
+        .filter_map(|update| update.transcript_entry.as_mut())
+        .flat_map(|entry| entry.shared_information.iter_mut())
+    {
+        if let Some(id) = shared.rumor_id.take_if(|id| !is_valid_rumor_id(id)) {
+            problems.push(format!("shared_information: invalid rumor_id \"{id}\""));
+        }
+    }
+
+    problems
+}
+
+/// Problems with a GM response, without changing it
+pub fn check(response: &GmResponse, state: &GameState, intents: &[Intent]) -> Vec<String> {
+    let mut copy = response.clone();
+    correct(&mut copy, state, intents)
+}
+
+fn retain_known<T>(
+    entries: &mut HashMap<String, T>,
+    known: &HashSet<&str>,
+    section: &str,
+    problems: &mut Vec<String>,
+) {
+    entries.retain(|name, _| {
+        let ok = known.contains(name.as_str());
+        if !ok {
+            problems.push(format!("{section}: unknown character \"{name}\""));
+        }
+        ok
+    });
+}
@@ -1,7 +1,11 @@
+pub mod api;
+pub mod config;
+pub mod data_dir;
 pub mod llm;
 pub mod game;
 pub mod gm;
 pub mod logging;
+pub mod metrics;
 pub mod npcs;
 pub mod prompts;
 pub mod types;
@@ -9,25 +13,30 @@ pub mod utils;
 
 // Re-export for tests
 pub use llm::{ClaudeClient, OllamaClient, LlmClient};
+pub use config::Config;
+pub use data_dir::DataDir;
 pub use game::GameStateManager;
 pub use prompts::{PromptBuilder, PromptLoader};
 pub use types::*;
 
+use api::extract::Json;
+use api::{ApiError, ApiResult, Problem};
 use axum::{
     extract::State,
     routing::{get, post},
-    Json, Router,
+    Router,
 };
 use std::sync::Arc;
 // Using crate:: to avoid shadowing the public export
 
 pub struct AppState {
+    pub config: config::Config,
     pub game_manager: GameStateManager,
     pub llm_client: Arc<dyn LlmClient>,
     pub prompt_builder: PromptBuilder,
 }
 
-type SharedState = Arc<AppState>;
+pub(crate) type SharedState = Arc<AppState>;
 
 async fn health() -> &'static str {
     "OK\n"
@@ -37,106 +46,123 @@ async fn get_game_state(State(state): State<SharedState>) -> Json<types::GameSta
     Json(state.game_manager.get_state())
 }
 
-async fn collect_intents_handler(State(state): State<SharedState>) -> Json<Vec<types::Intent>> {
-    let intents = npcs::collect_intents(
+async fn collect_intents_handler(
+    State(state): State<SharedState>,
+) -> ApiResult<Json<types::CollectIntentsResponse>> {
+    let collection = npcs::collect_intents(
         &state.game_manager, 
         Arc::clone(&state.llm_client),
-        &state.prompt_builder
+        &state.prompt_builder,
+        &npcs::PendingMemories::default(),
     ).await;
-    Json(intents)
+
+    let failures: Vec<Problem> = collection
+        .failures
+        .into_iter()
+        .map(|e| ApiError::from(e).problem())
+        .collect();
+
+    if collection.intents.is_empty() && !failures.is_empty() {
+        return Err(ApiError::NpcFailures {
+            message: "No NPC produced an intent".to_string(),
+            failures,
+        });
+    }
+
+    Ok(Json(types::CollectIntentsResponse {
+        intents: collection.intents,
+        failures,
+    }))
 }
 
 async fn resolve_intents_handler(
     State(state): State<SharedState>,
     Json(intents): Json<Vec<types::Intent>>,
-) -> Json<types::GmResponse> {
-    match gm::resolve_intents(
-        &state.game_manager, 
-        intents, 
-        Arc::clone(&state.llm_client),
-        &state.prompt_builder
-    ).await {
-        Ok(response) => Json(response),
-        Err(e) => {
-            log::error!("Failed to resolve intents: {e}");
-            panic!("GM resolution failed");
-        }
-    }
+) -> ApiResult<Json<types::GmResponse>> {
+    let _turn_guard = state.game_manager.turn_lock.lock().await;
+    let response = game::transaction::atomically(&state.game_manager, async {
+        // Advance the turn and clock as a full turn would, so due events and the
+        // GM see the turn being resolved rather than the last one
+        state.game_manager.begin_turn();
+        let world_events = game::events::take_due_events(&state.game_manager);
+        gm::resolve_intents(
+            &state.game_manager, 
+            intents, 
+            world_events,
+            Arc::clone(&state.llm_client),
+            &state.prompt_builder
+        ).await
+    }).await?;
+    Ok(Json(response))
 }
 
-#[axum::debug_handler]
 async fn update_memories_handler(
     State(state): State<SharedState>,
     Json(memory_updates): Json<Vec<types::MemoryUpdateInput>>,
-) -> String {
-    match npcs::update_memories(
+) -> ApiResult<&'static str> {
+    let _turn_guard = state.game_manager.turn_lock.lock().await;
+    let outcomes = npcs::update_memories(
         memory_updates,
+        &state.game_manager,
         Arc::clone(&state.llm_client),
         &state.prompt_builder
-    ).await {
-        Ok(_) => "Memories updated".to_string(),
-        Err(e) => {
-            log::error!("Failed to update memories: {e}");
-            panic!("Memory update failed");
-        }
+    ).await;
+
+    let failures: Vec<Problem> = outcomes
+        .iter()
+        .filter_map(|outcome| {
+            outcome.error.as_ref().map(|e| {
+                Problem::for_npc(&outcome.npc, e, outcome.llm_excerpt.as_deref())
+            })
+        })
+        .collect();
+
+    if failures.is_empty() {
+        Ok("Memories updated")
+    } else {
+        let npcs: Vec<&str> = failures.iter().filter_map(|p| p.npc.as_deref()).collect();
+        Err(ApiError::NpcFailures {
+            message: format!("Memory update failed for {}", npcs.join(", ")),
+            failures,
+        })
     }
 }
 
 async fn execute_turn_handler(
     State(state): State<SharedState>,
     Json(request): Json<types::ExecuteTurnRequest>,
-) -> Json<types::ExecuteTurnResponse> {
+) -> ApiResult<Json<types::ExecuteTurnResponse>> {
     let repeat_count = request.repeat.unwrap_or(1);
-    let mut turns_executed = 0;
-    let mut last_result = None;
-    
-    if request.endless {
-        log::info!("🔄 [Turn Mode][System] Starting endless turn execution (delay: {}ms)", request.delay_ms);
-        loop {
-            match game::turn::execute_turn(
-                &state.game_manager,
-                Arc::clone(&state.llm_client),
-                &state.prompt_builder
-            ).await {
-                Ok(response) => {
-                    turns_executed += 1;
-                    last_result = Some(response);
-                    log::info!("\n✅ [Turn Complete][System] Turn {} completed\n{}\n", turns_executed, "=".repeat(60));
-                    tokio::time::sleep(tokio::time::Duration::from_millis(request.delay_ms)).await;
-                }
-                Err(e) => {
-                    log::error!("Failed to execute turn {}: {e}", turns_executed + 1);
-                    break;
-                }
-            }
-        }
+    let repeat = if request.endless {
+        log::info!("🔄 [Turn Mode][System] Starting endless turn execution (delay: {}ms, {:?})", request.delay_ms, state.game_manager.turn_mode);
+        None
     } else {
-        log::info!("🔄 [Turn Mode][System] Executing {} turn(s)", repeat_count);
-        for i in 0..repeat_count {
-            match game::turn::execute_turn(
-                &state.game_manager,
-                Arc::clone(&state.llm_client),
-                &state.prompt_builder
-            ).await {
-                Ok(response) => {
-                    turns_executed += 1;
-                    last_result = Some(response);
-                    log::info!("\n✅ [Turn Complete][System] Turn {}/{} completed\n{}\n", turns_executed, repeat_count, "=".repeat(60));
-                    
-                    // Add delay between turns if not the last turn
-                    if i < repeat_count - 1 {
-                        tokio::time::sleep(tokio::time::Duration::from_millis(request.delay_ms)).await;
-                    }
-                }
-                Err(e) => {
-                    log::error!("Failed to execute turn {}: {e}", i + 1);
-                    break;
-                }
-            }
-        }
+        log::info!("🔄 [Turn Mode][System] Executing {} turn(s) ({:?})", repeat_count, state.game_manager.turn_mode);
+        Some(repeat_count)
+    };
+
+    let run = game::turn::execute_turns(
+        &state.game_manager,
+        Arc::clone(&state.llm_client),
+        &state.prompt_builder,
+        repeat,
+        tokio::time::Duration::from_millis(request.delay_ms),
+    ).await;
+    let turns_executed = run.turns_executed;
+    let last_result = run.last_result;
+    let failure = run.failure.map(|e| {
+        log::error!("Failed to execute turn {}: {e:#}", turns_executed + 1);
+        ApiError::from(e)
+    });
+
+    // Nothing to report but the failure itself
+    if turns_executed == 0
+        && let Some(failure) = failure
+    {
+        return Err(failure);
     }
-    
-    Json(types::ExecuteTurnResponse {
+
+    Ok(Json(types::ExecuteTurnResponse {
         turns_executed,
         last_turn_result: last_result,
         status: if request.endless {
@@ -144,10 +170,31 @@ async fn execute_turn_handler(
         } else {
             format!("Executed {}/{} turns", turns_executed, repeat_count)
         },
-    })
+        error: failure.map(|failure| failure.problem()),
+    }))
 }
 
+/// The default world at the top level, plus every other world under /worlds/{id}
 pub fn create_router(app_state: Arc<AppState>) -> Router {
+    let worlds = Arc::new(api::worlds::WorldRegistry::new(Arc::clone(&app_state)));
+    world_routes()
+        .with_state(app_state)
+        .merge(api::worlds::routes(worlds))
+        .route("/metrics", get(api::metrics::prometheus))
+        .fallback(api::error::not_found_fallback)
+        .method_not_allowed_fallback(api::error::method_not_allowed_fallback)
+        .layer(axum::middleware::from_fn(api::metrics::track_requests))
+}
+
+/// Everything a single world answers to
+pub(crate) fn world_router(state: SharedState) -> Router {
+    world_routes()
+        .fallback(api::error::not_found_fallback)
+        .method_not_allowed_fallback(api::error::method_not_allowed_fallback)
+        .with_state(state)
+}
+
+fn world_routes() -> Router<SharedState> {
     Router::new()
         .route("/health", get(health))
         .route("/state", get(get_game_state))
@@ -155,5 +202,11 @@ pub fn create_router(app_state: Arc<AppState>) -> Router {
         .route("/turn/resolve", post(resolve_intents_handler))
         .route("/turn/memories", post(update_memories_handler))
         .route("/turn/execute", post(execute_turn_handler))
-        .with_state(app_state)
+        .merge(api::memories::routes())
+        .merge(api::knowledge::routes())
+        .merge(api::player::routes())
+        .merge(api::world::routes())
+        .merge(api::admin::routes())
+        .merge(api::metrics::routes())
+        .merge(api::feed::routes())
 }
\ No newline at end of file
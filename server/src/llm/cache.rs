@@ -0,0 +1,238 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use super::{LlmChunk, LlmClient, LlmOptions, LlmResponse, LlmStream, Prompt, Usage};
+use crate::config::LlmCacheConfig;
+use crate::metrics::metrics;
+
+/// The provider named in the usage of a response served from the cache
+pub const CACHE_PROVIDER: &str = "cache";
+
+/// Answers prompts it has seen before from disk instead of asking the model again.
+/// Responses are keyed on the prompt, the call's options and a fingerprint naming
+/// the provider, model and its default sampling, so changing any of them never
+/// serves a stale response. A response is only stored once the caller accepts it,
+/// so one that couldn't be parsed is asked for again rather than replayed.
+pub struct CachingLlmClient {
+    inner: Arc<dyn LlmClient>,
+    cache: ResponseCache,
+    fingerprint: String,
+}
+
+/// One JSON file per response, named after the hash of its key
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_bytes: u64,
+    size: Mutex<Option<u64>>,  // Bytes on disk, kept up to date once first counted
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    fingerprint: String,
+    created_at: DateTime<Utc>,
+    text: String,
+    usage: Usage,  // What the original call took
+}
+
+impl CachingLlmClient {
+    pub fn new(inner: Arc<dyn LlmClient>, cache: ResponseCache, fingerprint: impl Into<String>) -> Self {
+        Self {
+            inner,
+            cache,
+            fingerprint: fingerprint.into(),
+        }
+    }
+
+    fn key(&self, prompt: &Prompt, options: &LlmOptions) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.fingerprint.as_bytes());
+        hasher.update([0]);
+        hasher.update(serde_json::to_vec(options).unwrap_or_default());
+        hasher.update([0]);
+        hasher.update(serde_json::to_vec(&prompt.messages).unwrap_or_default());
+        hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    fn lookup(&self, key: &str) -> Option<LlmResponse> {
+        let Some(entry) = self.cache.get(key, &self.fingerprint) else {
+            metrics().llm_cache_lookups.with_label_values(&["miss"]).inc();
+            return None;
+        };
+        metrics().llm_cache_lookups.with_label_values(&["hit"]).inc();
+        log::debug!("💾 [LLM Cache][System] Hit {} from {}", &key[..12], entry.created_at.format("%Y-%m-%d %H:%M:%S"));
+
+        // Nothing was spent this time
+        let usage = Usage {
+            provider: CACHE_PROVIDER.to_string(),
+            model: entry.usage.model,
+            ..Usage::default()
+        };
+        Some(LlmResponse::new(entry.text).with_usage(usage))
+    }
+}
+
+impl ResponseCache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration, max_bytes: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+            max_bytes,
+            size: Mutex::new(None),
+        }
+    }
+
+    pub fn from_config(config: &LlmCacheConfig) -> Self {
+        Self::new(
+            &config.dir,
+            Duration::from_secs(config.ttl_secs),
+            config.max_size_mb * 1024 * 1024,
+        )
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+
+    fn get(&self, key: &str, fingerprint: &str) -> Option<CacheEntry> {
+        let path = self.path(key);
+        let content = std::fs::read_to_string(&path).ok()?;
+        let entry: CacheEntry = match serde_json::from_str(&content) {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::warn!("⚠️  [LLM Cache][System] Dropping unreadable {:?}: {}", path, e);
+                let _ = std::fs::remove_file(&path);
+                return None;
+            }
+        };
+
+        let age = (Utc::now() - entry.created_at).to_std().unwrap_or_default();
+        if age >= self.ttl {
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+        (entry.fingerprint == fingerprint).then_some(entry)
+    }
+
+    /// Store a response, then make room if the cache has outgrown its limit. The
+    /// directory is only scanned to count it the first time and to trim it.
+    /// The cache is only an aid, so failing to write it never fails the call.
+    fn put(&self, key: &str, entry: &CacheEntry) {
+        let written = match self.write(key, entry) {
+            Ok(written) => written,
+            Err(e) => {
+                log::warn!("⚠️  [LLM Cache][System] Failed to store response in {:?}: {}", self.dir, e);
+                return;
+            }
+        };
+        let mut size = self.size.lock().unwrap();
+        *size = match *size {
+            Some(total) if total + written <= self.max_bytes => Some(total + written),
+            _ => match self.evict() {
+                Ok(total) => Some(total),
+                Err(e) => {
+                    log::warn!("⚠️  [LLM Cache][System] Failed to trim {:?}: {}", self.dir, e);
+                    None
+                }
+            },
+        };
+    }
+
+    /// Write a response, returning how many bytes it took
+    fn write(&self, key: &str, entry: &CacheEntry) -> std::io::Result<u64> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        let temp_path = path.with_extension("json.tmp");
+        let content = serde_json::to_vec(entry)?;
+        std::fs::write(&temp_path, &content)?;
+        std::fs::rename(&temp_path, &path)?;
+        Ok(content.len() as u64)
+    }
+
+    /// Drop the oldest responses until the cache is back under its size limit, with
+    /// a tenth of it to spare so the next few puts don't trim it again. Returns the
+    /// bytes left.
+    fn evict(&self) -> std::io::Result<u64> {
+        let mut files = Vec::new();
+        let mut total = 0;
+        for entry in std::fs::read_dir(&self.dir)?.flatten() {
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                total += metadata.len();
+                files.push((metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH), metadata.len(), entry.path()));
+            }
+        }
+        files.sort();
+
+        let target = if total > self.max_bytes { self.max_bytes - self.max_bytes / 10 } else { total };
+        let mut evicted = 0;
+        for (_, len, path) in files {
+            if total <= target {
+                break;
+            }
+            std::fs::remove_file(&path)?;
+            total -= len;
+            evicted += 1;
+        }
+        if evicted > 0 {
+            log::debug!("💾 [LLM Cache][System] Evicted {} old responses to stay under {} bytes", evicted, self.max_bytes);
+        }
+        Ok(total)
+    }
+}
+
+#[async_trait]
+impl LlmClient for CachingLlmClient {
+    async fn query(&self, prompt: Prompt, working_dir: &Path, options: &LlmOptions) -> Result<LlmResponse> {
+        let key = self.key(&prompt, options);
+        if let Some(response) = self.lookup(&key) {
+            return Ok(response);
+        }
+
+        let mut response = self.inner.query(prompt, working_dir, options).await?;
+        response.cache_key = Some(key);
+        Ok(response)
+    }
+
+    async fn query_stream(&self, prompt: Prompt, working_dir: &Path, options: &LlmOptions) -> Result<LlmStream> {
+        let key = self.key(&prompt, options);
+        if let Some(response) = self.lookup(&key) {
+            let chunk = LlmChunk {
+                text: response.text,
+                usage: Some(response.usage),
+                cache_key: None,
+            };
+            return Ok(stream::once(async move { Ok(chunk) }).boxed());
+        }
+
+        // Only a response streamed to the end can be accepted; one cut off early can't
+        let stream = self.inner.query_stream(prompt, working_dir, options).await?;
+        Ok(stream
+            .map(move |chunk| {
+                chunk.map(|mut chunk| {
+                    if chunk.usage.is_some() {
+                        chunk.cache_key = Some(key.clone());
+                    }
+                    chunk
+                })
+            })
+            .boxed())
+    }
+
+    fn accept(&self, response: &LlmResponse) {
+        let Some(key) = &response.cache_key else { return };
+        self.cache.put(key, &CacheEntry {
+            fingerprint: self.fingerprint.clone(),
+            created_at: Utc::now(),
+            text: response.text.clone(),
+            usage: response.usage.clone(),
+        });
+    }
+}
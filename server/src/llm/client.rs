@@ -1,27 +1,136 @@
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use super::{LlmOptions, LlmResponse, LlmRole, Prompt, Usage};
+use futures::stream::{self, BoxStream, StreamExt};
+use tracing::{field, Instrument, Span};
 use std::path::Path;
+use std::time::Instant;
 use tokio::process::Command;
 
+/// A piece of a response as it's generated. The last one carries the usage, and
+/// the cache key if a cache is waiting to keep the response.
+#[derive(Debug, Clone, Default)]
+pub struct LlmChunk {
+    pub text: String,
+    pub usage: Option<Usage>,
+    pub cache_key: Option<String>,
+}
+
+pub type LlmStream = BoxStream<'static, Result<LlmChunk>>;
+
 #[async_trait]
 pub trait LlmClient: Send + Sync {
-    async fn query(&self, prompt: String, working_dir: &Path) -> Result<String>;
+    async fn query(&self, prompt: Prompt, working_dir: &Path, options: &LlmOptions) -> Result<LlmResponse>;
+
+    /// Yield the response in pieces as it's generated. Providers that can't stream
+    /// answer with the whole response as a single piece. Dropping the stream stops
+    /// the generation where the provider allows it.
+    async fn query_stream(&self, prompt: Prompt, working_dir: &Path, options: &LlmOptions) -> Result<LlmStream> {
+        let response = self.query(prompt, working_dir, options).await?;
+        let chunk = LlmChunk {
+            text: response.text,
+            usage: Some(response.usage),
+            cache_key: response.cache_key,
+        };
+        Ok(stream::once(async move { Ok(chunk) }).boxed())
+    }
+
+    /// The caller could use `response`, so a client that keeps responses may keep
+    /// this one. Only accepted responses are ever replayed, never ones that failed
+    /// to parse.
+    fn accept(&self, _response: &LlmResponse) {}
+}
+
+fn llm_call_span(role: LlmRole, npc: Option<&str>) -> Span {
+    tracing::info_span!(
+        "llm_call",
+        role = role.as_str(),
+        npc = npc.unwrap_or("gm"),
+        provider = field::Empty,
+        model = field::Empty,
+        prompt_tokens = field::Empty,
+        completion_tokens = field::Empty,
+        latency_ms = field::Empty,
+    )
+}
+
+fn record_usage(span: &Span, usage: &Usage) {
+    span.record("provider", usage.provider.as_str());
+    span.record("model", usage.model.as_str());
+    span.record("prompt_tokens", usage.prompt_tokens);
+    span.record("completion_tokens", usage.completion_tokens);
+    span.record("latency_ms", usage.latency_ms);
+}
+
+/// Query inside an `llm_call` span that carries the role, NPC, provider and what the
+/// call took, so every log line it causes can be traced back to it
+pub async fn traced_query(
+    llm_client: &dyn LlmClient,
+    prompt: Prompt,
+    working_dir: &Path,
+    options: &LlmOptions,
+    role: LlmRole,
+    npc: Option<&str>,
+) -> Result<LlmResponse> {
+    let span = llm_call_span(role, npc);
+    let response = llm_client.query(prompt, working_dir, options).instrument(span.clone()).await?;
+    record_usage(&span, &response.usage);
+    Ok(response)
+}
+
+/// Like `traced_query`, but streaming: `on_chunk` sees each new piece and everything
+/// so far, and can stop the generation by returning an error
+pub async fn traced_stream_query(
+    llm_client: &dyn LlmClient,
+    prompt: Prompt,
+    working_dir: &Path,
+    options: &LlmOptions,
+    role: LlmRole,
+    npc: Option<&str>,
+    mut on_chunk: impl FnMut(&str, &str) -> Result<()> + Send,
+) -> Result<LlmResponse> {
+    let span = llm_call_span(role, npc);
+    let response = async {
+        let mut chunks = llm_client.query_stream(prompt, working_dir, options).await?;
+        let mut response = LlmResponse::default();
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+            response.text.push_str(&chunk.text);
+            if let Some(usage) = chunk.usage {
+                response.usage = usage;
+            }
+            if chunk.cache_key.is_some() {
+                response.cache_key = chunk.cache_key;
+            }
+            if !chunk.text.is_empty() {
+                on_chunk(&chunk.text, &response.text)?;
+            }
+        }
+        Ok::<_, anyhow::Error>(response)
+    }
+    .instrument(span.clone())
+    .await?;
+    record_usage(&span, &response.usage);
+    Ok(response)
 }
 
 pub struct ClaudeClient;
 
 #[async_trait]
 impl LlmClient for ClaudeClient {
-    async fn query(&self, prompt: String, working_dir: &Path) -> Result<String> {
+    /// The Claude CLI takes a single prompt and has no sampling options, so the
+    /// prompt is flattened and `options` go unused
+    async fn query(&self, prompt: Prompt, working_dir: &Path, _options: &LlmOptions) -> Result<LlmResponse> {
         log::debug!("LLM query from dir: {:?}", working_dir);
         log::debug!("Prompt length: {} chars", prompt.len());
+        let started = Instant::now();
         
         // Add a timeout to LLM queries to prevent hanging
         let output = tokio::time::timeout(
             std::time::Duration::from_secs(60),
             Command::new("claude")
                 .arg("--print")
-                .arg(prompt)
+                .arg(prompt.render())
                 .current_dir(working_dir)
                 .output()
         )
@@ -49,7 +158,13 @@ impl LlmClient for ClaudeClient {
             response
         };
         
-        Ok(cleaned)
+        // The CLI doesn't report tokens, only how long it took
+        Ok(LlmResponse::new(cleaned).with_usage(Usage {
+            provider: "claude".to_string(),
+            model: "claude".to_string(),
+            latency_ms: started.elapsed().as_millis() as u64,
+            ..Default::default()
+        }))
     }
 }
 
@@ -0,0 +1,47 @@
+use std::fmt;
+
+/// How much of a raw LLM response to keep when reporting it back to API clients
+const EXCERPT_CHARS: usize = 500;
+
+/// The LLM answered, but not with JSON we could use. Keeps the raw response for debugging.
+#[derive(Debug)]
+pub struct LlmParseError {
+    pub message: String,
+    pub raw_response: String,
+}
+
+impl LlmParseError {
+    pub fn new(message: impl Into<String>, raw_response: &str) -> Self {
+        Self {
+            message: message.into(),
+            raw_response: raw_response.to_string(),
+        }
+    }
+
+    /// The start of the raw response, short enough to put in an error body
+    pub fn excerpt(&self) -> String {
+        let mut excerpt: String = self.raw_response.chars().take(EXCERPT_CHARS).collect();
+        if self.raw_response.chars().count() > EXCERPT_CHARS {
+            excerpt.push('…');
+        }
+        excerpt
+    }
+}
+
+impl fmt::Display for LlmParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for LlmParseError {}
+
+/// The request to the LLM provider itself failed (timeout, connection, CLI error)
+#[derive(Debug)]
+pub struct LlmCallError;
+
+impl fmt::Display for LlmCallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LLM request failed")
+    }
+}
@@ -0,0 +1,203 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use prometheus::IntGauge;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+use super::{LlmClient, LlmOptions, LlmResponse, LlmStream, Prompt, Usage};
+use crate::config::LlmLimits;
+use crate::metrics::metrics;
+
+/// Puts every call to a provider through one queue: at most `max_in_flight` at once,
+/// and no more requests or tokens per minute than configured. Intents, GM resolution
+/// and memory updates in every world go through the same client, so they share it.
+pub struct LimitedLlmClient {
+    inner: Arc<dyn LlmClient>,
+    limiter: Arc<Limiter>,
+}
+
+struct Limiter {
+    provider: String,
+    slots: Arc<Semaphore>,
+    requests: Option<Mutex<Bucket>>,
+    tokens: Option<Mutex<Bucket>>,
+}
+
+/// Refills continuously up to a minute's allowance. Tokens can go into debt, since
+/// what a call really took is only known once it's done.
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    per_second: f64,
+    refilled_at: Instant,
+}
+
+/// A call's place in the queue, given back when dropped
+struct Slot {
+    limiter: Arc<Limiter>,
+    estimated_tokens: f64,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl LimitedLlmClient {
+    pub fn new(inner: Arc<dyn LlmClient>, provider: impl Into<String>, limits: &LlmLimits) -> Self {
+        Self {
+            inner,
+            limiter: Arc::new(Limiter {
+                provider: provider.into(),
+                slots: Arc::new(Semaphore::new(limits.max_in_flight)),
+                requests: limits.requests_per_minute.map(|rpm| Mutex::new(Bucket::per_minute(rpm as f64))),
+                tokens: limits.tokens_per_minute.map(|tpm| Mutex::new(Bucket::per_minute(tpm as f64))),
+            }),
+        }
+    }
+}
+
+impl Bucket {
+    fn per_minute(allowance: f64) -> Self {
+        Self {
+            capacity: allowance,
+            available: allowance,
+            per_second: allowance / 60.0,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.available = (self.available + elapsed * self.per_second).min(self.capacity);
+        self.refilled_at = now;
+    }
+
+    /// Take `amount`, or say how long until there's enough. A call bigger than the
+    /// whole allowance goes through once the bucket is full.
+    fn try_take(&mut self, amount: f64) -> Option<Duration> {
+        self.refill();
+        let needed = amount.min(self.capacity);
+        if self.available >= needed {
+            self.available -= amount;
+            None
+        } else {
+            Some(Duration::from_secs_f64((needed - self.available) / self.per_second))
+        }
+    }
+
+    fn charge(&mut self, amount: f64) {
+        self.refill();
+        self.available -= amount;
+    }
+}
+
+async fn take(bucket: &Mutex<Bucket>, amount: f64) {
+    loop {
+        let wait = bucket.lock().unwrap().try_take(amount);
+        match wait {
+            Some(wait) => tokio::time::sleep(wait).await,
+            None => return,
+        }
+    }
+}
+
+impl Limiter {
+    async fn acquire(self: &Arc<Self>, prompt: &Prompt) -> Slot {
+        let started = Instant::now();
+        let queued = Queued::join(&self.provider);
+
+        // Roughly four characters a token, until the provider says what it really was
+        let estimated_tokens = (prompt.len() / 4).max(1) as f64;
+        let permit = Arc::clone(&self.slots)
+            .acquire_owned()
+            .await
+            .expect("the LLM queue is never closed");
+        if let Some(requests) = &self.requests {
+            take(requests, 1.0).await;
+        }
+        if let Some(tokens) = &self.tokens {
+            take(tokens, estimated_tokens).await;
+        }
+        drop(queued);
+
+        let waited = started.elapsed();
+        metrics().llm_queue_wait.with_label_values(&[&self.provider]).observe(waited.as_secs_f64());
+        if waited >= Duration::from_secs(1) {
+            log::debug!("⏳ [LLM][{}] Waited {:.1}s for a slot", self.provider.to_uppercase(), waited.as_secs_f64());
+        }
+        metrics().llm_in_flight.with_label_values(&[&self.provider]).inc();
+
+        Slot {
+            limiter: Arc::clone(self),
+            estimated_tokens,
+            _permit: permit,
+        }
+    }
+}
+
+/// Counts a call as waiting until dropped, even if the caller gives up on it
+struct Queued(IntGauge);
+
+impl Queued {
+    fn join(provider: &str) -> Self {
+        let gauge = metrics().llm_queued.with_label_values(&[provider]);
+        gauge.inc();
+        Self(gauge)
+    }
+}
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+impl Slot {
+    /// Swap the estimate for what the call really took, if the provider reported it
+    fn settle(&self, usage: &Usage) {
+        let used = (usage.prompt_tokens + usage.completion_tokens) as f64;
+        if let Some(tokens) = &self.limiter.tokens
+            && used > 0.0
+        {
+            tokens.lock().unwrap().charge(used - self.estimated_tokens);
+        }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        metrics().llm_in_flight.with_label_values(&[&self.limiter.provider]).dec();
+    }
+}
+
+#[async_trait]
+impl LlmClient for LimitedLlmClient {
+    async fn query(&self, prompt: Prompt, working_dir: &Path, options: &LlmOptions) -> Result<LlmResponse> {
+        let slot = self.limiter.acquire(&prompt).await;
+        let response = self.inner.query(prompt, working_dir, options).await?;
+        slot.settle(&response.usage);
+        Ok(response)
+    }
+
+    async fn query_stream(&self, prompt: Prompt, working_dir: &Path, options: &LlmOptions) -> Result<LlmStream> {
+        let slot = self.limiter.acquire(&prompt).await;
+        let stream = self.inner.query_stream(prompt, working_dir, options).await?;
+
+        // The slot is held until the stream is finished or dropped
+        Ok(stream
+            .inspect(move |chunk| {
+                if let Ok(chunk) = chunk
+                    && let Some(usage) = &chunk.usage
+                {
+                    slot.settle(usage);
+                }
+            })
+            .boxed())
+    }
+
+    fn accept(&self, response: &LlmResponse) {
+        self.inner.accept(response);
+    }
+}
@@ -1,6 +1,18 @@
+pub mod cache;
 pub mod client;
+pub mod error;
+pub mod limiter;
 pub mod ollama;
+pub mod options;
 pub mod parser;
+pub mod prompt;
+pub mod usage;
 
-pub use client::{ClaudeClient, LlmClient};
-pub use ollama::OllamaClient;
\ No newline at end of file
+pub use cache::{CachingLlmClient, ResponseCache};
+pub use client::{traced_query, traced_stream_query, ClaudeClient, LlmChunk, LlmClient, LlmStream};
+pub use error::{LlmCallError, LlmParseError};
+pub use limiter::LimitedLlmClient;
+pub use ollama::OllamaClient;
+pub use options::{role_defaults, LlmOptions};
+pub use prompt::{Message, MessageRole, Prompt};
+pub use usage::{LlmResponse, LlmRole, Usage, UsageLedger, UsageReport};
@@ -2,34 +2,52 @@ use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use std::time::{Duration, Instant};
 
-use super::LlmClient;
+use super::{LlmChunk, LlmClient, LlmOptions, LlmResponse, LlmStream, Message, Prompt, Usage};
+use futures::stream::{self, StreamExt};
 
+#[derive(Clone)]
 pub struct OllamaClient {
     model: String,
     base_url: String,
+    top_p: f32,
+    timeout: Duration,
 }
 
 impl OllamaClient {
     pub fn new(model: impl Into<String>) -> Self {
-        Self {
-            model: model.into(),
-            base_url: "http://localhost:11434".to_string(),
-        }
+        Self::with_url(model, "http://localhost:11434")
     }
 
     pub fn with_url(model: impl Into<String>, base_url: impl Into<String>) -> Self {
         Self {
             model: model.into(),
             base_url: base_url.into(),
+            top_p: 0.9,
+            timeout: Duration::from_secs(60),
         }
     }
+
+    /// Top-p for calls whose options leave it unset. Temperature has no such
+    /// fallback: every role has its own, and a bare call gets the model's.
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        self.top_p = top_p;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
 }
 
+/// A chat request, so the system messages are kept apart and Ollama can reuse
+/// what it computed for them last time
 #[derive(Serialize)]
-struct OllamaRequest {
+struct OllamaRequest<'a> {
     model: String,
-    prompt: String,
+    messages: &'a [Message],
     stream: bool,
     format: String,
     options: OllamaOptions,
@@ -37,53 +55,157 @@ struct OllamaRequest {
 
 #[derive(Serialize)]
 struct OllamaOptions {
-    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
     top_p: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
 }
 
 #[derive(Deserialize)]
 struct OllamaResponse {
-    response: String,
+    #[serde(default)]
+    message: OllamaMessage,
+    #[serde(default)]
+    done: bool,              // Only the last line of a stream is done
+    #[serde(default)]
+    prompt_eval_count: u64,  // Prompt tokens
+    #[serde(default)]
+    eval_count: u64,         // Completion tokens
+    #[serde(default)]
+    error: Option<String>,   // Sent instead of a response if generation fails midway
 }
 
-#[async_trait]
-impl LlmClient for OllamaClient {
-    async fn query(&self, prompt: String, _working_dir: &Path) -> Result<String> {
+#[derive(Default, Deserialize)]
+struct OllamaMessage {
+    #[serde(default)]
+    content: String,
+}
+
+impl OllamaClient {
+    async fn send(&self, prompt: Prompt, options: &LlmOptions, stream: bool) -> Result<reqwest::Response> {
         log::debug!("Ollama query to model: {}", self.model);
         log::debug!("Prompt length: {} chars", prompt.len());
 
         let client = reqwest::Client::new();
-        
         let request = OllamaRequest {
             model: self.model.clone(),
-            prompt,
-            stream: false,
+            messages: &prompt.messages,
+            stream,
             format: "json".to_string(),
             options: OllamaOptions {
-                temperature: 0.7,
-                top_p: 0.9,
+                temperature: options.temperature,
+                top_p: options.top_p.unwrap_or(self.top_p),
+                num_predict: options.max_tokens,
+                seed: options.seed,
+                stop: options.stop.clone(),
             },
         };
 
         let response = tokio::time::timeout(
-            std::time::Duration::from_secs(60),
+            self.timeout,
             client
-                .post(format!("{}/api/generate", self.base_url))
+                .post(format!("{}/api/chat", self.base_url))
                 .json(&request)
                 .send()
         )
         .await
-        .map_err(|_| anyhow!("Ollama query timed out after 60 seconds"))??;
+        .map_err(|_| anyhow!("Ollama query timed out after {} seconds", self.timeout.as_secs()))??;
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
             return Err(anyhow!("Ollama request failed: {}", error_text));
         }
+        Ok(response)
+    }
+
+    fn usage(&self, response: &OllamaResponse, started: Instant) -> Usage {
+        Usage {
+            provider: "ollama".to_string(),
+            model: self.model.clone(),
+            prompt_tokens: response.prompt_eval_count,
+            completion_tokens: response.eval_count,
+            latency_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+/// A streaming response being read line by line; Ollama sends one JSON object per line
+struct OllamaStream {
+    client: OllamaClient,
+    response: reqwest::Response,
+    buffer: Vec<u8>,
+    started: Instant,
+    done: bool,
+}
+
+impl OllamaStream {
+    async fn next_chunk(mut self) -> Result<Option<(LlmChunk, Self)>> {
+        if self.done {
+            return Ok(None);
+        }
+        loop {
+            if let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                if line.trim_ascii().is_empty() {
+                    continue;
+                }
+                let piece: OllamaResponse = serde_json::from_slice(&line)
+                    .map_err(|e| anyhow!("Failed to parse Ollama stream line: {}", e))?;
+                if let Some(error) = piece.error {
+                    return Err(anyhow!("Ollama request failed: {}", error));
+                }
+                self.done = piece.done;
+                let chunk = LlmChunk {
+                    usage: piece.done.then(|| self.client.usage(&piece, self.started)),
+                    text: piece.message.content,
+                    cache_key: None,
+                };
+                return Ok(Some((chunk, self)));
+            }
+
+            let timeout = self.client.timeout;
+            let bytes = tokio::time::timeout(timeout, self.response.chunk())
+                .await
+                .map_err(|_| anyhow!("Ollama stream stalled for {} seconds", timeout.as_secs()))??;
+            match bytes {
+                Some(bytes) => self.buffer.extend_from_slice(&bytes),
+                None if self.buffer.trim_ascii().is_empty() => return Ok(None),
+                None => self.buffer.push(b'\n'),  // Last line without a newline
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for OllamaClient {
+    async fn query(&self, prompt: Prompt, _working_dir: &Path, options: &LlmOptions) -> Result<LlmResponse> {
+        let started = Instant::now();
+        let response = self.send(prompt, options, false).await?;
 
         let ollama_response: OllamaResponse = response.json().await
             .map_err(|e| anyhow!("Failed to parse Ollama response: {}", e))?;
 
-        Ok(ollama_response.response)
+        let usage = self.usage(&ollama_response, started);
+        Ok(LlmResponse::new(ollama_response.message.content).with_usage(usage))
+    }
+
+    async fn query_stream(&self, prompt: Prompt, _working_dir: &Path, options: &LlmOptions) -> Result<LlmStream> {
+        let started = Instant::now();
+        let response = self.send(prompt, options, true).await?;
+
+        let stream = OllamaStream {
+            client: self.clone(),
+            response,
+            buffer: Vec::new(),
+            started,
+            done: false,
+        };
+        Ok(stream::try_unfold(stream, OllamaStream::next_chunk).boxed())
     }
 }
 
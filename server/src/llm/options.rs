@@ -0,0 +1,62 @@
+use super::LlmRole;
+use serde::{Deserialize, Serialize};
+
+/// How one call should be sampled. Anything unset is left to the provider's own
+/// defaults, and providers that can't honor an option ignore it: the Claude CLI
+/// takes none of them, Ollama takes them all.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmOptions {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,  // Completion tokens before the answer is cut off
+    pub seed: Option<u64>,        // The same seed and prompt give the same answer
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,        // Generation ends at the first of these
+}
+
+impl LlmOptions {
+    /// These options, with anything they leave unset taken from `base`
+    pub fn or(&self, base: &LlmOptions) -> LlmOptions {
+        LlmOptions {
+            temperature: self.temperature.or(base.temperature),
+            top_p: self.top_p.or(base.top_p),
+            max_tokens: self.max_tokens.or(base.max_tokens),
+            seed: self.seed.or(base.seed),
+            stop: if self.stop.is_empty() { base.stop.clone() } else { self.stop.clone() },
+        }
+    }
+
+    /// Every problem with the options, each prefixed with `key`
+    pub fn validate(&self, key: &str) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Some(temperature) = self.temperature
+            && !(0.0..=2.0).contains(&temperature)
+        {
+            problems.push(format!("{key}.temperature: {temperature} is outside 0.0..=2.0"));
+        }
+        if let Some(top_p) = self.top_p
+            && !(top_p > 0.0 && top_p <= 1.0)
+        {
+            problems.push(format!("{key}.top_p: {top_p} is outside 0.0 (exclusive) to 1.0"));
+        }
+        if self.max_tokens == Some(0) {
+            problems.push(format!("{key}.max_tokens: must be at least 1, or unset"));
+        }
+        problems
+    }
+}
+
+/// What each kind of call gets unless configured otherwise: a steady GM and memory
+/// keeper, and NPCs free to surprise
+pub fn role_defaults(role: LlmRole) -> LlmOptions {
+    let temperature = match role {
+        LlmRole::Gm | LlmRole::GmRepair => 0.2,
+        LlmRole::Memory => 0.3,
+        LlmRole::Intent => 0.9,
+    };
+    LlmOptions {
+        temperature: Some(temperature),
+        ..LlmOptions::default()
+    }
+}
@@ -1,19 +1,37 @@
-use anyhow::{anyhow, Context, Result};
+use super::LlmParseError;
+use crate::metrics::metrics;
+use anyhow::Result;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 
+/// How much an LLM may write before a JSON object should have started
+const MAX_CHARS_BEFORE_JSON: usize = 400;
+
+/// Whether a response that's still being written can already be seen to have no JSON in it
+pub fn is_clearly_not_json(partial: &str) -> bool {
+    !partial.contains('{') && partial.chars().count() > MAX_CHARS_BEFORE_JSON
+}
+
 pub fn extract_json<T: DeserializeOwned>(response: &str) -> Result<T> {
+    parse_json(response).inspect_err(|_| {
+        // Just the type's own name, e.g. "GmResponse", to keep the label short
+        let expected = std::any::type_name::<T>().rsplit("::").next().unwrap_or_default();
+        metrics().llm_parse_failures.with_label_values(&[expected]).inc();
+    })
+}
+
+fn parse_json<T: DeserializeOwned>(response: &str) -> Result<T> {
     // First check if the response is wrapped in code blocks
     let cleaned = if response.contains("```json") {
         // Extract content between ```json and ```
         let start = response.find("```json").unwrap() + 7; // 7 = len("```json")
         let end = response.rfind("```").unwrap();
-        response[start..end].trim()
+        response[start..end.max(start)].trim()
     } else if response.contains("```") {
         // Extract content between ``` and ```
         let start = response.find("```").unwrap() + 3;
         let end = response.rfind("```").unwrap();
-        response[start..end].trim()
+        response[start..end.max(start)].trim()
     } else {
         response
     };
@@ -21,28 +39,32 @@ pub fn extract_json<T: DeserializeOwned>(response: &str) -> Result<T> {
     // Find JSON boundaries in the cleaned content
     let json_start = cleaned
         .find('{')
-        .ok_or_else(|| anyhow!("No JSON found in LLM response: {}", response))?;
+        .ok_or_else(|| LlmParseError::new("No JSON found in LLM response", response))?;
     
     let json_end = cleaned
         .rfind('}')
-        .ok_or_else(|| anyhow!("No closing brace found in LLM response: {}", response))?;
+        .filter(|end| *end > json_start)
+        .ok_or_else(|| LlmParseError::new("No closing brace found in LLM response", response))?;
     
     let json_str = &cleaned[json_start..=json_end];
     
     // First validate it's valid JSON
     let json_value: Value = serde_json::from_str(json_str)
-        .with_context(|| format!("LLM returned invalid JSON. Response: {}", response))?;
+        .map_err(|e| LlmParseError::new(format!("LLM returned invalid JSON: {e}"), response))?;
     
     // Log the parsed JSON for debugging
     log::debug!("Parsed JSON from LLM: {}", serde_json::to_string_pretty(&json_value)?);
     
     // Then try to deserialize to the target type
     serde_json::from_value::<T>(json_value.clone())
-        .with_context(|| {
-            format!(
-                "LLM JSON has incorrect format for type {}. Expected fields may be missing or have wrong types.\nReceived: {}",
-                std::any::type_name::<T>(),
-                serde_json::to_string_pretty(&json_value).unwrap_or_else(|_| json_str.to_string())
+        .map_err(|e| {
+            LlmParseError::new(
+                format!(
+                    "LLM JSON has incorrect format for type {}: {e}",
+                    std::any::type_name::<T>(),
+                ),
+                response,
             )
+            .into()
         })
-}
\ No newline at end of file
+}
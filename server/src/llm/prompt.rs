@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What separates the parts of a prompt once it's flattened into one string
+const SECTION_SEPARATOR: &str = "\n\n---\n\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageRole {
+    System,     // Instructions and who the model is playing; the same call after call
+    User,       // What the model is answering, and the context it needs for that
+    Assistant,  // What the model said earlier in the conversation
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Message {
+    pub role: MessageRole,
+    pub content: String,
+}
+
+/// A prompt as a conversation. Providers with a chat API get the messages as they
+/// are, so the unchanging system messages can be cached between calls; the rest
+/// get them flattened into one string with `render`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Prompt {
+    pub messages: Vec<Message>,
+}
+
+impl Prompt {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn system(self, content: impl Into<String>) -> Self {
+        self.with(MessageRole::System, content)
+    }
+
+    pub fn user(self, content: impl Into<String>) -> Self {
+        self.with(MessageRole::User, content)
+    }
+
+    pub fn assistant(self, content: impl Into<String>) -> Self {
+        self.with(MessageRole::Assistant, content)
+    }
+
+    fn with(mut self, role: MessageRole, content: impl Into<String>) -> Self {
+        self.messages.push(Message {
+            role,
+            content: content.into(),
+        });
+        self
+    }
+
+    /// Every message in order, as one string for providers that take a single prompt
+    pub fn render(&self) -> String {
+        self.messages
+            .iter()
+            .map(|message| message.content.as_str())
+            .collect::<Vec<_>>()
+            .join(SECTION_SEPARATOR)
+    }
+
+    /// Characters across all messages
+    pub fn len(&self) -> usize {
+        self.messages.iter().map(|message| message.content.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.iter().all(|message| message.content.is_empty())
+    }
+
+    /// Join sections into the content of a single message, the way they read when flattened
+    pub fn join_sections(sections: &[String]) -> String {
+        sections.join(SECTION_SEPARATOR)
+    }
+}
+
+impl fmt::Display for Prompt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render())
+    }
+}
+
+/// A plain prompt, asked as a single user message
+impl From<String> for Prompt {
+    fn from(content: String) -> Self {
+        Prompt::new().user(content)
+    }
+}
+
+impl From<&str> for Prompt {
+    fn from(content: &str) -> Self {
+        Prompt::new().user(content)
+    }
+}
@@ -0,0 +1,155 @@
+use crate::config::ModelPrice;
+use crate::llm::cache::CACHE_PROVIDER;
+use crate::metrics::metrics;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+
+/// What an LLM answered, and what answering cost
+#[derive(Debug, Clone, Default)]
+pub struct LlmResponse {
+    pub text: String,
+    pub usage: Usage,
+    pub cache_key: Option<String>,  // Set by a cache that will keep the response once accepted
+}
+
+impl LlmResponse {
+    /// A response from a provider that doesn't report usage
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            usage: Usage::default(),
+            cache_key: None,
+        }
+    }
+
+    pub fn with_usage(mut self, usage: Usage) -> Self {
+        self.usage = usage;
+        self
+    }
+}
+
+/// Tokens and time one LLM call took. Token counts stay 0 for providers that don't report them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Usage {
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub latency_ms: u64,
+}
+
+/// The part of a turn an LLM call was made for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlmRole {
+    Intent,
+    Gm,
+    GmRepair,
+    Memory,
+}
+
+impl LlmRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Intent => "intent",
+            Self::Gm => "gm",
+            Self::GmRepair => "gm_repair",
+            Self::Memory => "memory",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageTotals {
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub latency_ms: u64,
+    pub cost_usd: f64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, usage: &Usage, cost_usd: f64) {
+        self.calls += 1;
+        self.prompt_tokens += usage.prompt_tokens;
+        self.completion_tokens += usage.completion_tokens;
+        self.latency_ms += usage.latency_ms;
+        self.cost_usd += cost_usd;
+    }
+}
+
+/// Everything a world has spent on the LLM since the server started
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageReport {
+    pub total: UsageTotals,
+    pub by_turn: BTreeMap<u64, UsageTotals>,
+    pub by_npc: BTreeMap<String, UsageTotals>,  // Intent and memory calls; the GM is under by_role
+    pub by_role: BTreeMap<String, UsageTotals>,
+    pub by_model: BTreeMap<String, UsageTotals>,
+    pub unpriced_models: BTreeSet<String>,  // Used, but missing from llm.prices, so counted at no cost
+}
+
+/// Running totals of LLM usage, priced with the configured price table
+#[derive(Debug, Default)]
+pub struct UsageLedger {
+    prices: BTreeMap<String, ModelPrice>,
+    report: Mutex<UsageReport>,
+}
+
+impl UsageLedger {
+    pub fn new(prices: BTreeMap<String, ModelPrice>) -> Self {
+        Self {
+            prices,
+            report: Mutex::default(),
+        }
+    }
+
+    pub fn record(&self, turn: u64, role: LlmRole, npc: Option<&str>, usage: &Usage) {
+        let cost_usd = match self.prices.get(&usage.model) {
+            Some(price) => {
+                (usage.prompt_tokens as f64 * price.prompt_per_million
+                    + usage.completion_tokens as f64 * price.completion_per_million)
+                    / 1_000_000.0
+            }
+            None => 0.0,
+        };
+        log::debug!(
+            "💰 [Usage][{}] {} {}: {} prompt + {} completion tokens in {}ms (${cost_usd:.6})",
+            npc.unwrap_or("GM").to_uppercase(),
+            role.as_str(),
+            usage.model,
+            usage.prompt_tokens,
+            usage.completion_tokens,
+            usage.latency_ms
+        );
+
+        let provider = if usage.provider.is_empty() { "unknown" } else { usage.provider.as_str() };
+        // A cache hit is no call at all, so it would only drag the latencies down
+        if provider == CACHE_PROVIDER {
+            metrics().llm_cache_hits.with_label_values(&[role.as_str()]).inc();
+        } else {
+            metrics()
+                .llm_call_duration
+                .with_label_values(&[role.as_str(), provider])
+                .observe(usage.latency_ms as f64 / 1000.0);
+        }
+
+        let mut report = self.report.lock().unwrap();
+        report.total.add(usage, cost_usd);
+        report.by_turn.entry(turn).or_default().add(usage, cost_usd);
+        report.by_role.entry(role.as_str().to_string()).or_default().add(usage, cost_usd);
+        if let Some(npc) = npc {
+            report.by_npc.entry(npc.to_string()).or_default().add(usage, cost_usd);
+        }
+        if !usage.model.is_empty() {
+            report.by_model.entry(usage.model.clone()).or_default().add(usage, cost_usd);
+            if !self.prices.contains_key(&usage.model) {
+                report.unpriced_models.insert(usage.model.clone());
+            }
+        }
+    }
+
+    pub fn report(&self) -> UsageReport {
+        self.report.lock().unwrap().clone()
+    }
+}
@@ -1,53 +1,72 @@
-use env_logger::Builder;
-use log::LevelFilter;
-use std::env;
-use std::io::Write;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tracing::{Event, Level, Subscriber};
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::fmt::format::{FmtSpan, Writer};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
 
-pub fn init_logger() {
-    let mut builder = Builder::new();
-    
-    // Check if RUST_LOG is set, otherwise use default
-    if env::var("RUST_LOG").is_ok() {
-        builder.parse_env("RUST_LOG");
-    } else {
-        // Default to info level for the server crate
-        builder.filter_module("server", LevelFilter::Info);
-    }
-    
-    // Custom format for cleaner output
-    builder.format(|buf, record| {
-        let level = record.level();
-        let target = record.target();
-        
-        // For info level, use minimal formatting
-        if level == log::Level::Info && !target.contains("debug") {
-            writeln!(buf, "» {}", record.args())
-        } else {
-            // For debug/trace, include more context
-            writeln!(
-                buf,
-                "[{} {}] {}",
-                chrono::Local::now().format("%H:%M:%S"),
-                level,
-                record.args()
-            )
-        }
-    });
-    
-    builder.init();
+/// Used when no filter is configured
+const DEFAULT_FILTER: &str = "server=info";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// The console format: game events as plain lines, details with a timestamp
+    #[default]
+    Human,
+    /// One JSON object per line, with the turn/phase/NPC spans each event happened in
+    Json,
 }
 
-// Convenience macros for game events
-#[macro_export]
-macro_rules! game_event {
-    ($($arg:tt)*) => {
-        log::info!($($arg)*)
-    };
+/// Set up logging with a RUST_LOG style filter, or info for the server crate if none is given.
+/// `log` records from anywhere are picked up too, inside whatever span is current.
+pub fn init_logger(filter: Option<&str>, format: LogFormat) {
+    let filter = EnvFilter::try_new(filter.unwrap_or(DEFAULT_FILTER)).unwrap_or_else(|e| {
+        eprintln!("Invalid log filter, using {DEFAULT_FILTER}: {e}");
+        EnvFilter::new(DEFAULT_FILTER)
+    });
+    let registry = tracing_subscriber::registry().with(filter);
+
+    match format {
+        LogFormat::Human => registry
+            .with(tracing_subscriber::fmt::layer().event_format(HumanFormat))
+            .init(),
+        LogFormat::Json => registry
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true)
+                    // Closing spans report how long they were busy, e.g. each LLM call
+                    .with_span_events(FmtSpan::CLOSE),
+            )
+            .init(),
+    }
 }
 
-#[macro_export]
-macro_rules! game_debug {
-    ($($arg:tt)*) => {
-        log::debug!($($arg)*)
-    };
-}
\ No newline at end of file
+/// Game events at info level print as they are, for reading along with a turn;
+/// everything else gets a timestamp and level
+struct HumanFormat;
+
+impl<S, N> FormatEvent<S, N> for HumanFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let normalized = event.normalized_metadata();
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+
+        if *metadata.level() == Level::INFO && !metadata.target().contains("debug") {
+            write!(writer, "» ")?;
+        } else {
+            write!(writer, "[{} {}] ", chrono::Local::now().format("%H:%M:%S"), metadata.level())?;
+        }
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
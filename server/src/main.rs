@@ -1,36 +1,39 @@
 use std::sync::Arc;
+use clap::Parser;
 use server::{
-    AppState, ClaudeClient, OllamaClient, GameStateManager, LlmClient, PromptBuilder, PromptLoader,
-    create_router, logging,
+    config::Overrides, llm::{CachingLlmClient, LimitedLlmClient, LlmOptions, ResponseCache}, AppState, ClaudeClient, Config, DataDir, OllamaClient, GameStateManager, LlmClient,
+    PromptBuilder, PromptLoader, create_router, logging,
 };
 
 #[tokio::main]
 async fn main() {
-    // Load .env file if it exists
+    // Load .env file if it exists, so its variables count as environment overrides
     dotenv::dotenv().ok();
     
+    // Config file, then environment variables, then command line flags
+    let overrides = Overrides::parse();
+    let config = match Config::load(&overrides) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("❌ {e:#}");
+            std::process::exit(1);
+        }
+    };
+    
     // Initialize logger
-    logging::init_logger();
+    logging::init_logger(config.logging.level.as_deref(), config.logging.format);
     
-    let addr = "0.0.0.0:3000";
+    // Everything the world keeps on disk lives under one data directory
+    let data_dir = DataDir::new(config.paths.data_dir.clone());
+    log::info!("📁 [Server][System] Using data directory: {:?}", data_dir.root());
 
     // Initialize game state
-    let game_manager = GameStateManager::new();
+    let game_manager = GameStateManager::from_config(&config);
     
-    // Initialize LLM client based on environment variable
-    let llm_provider = match std::env::var("LLM_PROVIDER") {
-        Ok(provider) => provider,
-        Err(_) => {
-            log::error!("❌ [Server][LLM] No LLM provider configured!");
-            log::error!("");
-            log::error!("Please run the setup script first:");
-            log::error!("  ./bin/setup-llm.sh");
-            log::error!("");
-            log::error!("This will help you choose and configure an LLM provider.");
-            log::error!("Available providers: claude, ollama");
-            std::process::exit(1);
-        }
-    };
+    // Initialize LLM client; validation guarantees a known provider
+    let llm_provider = config.llm.provider.clone().unwrap_or_default();
+    let ollama = &config.llm.ollama;
+    let ollama_model = config.llm.model.clone().unwrap_or_else(|| "llama3.2:latest".to_string());
 
     let llm_client: Arc<dyn LlmClient> = match llm_provider.as_str() {
         "claude" => {
@@ -52,19 +55,18 @@ async fn main() {
                 std::process::exit(1);
             }
             
-            if let Ok(model) = std::env::var("LLM_MODEL") {
+            if let Some(model) = &config.llm.model {
                 log::info!("🤖 [Server][LLM] Model: {}", model);
             }
             Arc::new(ClaudeClient)
         }
-        "ollama" => {
+        _ => {
             log::info!("🤖 [Server][LLM] Using Ollama provider");
-            let model = std::env::var("LLM_MODEL").unwrap_or_else(|_| "llama3.2:latest".to_string());
-            log::info!("🤖 [Server][LLM] Model: {}", model);
+            log::info!("🤖 [Server][LLM] Model: {}", ollama_model);
             
             // Check if Ollama is running
-            if let Err(_) = server::llm::ollama::check_ollama_status("http://localhost:11434").await {
-                log::error!("❌ [Server][LLM] Ollama is not running!");
+            if server::llm::ollama::check_ollama_status(&ollama.url).await.is_err() {
+                log::error!("❌ [Server][LLM] Ollama is not running at {}!", ollama.url);
                 log::error!("");
                 log::error!("Please start Ollama service first:");
                 log::error!("  sudo systemctl start ollama");
@@ -78,15 +80,11 @@ async fn main() {
             }
             
             log::info!("✅ [Server][LLM] Ollama is running");
-            Arc::new(OllamaClient::new(model))
-        }
-        provider => {
-            log::error!("❌ [Server][LLM] Unknown provider: {}", provider);
-            log::error!("");
-            log::error!("Supported providers: claude, ollama");
-            log::error!("");
-            log::error!("Please run ./bin/setup-llm.sh to configure a valid provider.");
-            std::process::exit(1);
+            Arc::new(
+                OllamaClient::with_url(ollama_model.clone(), ollama.url.clone())
+                    .with_top_p(ollama.top_p)
+                    .with_timeout(std::time::Duration::from_secs(ollama.timeout_secs)),
+            )
         }
     };
     
@@ -101,7 +99,7 @@ async fn main() {
     };
     let test_dir = std::env::current_dir().unwrap();
     
-    match llm_client.query(test_prompt, &test_dir).await {
+    match llm_client.query(test_prompt.into(), &test_dir, &LlmOptions::default()).await {
         Ok(_) => {
             log::info!("✅ [Server][LLM] LLM connection successful");
         }
@@ -112,7 +110,7 @@ async fn main() {
             match llm_provider.as_str() {
                 "ollama" => {
                     log::error!("The model might not be downloaded. Pull it with:");
-                    log::error!("  ollama pull {}", std::env::var("LLM_MODEL").unwrap_or_else(|_| "llama3.2:latest".to_string()));
+                    log::error!("  ollama pull {}", ollama_model);
                 }
                 "claude" => {
                     log::error!("Is Claude CLI installed and configured?");
@@ -129,18 +127,36 @@ async fn main() {
         }
     }
     
+    // Every world's calls queue through the same limits from here on
+    let limits = config.llm.limits_for(&llm_provider);
+    log::info!(
+        "🚦 [Server][LLM] Up to {} calls at once, {} requests and {} tokens a minute",
+        limits.max_in_flight,
+        limits.requests_per_minute.map_or("unlimited".to_string(), |rpm| rpm.to_string()),
+        limits.tokens_per_minute.map_or("unlimited".to_string(), |tpm| tpm.to_string()),
+    );
+    let llm_client: Arc<dyn LlmClient> = Arc::new(LimitedLlmClient::new(llm_client, &llm_provider, &limits));
+
+    // Cached answers skip the queue too
+    let llm_client: Arc<dyn LlmClient> = if config.llm.cache.enabled {
+        let fingerprint = match llm_provider.as_str() {
+            "claude" => format!("claude {}", config.llm.model.as_deref().unwrap_or("default")),
+            _ => format!("ollama {} top_p={}", ollama_model, ollama.top_p),
+        };
+        log::info!("💾 [Server][LLM] Caching responses in {:?} for {}s", config.llm.cache.dir, config.llm.cache.ttl_secs);
+        Arc::new(CachingLlmClient::new(llm_client, ResponseCache::from_config(&config.llm.cache), fingerprint))
+    } else {
+        llm_client
+    };
+
     // Initialize prompt system
-    let data_dir = std::env::current_dir()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("data");
-    log::debug!("Using data directory: {:?}", data_dir);
     let prompt_loader = PromptLoader::new(data_dir);
     let prompt_builder = PromptBuilder::new(prompt_loader);
 
     // Create shared app state
+    let addr = config.server.bind.clone();
     let app_state = Arc::new(AppState {
+        config,
         game_manager,
         llm_client,
         prompt_builder,
@@ -150,7 +166,7 @@ async fn main() {
     let app = create_router(app_state);
 
     // Start server
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
     log::info!("🚀 [Server][System] Two Animals server running on http://{addr}");
     
     // Run server with graceful shutdown
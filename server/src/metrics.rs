@@ -0,0 +1,152 @@
+use prometheus::{
+    exponential_buckets, Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use std::sync::LazyLock;
+
+/// Operational metrics for the whole process, scraped from `/metrics`.
+/// Worlds share them; per-world LLM spend is under `/metrics/usage`.
+pub struct Metrics {
+    registry: Registry,
+    pub turns: IntCounterVec,                 // outcome: completed, failed
+    pub turn_duration: HistogramVec,          // outcome
+    pub llm_call_duration: HistogramVec,      // role, provider
+    pub llm_parse_failures: IntCounterVec,    // expected: the type the LLM should have answered with
+    pub llm_queued: IntGaugeVec,              // provider
+    pub llm_in_flight: IntGaugeVec,           // provider
+    pub llm_queue_wait: HistogramVec,         // provider
+    pub llm_cache_lookups: IntCounterVec,     // result: hit, miss
+    pub llm_cache_hits: IntCounterVec,        // role
+    pub intents_dropped: IntCounter,
+    pub gm_corrections: IntCounter,
+    pub active_contracts: IntGauge,
+    pub memory_evictions: IntCounterVec,      // kind: self, relationship
+    pub http_requests: IntCounterVec,         // method, route, status
+    pub http_request_duration: HistogramVec,  // method, route
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        // Seconds, from 50ms up to about 7 minutes for a slow turn
+        let slow_buckets = exponential_buckets(0.05, 2.0, 14).unwrap();
+        // Seconds, from 1ms up to about 16s for a request
+        let fast_buckets = exponential_buckets(0.001, 2.0, 15).unwrap();
+
+        let metrics = Self {
+            turns: IntCounterVec::new(
+                Opts::new("two_animals_turns_total", "Turns run, by outcome"),
+                &["outcome"],
+            )
+            .unwrap(),
+            turn_duration: HistogramVec::new(
+                HistogramOpts::new("two_animals_turn_duration_seconds", "How long turns take, by outcome")
+                    .buckets(slow_buckets.clone()),
+                &["outcome"],
+            )
+            .unwrap(),
+            llm_call_duration: HistogramVec::new(
+                HistogramOpts::new("two_animals_llm_call_duration_seconds", "LLM call latency, by role and provider")
+                    .buckets(slow_buckets),
+                &["role", "provider"],
+            )
+            .unwrap(),
+            llm_parse_failures: IntCounterVec::new(
+                Opts::new("two_animals_llm_parse_failures_total", "LLM responses that weren't usable JSON"),
+                &["expected"],
+            )
+            .unwrap(),
+            llm_queued: IntGaugeVec::new(
+                Opts::new("two_animals_llm_queued_calls", "LLM calls waiting for the provider's limits"),
+                &["provider"],
+            )
+            .unwrap(),
+            llm_in_flight: IntGaugeVec::new(
+                Opts::new("two_animals_llm_in_flight_calls", "LLM calls the provider is working on"),
+                &["provider"],
+            )
+            .unwrap(),
+            llm_queue_wait: HistogramVec::new(
+                HistogramOpts::new("two_animals_llm_queue_wait_seconds", "How long LLM calls wait before being sent")
+                    .buckets(fast_buckets.clone()),
+                &["provider"],
+            )
+            .unwrap(),
+            llm_cache_lookups: IntCounterVec::new(
+                Opts::new("two_animals_llm_cache_lookups_total", "Prompts looked up in the response cache, by result"),
+                &["result"],
+            )
+            .unwrap(),
+            llm_cache_hits: IntCounterVec::new(
+                Opts::new("two_animals_llm_cache_hits_total", "LLM calls answered from the response cache, by role"),
+                &["role"],
+            )
+            .unwrap(),
+            intents_dropped: IntCounter::new(
+                "two_animals_intents_dropped_total",
+                "NPC intents lost to a failed LLM call or unreadable response",
+            )
+            .unwrap(),
+            gm_corrections: IntCounter::new(
+                "two_animals_gm_corrections_total",
+                "Parts of GM responses dropped because they contradicted the world",
+            )
+            .unwrap(),
+            active_contracts: IntGauge::new("two_animals_active_contracts", "Contracts open after the last resolution")
+                .unwrap(),
+            memory_evictions: IntCounterVec::new(
+                Opts::new("two_animals_memory_evictions_total", "Memories faded to make room, by kind"),
+                &["kind"],
+            )
+            .unwrap(),
+            http_requests: IntCounterVec::new(
+                Opts::new("two_animals_http_requests_total", "HTTP requests served"),
+                &["method", "route", "status"],
+            )
+            .unwrap(),
+            http_request_duration: HistogramVec::new(
+                HistogramOpts::new("two_animals_http_request_duration_seconds", "HTTP request latency")
+                    .buckets(fast_buckets),
+                &["method", "route"],
+            )
+            .unwrap(),
+            registry,
+        };
+
+        metrics.register(Box::new(metrics.turns.clone()));
+        metrics.register(Box::new(metrics.turn_duration.clone()));
+        metrics.register(Box::new(metrics.llm_call_duration.clone()));
+        metrics.register(Box::new(metrics.llm_parse_failures.clone()));
+        metrics.register(Box::new(metrics.llm_queued.clone()));
+        metrics.register(Box::new(metrics.llm_in_flight.clone()));
+        metrics.register(Box::new(metrics.llm_queue_wait.clone()));
+        metrics.register(Box::new(metrics.llm_cache_lookups.clone()));
+        metrics.register(Box::new(metrics.llm_cache_hits.clone()));
+        metrics.register(Box::new(metrics.intents_dropped.clone()));
+        metrics.register(Box::new(metrics.gm_corrections.clone()));
+        metrics.register(Box::new(metrics.active_contracts.clone()));
+        metrics.register(Box::new(metrics.memory_evictions.clone()));
+        metrics.register(Box::new(metrics.http_requests.clone()));
+        metrics.register(Box::new(metrics.http_request_duration.clone()));
+        metrics
+    }
+
+    fn register(&self, collector: Box<dyn prometheus::core::Collector>) {
+        self.registry.register(collector).expect("metric registered twice");
+    }
+
+    /// Everything in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            log::error!("Failed to encode metrics: {e}");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
@@ -0,0 +1,249 @@
+use crate::data_dir::DataDir;
+use crate::game::transaction::write_file;
+use crate::npcs::knowledge::{
+    fidelity, load_knowledge, save_knowledge, trust_from_relationship, Fact, FactSource,
+};
+use crate::npcs::memory_store::load_npc_memories;
+use crate::types::SharedInformation;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Each retelling loses a little certainty even between close friends
+const RETELLING_DECAY: f32 = 0.9;
+
+/// How sure an NPC is of something they said without having learned it
+const CLAIM_CONFIDENCE: f32 = 0.5;
+
+/// Below this trust a listener hears the information but doesn't believe it
+const MIN_TRUST_TO_BELIEVE: f32 = 0.35;
+
+/// The full history of one piece of information spreading through the world
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rumor {
+    pub id: String,
+    pub origin: String,
+    pub original_content: String,
+    pub first_turn: u64,
+    pub tellings: Vec<Telling>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Telling {
+    pub turn: u64,
+    pub from: String,
+    pub to: String,
+    pub content: String,
+    pub trust: f32,
+    pub confidence: f32,
+    pub fidelity: f32,
+    pub believed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RumorTrace {
+    #[serde(flatten)]
+    pub rumor: Rumor,
+    pub holders: BTreeMap<String, Fact>,  // What each NPC who knows it currently believes
+}
+
+/// Whether `rumor_id` has the shape `originate` gives ids (`rumor_t3_wolf_1`), so it
+/// can't name a file outside the rumors directory
+pub fn is_valid_rumor_id(rumor_id: &str) -> bool {
+    rumor_id.strip_prefix("rumor_").is_some_and(|rest| {
+        !rest.is_empty() && rest.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    })
+}
+
+fn rumor_path(data_dir: &DataDir, rumor_id: &str) -> PathBuf {
+    data_dir.rumors_dir().join(format!("{rumor_id}.json"))
+}
+
+pub fn load_rumor(data_dir: &DataDir, rumor_id: &str) -> Result<Option<Rumor>> {
+    if !is_valid_rumor_id(rumor_id) {
+        return Ok(None);
+    }
+    let path = rumor_path(data_dir, rumor_id);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path)?;
+    let rumor = serde_json::from_str(&content)
+        .with_context(|| format!("Corrupt rumor file {:?}", path))?;
+    Ok(Some(rumor))
+}
+
+fn save_rumor(data_dir: &DataDir, rumor: &Rumor) -> Result<()> {
+    std::fs::create_dir_all(data_dir.rumors_dir())?;
+    write_file(rumor_path(data_dir, &rumor.id), serde_json::to_string_pretty(rumor)?)?;
+    Ok(())
+}
+
+pub fn list_rumors(data_dir: &DataDir) -> Result<Vec<Rumor>> {
+    let dir = data_dir.rumors_dir();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut rumors = Vec::new();
+    for entry in std::fs::read_dir(&dir)?.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if let Some(id) = name.strip_suffix(".json")
+            && let Some(rumor) = load_rumor(data_dir, id)?
+        {
+            rumors.push(rumor);
+        }
+    }
+    rumors.sort_by(|a, b| a.first_turn.cmp(&b.first_turn).then(a.id.cmp(&b.id)));
+    Ok(rumors)
+}
+
+/// Follow a rumor from its origin through every retelling to what each NPC now believes
+pub fn trace_rumor<'a>(data_dir: &DataDir, rumor_id: &str, npc_names: impl IntoIterator<Item = &'a String>) -> Result<Option<RumorTrace>> {
+    let Some(rumor) = load_rumor(data_dir, rumor_id)? else {
+        return Ok(None);
+    };
+
+    let mut holders = BTreeMap::new();
+    for npc in npc_names {
+        if let Some(fact) = load_knowledge(data_dir, npc)?.get(rumor_id) {
+            holders.insert(npc.clone(), fact.clone());
+        }
+    }
+
+    Ok(Some(RumorTrace { rumor, holders }))
+}
+
+/// Start a new rumor with the NPC as its origin
+fn originate(
+    data_dir: &DataDir,
+    npc_name: &str,
+    content: &str,
+    turn: u64,
+    source: FactSource,
+    confidence: f32,
+) -> Result<Fact> {
+    let mut knowledge = load_knowledge(data_dir, npc_name)?;
+    let rumor_id = format!("rumor_t{turn}_{npc_name}_{}", knowledge.facts.len() + 1);
+
+    let fact = Fact {
+        rumor_id: rumor_id.clone(),
+        content: content.to_string(),
+        source,
+        confidence,
+        learned_turn: turn,
+        retellings: 0,
+        fidelity: 1.0,
+        believed: true,
+    };
+    knowledge.facts.push(fact.clone());
+    save_knowledge(data_dir, npc_name, &knowledge)?;
+
+    save_rumor(data_dir, &Rumor {
+        id: rumor_id,
+        origin: npc_name.to_string(),
+        original_content: content.to_string(),
+        first_turn: turn,
+        tellings: Vec::new(),
+    })?;
+
+    Ok(fact)
+}
+
+/// Record things an NPC witnessed first-hand so they can pass them on later
+pub fn record_witnessed(data_dir: &DataDir, npc_name: &str, contents: &[String], turn: u64) -> Result<Vec<String>> {
+    let mut rumor_ids = Vec::new();
+    for content in contents.iter().filter(|c| !c.trim().is_empty()) {
+        let fact = originate(data_dir, npc_name, content, turn, FactSource::Witnessed, 1.0)?;
+        log::debug!("{npc_name} learned {}: {content}", fact.rumor_id);
+        rumor_ids.push(fact.rumor_id);
+    }
+    Ok(rumor_ids)
+}
+
+/// Pass information the GM saw being shared in a contract from speaker to listeners.
+/// Whether a listener believes it depends on how much they trust the speaker.
+pub fn spread(data_dir: &DataDir, shared: &SharedInformation, participants: &[String], turn: u64) -> Result<()> {
+    let speaker = &shared.speaker;
+
+    // Information the speaker never learned (a guess, a lie, something unrecorded)
+    // starts a new rumor with them as its origin, as a claim rather than something seen
+    let known = match &shared.rumor_id {
+        Some(id) => load_knowledge(data_dir, speaker)?.get(id).cloned(),
+        None => None,
+    };
+    let speaker_fact = match known {
+        Some(fact) => fact,
+        None => originate(data_dir, speaker, &shared.content, turn, FactSource::Claimed, CLAIM_CONFIDENCE)?,
+    };
+
+    let mut rumor = match load_rumor(data_dir, &speaker_fact.rumor_id)? {
+        Some(rumor) => rumor,
+        None => Rumor {
+            id: speaker_fact.rumor_id.clone(),
+            origin: speaker.clone(),
+            original_content: speaker_fact.content.clone(),
+            first_turn: turn,
+            tellings: Vec::new(),
+        },
+    };
+
+    let listeners: Vec<&String> = if shared.listeners.is_empty() {
+        participants.iter().collect()
+    } else {
+        shared.listeners.iter().collect()
+    };
+
+    for listener in listeners.into_iter().filter(|l| *l != speaker) {
+        let memories = load_npc_memories(data_dir, listener)?;
+        let trust = trust_from_relationship(memories.relationships.get(speaker));
+        let believed = trust >= MIN_TRUST_TO_BELIEVE;
+        let confidence = (speaker_fact.confidence * trust * RETELLING_DECAY).clamp(0.0, 1.0);
+        let fidelity = fidelity(&rumor.original_content, &shared.content);
+
+        let mut knowledge = load_knowledge(data_dir, listener)?;
+        match knowledge.get_mut(&rumor.id) {
+            // Hearing it again only matters if this telling is more convincing
+            Some(existing) => {
+                if believed && confidence > existing.confidence {
+                    existing.content = shared.content.clone();
+                    existing.source = FactSource::ToldBy { npc: speaker.clone(), trust };
+                    existing.confidence = confidence;
+                    existing.fidelity = fidelity;
+                    existing.believed = true;
+                }
+            }
+            None => knowledge.facts.push(Fact {
+                rumor_id: rumor.id.clone(),
+                content: shared.content.clone(),
+                source: FactSource::ToldBy { npc: speaker.clone(), trust },
+                confidence,
+                learned_turn: turn,
+                retellings: speaker_fact.retellings + 1,
+                fidelity,
+                believed,
+            }),
+        }
+        save_knowledge(data_dir, listener, &knowledge)?;
+
+        let verdict = if believed { "believes it" } else { "doesn't believe it" };
+        log::info!(
+            "  🗣️ [Gossip] {} told {}: \"{}\" ({}, trust {:.2})",
+            speaker.to_uppercase(), listener.to_uppercase(), shared.content, verdict, trust
+        );
+
+        rumor.tellings.push(Telling {
+            turn,
+            from: speaker.clone(),
+            to: listener.clone(),
+            content: shared.content.clone(),
+            trust,
+            confidence,
+            fidelity,
+            believed,
+        });
+    }
+
+    save_rumor(data_dir, &rumor)
+}
@@ -1,100 +1,205 @@
-use crate::llm::{parser, LlmClient};
+use crate::game::feed::FeedEvent;
+use crate::llm::{parser, traced_stream_query, LlmCallError, LlmClient, LlmParseError, LlmRole};
 use crate::game::GameStateManager;
+use crate::metrics::metrics;
+use crate::npcs::{FailingNpc, PendingMemories};
 use crate::prompts::PromptBuilder;
-use crate::types::{Intent, Npc};
+use crate::types::{Contract, GameState, Intent, IntentFailure, Npc};
 use crate::utils::wrap_text;
+use anyhow::{Context, Result};
 use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::sync::Arc;
 
+/// Intents from every NPC that answered, plus the reason each other NPC didn't
+pub struct IntentCollection {
+    pub intents: Vec<Intent>,
+    pub failures: Vec<anyhow::Error>,  // Each carries a `FailingNpc` context
+}
+
+impl IntentCollection {
+    /// Serializable summary of each failure for turn results
+    pub fn failure_reports(&self) -> Vec<IntentFailure> {
+        self.failures
+            .iter()
+            .map(|e| IntentFailure {
+                npc: e.downcast_ref::<FailingNpc>().map(|f| f.0.clone()).unwrap_or_default(),
+                error: format!("{e:#}"),
+                llm_excerpt: e.downcast_ref::<LlmParseError>().map(LlmParseError::excerpt),
+            })
+            .collect()
+    }
+}
+
+/// How NPCs in the same interaction answer within a turn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContractResponses {
+    /// One after another, each hearing what the others just said and did
+    #[default]
+    Alternating,
+    /// All at once, each from the transcript so far
+    Simultaneous,
+}
+
+/// Ask every NPC for an intent. Free NPCs all answer at once; NPCs in an interaction
+/// answer as a group, sharing its transcript, and with `ContractResponses::Alternating`
+/// take turns so each replies to what the others just did. Each NPC waits for its
+/// own memories from the last turn if they're still in `pending`, and no one else's.
 pub async fn collect_intents(
     game_manager: &GameStateManager,
     llm_client: Arc<dyn LlmClient>,
     prompt_builder: &PromptBuilder,
-) -> Vec<Intent> {
-    let npcs_to_process = {
-        let game = game_manager.state.lock().unwrap();
-        game.npcs.clone()
-    };
-
-    let total_npcs = npcs_to_process.len();
-    log::debug!("Collecting intents from {total_npcs} NPCs in parallel");
-
+    pending: &PendingMemories,
+) -> IntentCollection {
     // Get current game state for prompt building
     let game_state = game_manager.get_state();
+    let game_state_turn = game_state.turn;
+    let groups = response_groups(&game_state, game_manager.contract_responses);
+
+    let total_npcs = game_state.npcs.len();
+    log::debug!("Collecting intents from {total_npcs} NPCs in {} groups", groups.len());
     
-    // Create futures for all NPCs
-    let intent_futures: Vec<_> = npcs_to_process
-        .iter()
-        .map(|(name, npc)| {
-            let name_clone = name.clone();
-            let npc_clone = npc.clone();
-            let game_state_clone = game_state.clone();
-            let llm_client_clone = Arc::clone(&llm_client);
-            let prompt_builder_ref = prompt_builder;
+    // Create futures for all groups, each asking its NPCs in order
+    let group_futures: Vec<_> = groups
+        .into_iter()
+        .map(|group| {
+            let game_state = &game_state;
+            let llm_client = &llm_client;
             
             async move {
-                collect_single_intent(
-                    name_clone, 
-                    npc_clone, 
-                    game_state_clone, 
-                    llm_client_clone,
-                    prompt_builder_ref
-                ).await
+                let mut earlier: Vec<Intent> = Vec::new();
+                let mut results = Vec::with_capacity(group.len());
+                for npc in group {
+                    let name = npc.name.clone();
+                    pending.wait_for(&name).await;
+                    let result = collect_single_intent(
+                        npc,
+                        game_state,
+                        &earlier,
+                        Arc::clone(llm_client),
+                        prompt_builder,
+                        game_manager
+                    ).await
+                    .inspect_err(|e| game_manager.feed.publish(FeedEvent::IntentFailed {
+                        turn: game_state_turn,
+                        npc: name.clone(),
+                        error: format!("{e:#}"),
+                    }))
+                    .context(FailingNpc(name));
+                    if let Ok(intent) = &result {
+                        earlier.push(intent.clone());
+                    }
+                    results.push(result);
+                }
+                results
             }
         })
         .collect();
 
-    // Wait for all intents to be collected in parallel
-    let results = join_all(intent_futures).await;
+    // Wait for all groups in parallel
+    let results = join_all(group_futures).await;
 
-    // Filter out None values and collect successful intents
-    let intents: Vec<Intent> = results.into_iter().flatten().collect();
+    // Split successful intents from failures
+    let mut collection = IntentCollection {
+        intents: Vec::new(),
+        failures: Vec::new(),
+    };
+    for result in results.into_iter().flatten() {
+        match result {
+            Ok(intent) => collection.intents.push(intent),
+            Err(e) => collection.failures.push(e),
+        }
+    }
 
-    let intent_count = intents.len();
-    log::debug!("All intents collected! Got {intent_count} intents");
+    let intent_count = collection.intents.len();
+    let failure_count = collection.failures.len();
+    metrics().intents_dropped.inc_by(failure_count as u64);
+    log::debug!("All intents collected! Got {intent_count} intents, {failure_count} failures");
     
-    intents
+    collection
+}
+
+/// The NPCs that answer together, in the order they answer. Everyone is on their
+/// own except participants of an interaction answering alternately, who go as one
+/// group; who goes first rotates from turn to turn.
+fn response_groups(game_state: &GameState, responses: ContractResponses) -> Vec<Vec<Npc>> {
+    let mut groups = Vec::new();
+    let mut grouped = HashSet::new();
+    if responses == ContractResponses::Alternating {
+        let mut contracts: Vec<&Contract> = game_state.contracts.values().collect();
+        contracts.sort_by(|a, b| a.id.cmp(&b.id));
+        for contract in contracts {
+            let mut group: Vec<Npc> = contract.participants
+                .iter()
+                .filter_map(|name| game_state.npcs.get(name))
+                .filter(|npc| npc.active_contract.as_ref() == Some(&contract.id) && !grouped.contains(&npc.name))
+                .cloned()
+                .collect();
+            if group.is_empty() {
+                continue;
+            }
+            let first = (game_state.turn as usize) % group.len();
+            group.rotate_left(first);
+            grouped.extend(group.iter().map(|npc| npc.name.clone()));
+            groups.push(group);
+        }
+    }
+
+    let mut free: Vec<&Npc> = game_state.npcs.values().filter(|npc| !grouped.contains(&npc.name)).collect();
+    free.sort_by(|a, b| a.name.cmp(&b.name));
+    groups.extend(free.into_iter().map(|npc| vec![npc.clone()]));
+    groups
 }
 
 async fn collect_single_intent(
-    name: String,
     npc: Npc,
-    game_state: crate::types::GameState,
+    game_state: &GameState,
+    earlier: &[Intent],  // What others in the NPC's interaction did before them this turn
     llm_client: Arc<dyn LlmClient>,
     prompt_builder: &PromptBuilder,
-) -> Option<Intent> {
+    game_manager: &GameStateManager,
+) -> Result<Intent> {
+    let name = npc.name.clone();
+    let turn = game_state.turn;
     log::debug!("Getting intent from {name}");
 
     // Build prompt using the prompt builder
-    let prompt = match prompt_builder.build_npc_intent_prompt(&npc, &game_state) {
-        Ok(p) => p,
-        Err(e) => {
-            log::error!("Failed to build prompt for {name}: {e}");
-            return None;
-        }
-    };
+    let prompt = prompt_builder.build_npc_intent_prompt_after(&npc, game_state, earlier)
+        .inspect_err(|e| log::error!("Failed to build prompt for {name}: {e}"))?;
 
     // Query LLM - use data directory for working dir
     log::info!("🎭 [Intent Collection][{}] Gathering intent...", name.to_uppercase());
-    let working_dir = std::path::Path::new("../data");
-    let response = match llm_client.query(prompt, working_dir).await {
-        Ok(resp) => resp,
-        Err(e) => {
-            log::error!("Failed to get response from LLM for {name}: {e}");
-            return None;
+    let working_dir = prompt_builder.data_dir().root();
+    // Streamed, so listeners see the thought take shape, and a response that's
+    // plainly not going to be JSON is cut off instead of waited out
+    let options = game_manager.sampling.options_for(LlmRole::Intent, Some(&name));
+    let response = traced_stream_query(&*llm_client, prompt, working_dir, &options, LlmRole::Intent, Some(&name), |chunk, so_far| {
+        if parser::is_clearly_not_json(so_far) {
+            metrics().llm_parse_failures.with_label_values(&["Intent"]).inc();
+            return Err(LlmParseError::new("LLM response isn't turning into JSON, stopped it early", so_far).into());
         }
-    };
+        game_manager.feed.publish(FeedEvent::IntentDelta { turn, npc: name.clone(), text: chunk.to_string() });
+        Ok(())
+    })
+    .await
+    .inspect_err(|e| log::error!("Failed to get response from LLM for {name}: {e}"))
+    .context(LlmCallError)?;
+    game_manager.usage.record(turn, LlmRole::Intent, Some(&name), &response.usage);
 
     // Parse response
-    match parser::extract_json::<Intent>(&response) {
-        Ok(intent) => {
-            let wrapped_action = wrap_text(&intent.action, 70, "     ");
-            log::info!("  💭 [Intent][{}]\n{}", name.to_uppercase(), wrapped_action);
-            Some(intent)
-        }
-        Err(e) => {
-            log::error!("Failed to parse intent from {name}: {e}");
-            None
-        }
-    }
-}
\ No newline at end of file
+    let intent = parser::extract_json::<Intent>(&response.text)
+        .inspect_err(|e| log::error!("Failed to parse intent from {name}: {e}"))?;
+    llm_client.accept(&response);
+
+    let wrapped_action = wrap_text(&intent.action, 70, "     ");
+    log::info!("  💭 [Intent][{}]\n{}", name.to_uppercase(), wrapped_action);
+    game_manager.feed.publish(FeedEvent::IntentReady {
+        turn,
+        npc: name.clone(),
+        thought: intent.thought.clone(),
+        action: intent.action.clone(),
+    });
+    Ok(intent)
+}
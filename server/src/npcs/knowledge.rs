@@ -0,0 +1,136 @@
+use crate::data_dir::DataDir;
+use crate::game::transaction::write_file;
+use crate::npcs::memory::RelationshipMemory;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Everything an NPC believes they know about the world, first- or second-hand
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KnowledgeBase {
+    pub facts: Vec<Fact>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fact {
+    pub rumor_id: String,         // Shared by every retelling of the same piece of information
+    pub content: String,          // The fact as this NPC understands it
+    pub source: FactSource,
+    pub confidence: f32,          // 0.0 to 1.0
+    pub learned_turn: u64,
+    pub retellings: u32,          // 0 = witnessed, 1 = heard from a witness, ...
+    pub fidelity: f32,            // Word overlap with the original account, 1.0 = verbatim
+    pub believed: bool,           // False when the teller wasn't trusted enough
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FactSource {
+    Witnessed,
+    ToldBy { npc: String, trust: f32 },
+    Claimed,  // Said aloud without having witnessed or been told it
+}
+
+// What the GM and prompts see of a fact
+#[derive(Debug, Clone, Serialize)]
+pub struct KnownFact {
+    pub rumor_id: String,
+    pub content: String,
+    pub confidence: f32,
+}
+
+impl KnowledgeBase {
+    pub fn get(&self, rumor_id: &str) -> Option<&Fact> {
+        self.facts.iter().find(|f| f.rumor_id == rumor_id)
+    }
+
+    pub fn get_mut(&mut self, rumor_id: &str) -> Option<&mut Fact> {
+        self.facts.iter_mut().find(|f| f.rumor_id == rumor_id)
+    }
+
+    /// Facts the NPC actually believes, most confident first
+    pub fn believed_facts(&self) -> Vec<&Fact> {
+        let mut facts: Vec<&Fact> = self.facts.iter().filter(|f| f.believed).collect();
+        facts.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        facts
+    }
+
+    pub fn known_facts(&self) -> Vec<KnownFact> {
+        self.believed_facts()
+            .into_iter()
+            .map(|f| KnownFact {
+                rumor_id: f.rumor_id.clone(),
+                content: f.content.clone(),
+                confidence: f.confidence,
+            })
+            .collect()
+    }
+}
+
+impl Fact {
+    /// Describe the fact the way the NPC would think about it
+    pub fn describe(&self) -> String {
+        let certainty = match self.confidence {
+            c if c >= 0.8 => "you're sure",
+            c if c >= 0.5 => "you're fairly sure",
+            c if c >= 0.25 => "you're not sure",
+            _ => "you doubt it",
+        };
+        match &self.source {
+            FactSource::Witnessed => format!("{} (you witnessed this)", self.content),
+            FactSource::ToldBy { npc, .. } => format!("{} ({} told you; {})", self.content, npc, certainty),
+            FactSource::Claimed => format!("{} (you said this; {})", self.content, certainty),
+        }
+    }
+}
+
+/// How much an NPC trusts another, from 0.0 (not at all) to 1.0 (completely),
+/// derived from their relationship memory. Strangers get a neutral 0.5.
+pub fn trust_from_relationship(relationship: Option<&RelationshipMemory>) -> f32 {
+    match relationship {
+        Some(rel) => {
+            let feeling = (rel.overall_bond + rel.current_sentiment) / 2.0;
+            ((feeling + 1.0) / 2.0).clamp(0.0, 1.0)
+        }
+        None => 0.5,
+    }
+}
+
+/// Share of words two accounts have in common (Jaccard similarity)
+pub fn fidelity(original: &str, retold: &str) -> f32 {
+    let words = |text: &str| -> std::collections::HashSet<String> {
+        text.split_whitespace()
+            .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+            .filter(|w| !w.is_empty())
+            .collect()
+    };
+    let original = words(original);
+    let retold = words(retold);
+    let union = original.union(&retold).count();
+    if union == 0 {
+        return 1.0;
+    }
+    original.intersection(&retold).count() as f32 / union as f32
+}
+
+fn knowledge_path(data_dir: &DataDir, npc_name: &str) -> PathBuf {
+    data_dir.npc_dir(npc_name).join("knowledge.json")
+}
+
+pub fn load_knowledge(data_dir: &DataDir, npc_name: &str) -> Result<KnowledgeBase> {
+    let path = knowledge_path(data_dir, npc_name);
+    if !path.exists() {
+        return Ok(KnowledgeBase::default());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+pub fn save_knowledge(data_dir: &DataDir, npc_name: &str, knowledge: &KnowledgeBase) -> Result<()> {
+    let path = knowledge_path(data_dir, npc_name);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    write_file(path, serde_json::to_string_pretty(knowledge)?)?;
+    Ok(())
+}
@@ -31,6 +31,46 @@ pub struct Memory {
     pub timestamp: DateTime<Utc>,
     pub emotional_impact: String,
     pub importance: f32,  // 0.0 to 1.0
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,     // Pinned memories never fade
+}
+
+impl MemorySystem {
+    /// Check that every value is within the ranges the memory prompts promise the LLM
+    pub fn validate(&self, owner: &str) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        for (i, event) in self.self_memories.recent_events.iter().enumerate() {
+            if event.trim().is_empty() {
+                errors.push(format!("self_memories.recent_events[{i}] is empty"));
+            }
+        }
+
+        for (other, relationship) in &self.relationships {
+            if other.is_empty() {
+                errors.push("relationship name must not be empty".to_string());
+            }
+            if other == owner {
+                errors.push(format!("{owner} cannot have a relationship with themselves"));
+            }
+            if !(-1.0..=1.0).contains(&relationship.current_sentiment) {
+                errors.push(format!("relationships.{other}.current_sentiment must be between -1.0 and 1.0"));
+            }
+            if !(-1.0..=1.0).contains(&relationship.overall_bond) {
+                errors.push(format!("relationships.{other}.overall_bond must be between -1.0 and 1.0"));
+            }
+            for (i, memory) in relationship.recent_memories.iter().enumerate() {
+                if memory.event.trim().is_empty() {
+                    errors.push(format!("relationships.{other}.recent_memories[{i}].event is empty"));
+                }
+                if !(0.0..=1.0).contains(&memory.importance) {
+                    errors.push(format!("relationships.{other}.recent_memories[{i}].importance must be between 0.0 and 1.0"));
+                }
+            }
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
 }
 
 // Input from LLM when updating memories
@@ -39,17 +79,39 @@ pub struct MemoryUpdate {
     pub immediate_self_context: String,
     pub new_self_memory: Option<String>,
     pub relationship_updates: HashMap<String, RelationshipUpdate>,
+    #[serde(default)]
+    pub notable_facts: Vec<String>,  // Witnessed information worth passing on
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RelationshipUpdate {
     pub immediate_context: String,
-    pub new_memory: Option<Memory>,
+    pub new_memory: Option<NewMemory>,
     pub current_sentiment: f32,
     pub long_term_summary_update: Option<String>,
     pub potential_core_memory: Option<String>,
 }
 
+// A memory as the LLM reports it; the server stamps it with the world time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewMemory {
+    pub event: String,
+    pub emotional_impact: String,
+    pub importance: f32,
+}
+
+impl NewMemory {
+    pub fn at(self, timestamp: DateTime<Utc>) -> Memory {
+        Memory {
+            event: self.event,
+            timestamp,
+            emotional_impact: self.emotional_impact,
+            importance: self.importance,
+            pinned: false,
+        }
+    }
+}
+
 // Used when a memory needs to fade
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FadeDecision {
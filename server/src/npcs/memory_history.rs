@@ -0,0 +1,183 @@
+use crate::npcs::memory::{Memory, MemorySystem, RelationshipMemory};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A snapshot of an NPC's memories, written every time the memories are saved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryVersion {
+    pub version: u32,
+    pub turn: u64,
+    pub timestamp: DateTime<Utc>,
+    pub reason: String,
+    pub memories: MemorySystem,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryVersionSummary {
+    pub version: u32,
+    pub turn: u64,
+    pub timestamp: DateTime<Utc>,
+    pub reason: String,
+}
+
+impl From<&MemoryVersion> for MemoryVersionSummary {
+    fn from(version: &MemoryVersion) -> Self {
+        Self {
+            version: version.version,
+            turn: version.turn,
+            timestamp: version.timestamp,
+            reason: version.reason.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Change<T> {
+    pub from: T,
+    pub to: T,
+}
+
+fn change<T: PartialEq + Clone>(from: &T, to: &T) -> Option<Change<T>> {
+    (from != to).then(|| Change { from: from.clone(), to: to.clone() })
+}
+
+fn added<T: PartialEq + Clone>(from: &[T], to: &[T]) -> Vec<T> {
+    to.iter().filter(|item| !from.contains(item)).cloned().collect()
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct SelfMemoryDiff {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub immediate_context: Option<Change<String>>,
+    pub events_added: Vec<String>,
+    pub events_removed: Vec<String>,
+    pub core_memories_formed: Vec<String>,
+    pub core_memories_removed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct RelationshipDiff {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub immediate_context: Option<Change<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sentiment: Option<Change<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overall_bond: Option<Change<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub long_term_summary: Option<Change<String>>,
+    pub memories_added: Vec<Memory>,
+    pub memories_faded: Vec<Memory>,
+    pub core_memories_formed: Vec<String>,
+    pub core_memories_removed: Vec<String>,
+}
+
+impl RelationshipDiff {
+    fn is_empty(&self) -> bool {
+        self.immediate_context.is_none()
+            && self.sentiment.is_none()
+            && self.overall_bond.is_none()
+            && self.long_term_summary.is_none()
+            && self.memories_added.is_empty()
+            && self.memories_faded.is_empty()
+            && self.core_memories_formed.is_empty()
+            && self.core_memories_removed.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryDiff {
+    pub from: MemoryVersionSummary,
+    pub to: MemoryVersionSummary,
+    pub self_memories: SelfMemoryDiff,
+    pub relationships_added: Vec<String>,
+    pub relationships_removed: Vec<String>,
+    pub relationship_changes: BTreeMap<String, RelationshipDiff>,
+}
+
+fn empty_relationship() -> RelationshipMemory {
+    RelationshipMemory {
+        immediate_context: String::new(),
+        recent_memories: Vec::new(),
+        long_term_summary: String::new(),
+        core_memories: Vec::new(),
+        current_sentiment: 0.0,
+        overall_bond: 0.0,
+    }
+}
+
+fn memory_key(memory: &Memory) -> (&str, DateTime<Utc>) {
+    (memory.event.as_str(), memory.timestamp)
+}
+
+fn diff_relationship(from: &RelationshipMemory, to: &RelationshipMemory) -> RelationshipDiff {
+    let from_keys: Vec<_> = from.recent_memories.iter().map(memory_key).collect();
+    let to_keys: Vec<_> = to.recent_memories.iter().map(memory_key).collect();
+
+    RelationshipDiff {
+        immediate_context: change(&from.immediate_context, &to.immediate_context),
+        sentiment: change(&from.current_sentiment, &to.current_sentiment),
+        overall_bond: change(&from.overall_bond, &to.overall_bond),
+        long_term_summary: change(&from.long_term_summary, &to.long_term_summary),
+        memories_added: to.recent_memories
+            .iter()
+            .filter(|m| !from_keys.contains(&memory_key(m)))
+            .cloned()
+            .collect(),
+        memories_faded: from.recent_memories
+            .iter()
+            .filter(|m| !to_keys.contains(&memory_key(m)))
+            .cloned()
+            .collect(),
+        core_memories_formed: added(&from.core_memories, &to.core_memories),
+        core_memories_removed: added(&to.core_memories, &from.core_memories),
+    }
+}
+
+/// Describe everything that changed in an NPC's memories between two versions
+pub fn diff_versions(from: &MemoryVersion, to: &MemoryVersion) -> MemoryDiff {
+    let old = &from.memories;
+    let new = &to.memories;
+
+    let self_memories = SelfMemoryDiff {
+        immediate_context: change(&old.self_memories.immediate_context, &new.self_memories.immediate_context),
+        events_added: added(&old.self_memories.recent_events, &new.self_memories.recent_events),
+        events_removed: added(&new.self_memories.recent_events, &old.self_memories.recent_events),
+        core_memories_formed: added(&old.self_memories.core_memories, &new.self_memories.core_memories),
+        core_memories_removed: added(&new.self_memories.core_memories, &old.self_memories.core_memories),
+    };
+
+    let mut relationships_added: Vec<String> = new.relationships
+        .keys()
+        .filter(|name| !old.relationships.contains_key(*name))
+        .cloned()
+        .collect();
+    relationships_added.sort();
+
+    let mut relationships_removed: Vec<String> = old.relationships
+        .keys()
+        .filter(|name| !new.relationships.contains_key(*name))
+        .cloned()
+        .collect();
+    relationships_removed.sort();
+
+    // New relationships are diffed against an empty one so their first memories show up
+    let empty = empty_relationship();
+    let relationship_changes = new.relationships
+        .iter()
+        .map(|(name, relationship)| {
+            let previous = old.relationships.get(name).unwrap_or(&empty);
+            (name.clone(), diff_relationship(previous, relationship))
+        })
+        .filter(|(_, diff)| !diff.is_empty())
+        .collect();
+
+    MemoryDiff {
+        from: from.into(),
+        to: to.into(),
+        self_memories,
+        relationships_added,
+        relationships_removed,
+        relationship_changes,
+    }
+}
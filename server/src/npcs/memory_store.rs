@@ -0,0 +1,161 @@
+use crate::data_dir::DataDir;
+use crate::config::MemoryPolicy;
+use crate::game::transaction::{remove_file, write_file};
+use crate::npcs::memory::{MemorySystem, SelfMemories};
+use crate::npcs::memory_history::{MemoryVersion, MemoryVersionSummary};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+fn history_dir(data_dir: &DataDir, npc_name: &str) -> PathBuf {
+    data_dir.npc_dir(npc_name).join("memory_history")
+}
+
+fn version_path(data_dir: &DataDir, npc_name: &str, version: u32) -> PathBuf {
+    history_dir(data_dir, npc_name).join(format!("v{version:06}.json"))
+}
+
+pub fn load_npc_memories(data_dir: &DataDir, npc_name: &str) -> Result<MemorySystem> {
+    let npc_dir = data_dir.npc_dir(npc_name);
+    let memory_path = npc_dir.join("memories.json");
+
+    if memory_path.exists() {
+        let content = std::fs::read_to_string(&memory_path)?;
+        let memories: MemorySystem = serde_json::from_str(&content)?;
+        Ok(memories)
+    } else {
+        // Try to load from initial_memories.json
+        let initial_path = npc_dir.join("initial_memories.json");
+        if initial_path.exists() {
+            log::debug!("Loading initial memories for {}", npc_name);
+            let content = std::fs::read_to_string(&initial_path)?;
+            let memories: MemorySystem = serde_json::from_str(&content)?;
+            Ok(memories)
+        } else {
+            // Initialize empty memory system
+            log::debug!("Creating new memory system for {}", npc_name);
+            Ok(MemorySystem {
+                self_memories: SelfMemories {
+                    immediate_context: String::new(),
+                    recent_events: Vec::new(),
+                    core_memories: Vec::new(),
+                },
+                relationships: HashMap::new(),
+            })
+        }
+    }
+}
+
+/// Save an NPC's memories and record the write as a new version in their history,
+/// keeping at most `policy.max_memory_versions` versions. The first save also
+/// records what the NPC started out with, so it can be diffed against.
+pub fn save_npc_memories(
+    data_dir: &DataDir,
+    npc_name: &str,
+    memories: &MemorySystem,
+    turn: u64,
+    reason: &str,
+    policy: &MemoryPolicy,
+) -> Result<MemoryVersionSummary> {
+    // Create directories if they don't exist
+    std::fs::create_dir_all(history_dir(data_dir, npc_name))?;
+
+    let mut latest = latest_version_number(data_dir, npc_name)?;
+    if latest.is_none() {
+        let initial = load_npc_memories(data_dir, npc_name)?;
+        write_version(data_dir, npc_name, 1, 0, "initial memories", initial)?;
+        latest = Some(1);
+    }
+
+    let memory_path = data_dir.npc_dir(npc_name).join("memories.json");
+    let json = serde_json::to_string_pretty(memories)?;
+    write_file(memory_path, json)?;
+
+    let version = write_version(
+        data_dir,
+        npc_name,
+        latest.map_or(1, |v| v + 1),
+        turn,
+        reason,
+        memories.clone(),
+    )?;
+    log::debug!("Saved memories for {npc_name} as version {} (turn {turn})", version.version);
+
+    prune_versions(data_dir, npc_name, policy.max_memory_versions)?;
+    Ok(version)
+}
+
+fn write_version(
+    data_dir: &DataDir,
+    npc_name: &str,
+    version: u32,
+    turn: u64,
+    reason: &str,
+    memories: MemorySystem,
+) -> Result<MemoryVersionSummary> {
+    let version = MemoryVersion {
+        version,
+        turn,
+        timestamp: chrono::Utc::now(),
+        reason: reason.to_string(),
+        memories,
+    };
+    write_file(
+        version_path(data_dir, npc_name, version.version),
+        serde_json::to_string_pretty(&version)?,
+    )?;
+    Ok((&version).into())
+}
+
+/// Drop the oldest versions until at most `keep` are left
+fn prune_versions(data_dir: &DataDir, npc_name: &str, keep: usize) -> Result<()> {
+    let versions = version_numbers(data_dir, npc_name)?;
+    let excess = versions.len().saturating_sub(keep);
+    for version in &versions[..excess] {
+        remove_file(version_path(data_dir, npc_name, *version))?;
+    }
+    if excess > 0 {
+        log::debug!("Pruned {excess} old memory versions for {npc_name}");
+    }
+    Ok(())
+}
+
+fn version_numbers(data_dir: &DataDir, npc_name: &str) -> Result<Vec<u32>> {
+    let dir = history_dir(data_dir, npc_name);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut versions: Vec<u32> = std::fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.strip_prefix('v')?.strip_suffix(".json")?.parse().ok()
+        })
+        .collect();
+    versions.sort_unstable();
+    Ok(versions)
+}
+
+fn latest_version_number(data_dir: &DataDir, npc_name: &str) -> Result<Option<u32>> {
+    Ok(version_numbers(data_dir, npc_name)?.last().copied())
+}
+
+pub fn load_memory_version(data_dir: &DataDir, npc_name: &str, version: u32) -> Result<Option<MemoryVersion>> {
+    let path = version_path(data_dir, npc_name, version);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path)?;
+    let memory_version = serde_json::from_str(&content)
+        .with_context(|| format!("Corrupt memory version file {:?}", path))?;
+    Ok(Some(memory_version))
+}
+
+pub fn list_memory_versions(data_dir: &DataDir, npc_name: &str) -> Result<Vec<MemoryVersionSummary>> {
+    version_numbers(data_dir, npc_name)?
+        .into_iter()
+        .filter_map(|version| load_memory_version(data_dir, npc_name, version).transpose())
+        .map(|version| version.map(|v| (&v).into()))
+        .collect()
+}
@@ -1,43 +1,170 @@
-use crate::llm::{parser, LlmClient};
+use crate::config::MemoryPolicy;
+use crate::game::GameStateManager;
+use crate::metrics::metrics;
+use crate::llm::{parser, traced_query, LlmCallError, LlmClient, LlmParseError, LlmRole, Prompt};
+use crate::npcs::gossip::record_witnessed;
 use crate::npcs::memory::{MemorySystem, MemoryUpdate};
+use crate::npcs::memory_store::{load_npc_memories, save_npc_memories};
 use crate::prompts::PromptBuilder;
-use crate::types::MemoryUpdateInput;
+use crate::types::{MemoryUpdateInput, MemoryUpdateOutcome};
 use crate::utils::wrap_text;
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
+use std::collections::BTreeSet;
 use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Console output of a single NPC's memory update, held back until it can be
+/// printed in order so concurrent updates don't interleave in the logs
+#[derive(Default)]
+struct BufferedLog {
+    lines: Vec<String>,
+}
+
+impl BufferedLog {
+    fn info(&mut self, line: String) {
+        self.lines.push(line);
+    }
+
+    fn flush(self) {
+        for line in self.lines {
+            log::info!("{line}");
+        }
+    }
+}
+
+/// The turn and in-world time new memories are stamped with
+#[derive(Debug, Clone, Copy)]
+struct Formed {
+    turn: u64,
+    world_time: DateTime<Utc>,
+}
+
+/// NPCs whose memories from the last turn are still being written. A pipelined
+/// turn has each NPC's intent wait for its own entry to clear, and the GM for all.
+pub struct PendingMemories {
+    npcs: watch::Sender<BTreeSet<String>>,
+}
+
+impl PendingMemories {
+    pub fn new(npcs: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            npcs: watch::Sender::new(npcs.into_iter().collect()),
+        }
+    }
+
+    pub fn finish(&self, npc: &str) {
+        self.npcs.send_modify(|npcs| {
+            npcs.remove(npc);
+        });
+    }
+
+    pub async fn wait_for(&self, npc: &str) {
+        // The sender lives as long as self, so waiting can't fail
+        let _ = self.npcs.subscribe().wait_for(|npcs| !npcs.contains(npc)).await;
+    }
+
+    pub async fn wait_for_all(&self) {
+        let _ = self.npcs.subscribe().wait_for(BTreeSet::is_empty).await;
+    }
+}
+
+impl Default for PendingMemories {
+    fn default() -> Self {
+        Self::new([])
+    }
+}
 
 pub async fn update_memories(
     memory_inputs: Vec<MemoryUpdateInput>,
+    game_manager: &GameStateManager,
     llm_client: Arc<dyn LlmClient>,
     prompt_builder: &PromptBuilder,
-) -> Result<()> {
+) -> Vec<MemoryUpdateOutcome> {
+    // Memories are stamped with the turn and in-world time they were formed
+    let turn = game_manager.current_turn();
+    let world_time = game_manager.clock().time;
+    update_memories_as_of(memory_inputs, turn, world_time, game_manager, llm_client, prompt_builder, &PendingMemories::default()).await
+}
+
+/// Update memories formed in `turn` at `world_time`, which may be behind the world
+/// if the next turn has already begun, clearing each NPC from `pending` once done
+pub async fn update_memories_as_of(
+    memory_inputs: Vec<MemoryUpdateInput>,
+    turn: u64,
+    world_time: DateTime<Utc>,
+    game_manager: &GameStateManager,
+    llm_client: Arc<dyn LlmClient>,
+    prompt_builder: &PromptBuilder,
+    pending: &PendingMemories,
+) -> Vec<MemoryUpdateOutcome> {
+    let formed = Formed { turn, world_time };
+    let max_concurrent = game_manager.memory_policy.max_concurrent_updates;
+
     let total_npcs = memory_inputs.len();
-    log::debug!("Updating memories for {total_npcs} NPCs");
-
-    // Update memories sequentially to avoid interleaved logs
-    for input in memory_inputs {
-        if let Err(e) = update_single_npc_memory(
-            input,
-            Arc::clone(&llm_client),
-            prompt_builder
-        ).await {
-            log::error!("Memory update failed: {e}");
+    log::debug!("Updating memories for {total_npcs} NPCs (up to {max_concurrent} at once)");
+
+    // Run updates concurrently; `buffered` yields results in input order,
+    // so each NPC's log is printed as one block in a stable order
+    let mut updates = stream::iter(memory_inputs)
+        .map(|input| {
+            let llm_client = Arc::clone(&llm_client);
+            async move {
+                let npc = input.npc_name.clone();
+                let mut log = BufferedLog::default();
+                let result = update_single_npc_memory(input, formed, game_manager, llm_client, prompt_builder, &mut log).await;
+                // Right away, not when `buffered` gets to it, so the NPC can move on
+                pending.finish(&npc);
+                (npc, log, result)
+            }
+        })
+        .buffered(max_concurrent);
+
+    let mut outcomes = Vec::with_capacity(total_npcs);
+    while let Some((npc, npc_log, result)) = updates.next().await {
+        npc_log.flush();
+        match result {
+            Ok(version) => {
+                log::info!("{}\n", "-".repeat(40));
+                outcomes.push(MemoryUpdateOutcome {
+                    npc,
+                    version: Some(version),
+                    error: None,
+                    llm_excerpt: None,
+                });
+            }
+            Err(e) => {
+                log::error!("Memory update failed for {npc}: {e:#}");
+                outcomes.push(MemoryUpdateOutcome {
+                    npc,
+                    version: None,
+                    error: Some(format!("{e:#}")),
+                    llm_excerpt: e.downcast_ref::<LlmParseError>().map(LlmParseError::excerpt),
+                });
+            }
         }
     }
 
-    Ok(())
+    outcomes
 }
 
+/// Write what an NPC made of the turn into their memories, then, if an interaction
+/// they were in ended, their summary of it
 async fn update_single_npc_memory(
     input: MemoryUpdateInput,
+    formed: Formed,
+    game_manager: &GameStateManager,
     llm_client: Arc<dyn LlmClient>,
     prompt_builder: &PromptBuilder,
-) -> Result<()> {
+    log: &mut BufferedLog,
+) -> Result<u32> {
     let npc_name = &input.npc_name;
+    let data_dir = &game_manager.data_dir;
     log::debug!("Updating memories for {npc_name}");
 
     // Load current memories
-    let current_memories = load_npc_memories(npc_name)?;
+    let current_memories = load_npc_memories(data_dir, npc_name)?;
 
     // Build memory update prompt
     let prompt = prompt_builder.build_memory_update_prompt(
@@ -45,96 +172,123 @@ async fn update_single_npc_memory(
         &current_memories
     )?;
 
+    log.info(format!("\n>>> Memory Update: {}\n{}", npc_name.to_uppercase(), "-".repeat(40)));
+    let version = remember(npc_name, prompt, "turn memory update", formed, game_manager, &*llm_client, log).await?;
+
+    let Some(contract_id) = &input.ended_contract else {
+        return Ok(version);
+    };
+    // The turn's memories are already saved, so a failed summary only loses the look back
+    match summarize_contract(npc_name, contract_id, formed, game_manager, &*llm_client, prompt_builder, log).await {
+        Ok(summarized) => Ok(summarized.unwrap_or(version)),
+        Err(e) => {
+            log::warn!("⚠️  [Interaction Summary][{}] Failed to summarize interaction {contract_id}: {e:#}", npc_name.to_uppercase());
+            Ok(version)
+        }
+    }
+}
+
+/// Have an NPC look back over a whole interaction that just ended, reading its
+/// transcript from start to finish, and keep what they remember of it. An
+/// interaction nothing was ever written down for has nothing to look back on.
+async fn summarize_contract(
+    npc_name: &str,
+    contract_id: &str,
+    formed: Formed,
+    game_manager: &GameStateManager,
+    llm_client: &dyn LlmClient,
+    prompt_builder: &PromptBuilder,
+    log: &mut BufferedLog,
+) -> Result<Option<u32>> {
+    if !game_manager.data_dir.contract_path(contract_id).exists() {
+        return Ok(None);
+    }
+    let transcript = game_manager.contract_manager().read_contract_transcript(contract_id)
+        .with_context(|| format!("Failed to read the transcript of {contract_id}"))?;
+    if transcript.is_empty() {
+        return Ok(None);
+    }
+    let current_memories = load_npc_memories(&game_manager.data_dir, npc_name)?;
+    let prompt = prompt_builder.build_contract_summary_prompt(npc_name, &transcript, &current_memories)?;
+
+    log.info(format!("\n  📜 [Interaction Summary] {} looks back on {}", npc_name.to_uppercase(), contract_id));
+    remember(npc_name, prompt, "interaction summary", formed, game_manager, llm_client, log).await.map(Some)
+}
+
+/// Ask the LLM how an NPC's memories change, then apply and save its answer
+async fn remember(
+    npc_name: &str,
+    prompt: Prompt,
+    reason: &str,
+    formed: Formed,
+    game_manager: &GameStateManager,
+    llm_client: &dyn LlmClient,
+    log: &mut BufferedLog,
+) -> Result<u32> {
+    let data_dir = &game_manager.data_dir;
+
     // Query LLM
-    log::info!("\n>>> Memory Update: {}\n{}", npc_name.to_uppercase(), "-".repeat(40));
-    let working_dir = std::path::Path::new("../data");
-    let response = llm_client.query(prompt, working_dir).await?;
+    let options = game_manager.sampling.options_for(LlmRole::Memory, Some(npc_name));
+    let response = traced_query(llm_client, prompt, data_dir.root(), &options, LlmRole::Memory, Some(npc_name))
+        .await
+        .context(LlmCallError)?;
+    game_manager.usage.record(formed.turn, LlmRole::Memory, Some(npc_name), &response.usage);
 
     // Parse memory update
-    let memory_update: MemoryUpdate = parser::extract_json(&response)?;
+    let memory_update: MemoryUpdate = parser::extract_json(&response.text)?;
+    llm_client.accept(&response);
+    let notable_facts = memory_update.notable_facts.clone();
 
-    // Apply updates to memory system
+    // Apply updates to memory system, on top of whatever was saved last
+    let current_memories = load_npc_memories(data_dir, npc_name)?;
     let updated_memories = apply_memory_update(
         current_memories,
         memory_update,
-        &input
+        formed.world_time,
+        &game_manager.memory_policy,
+        log,
     )?;
 
     // Save updated memories
-    save_npc_memories(npc_name, &updated_memories)?;
-
-    log::info!("{}\n", "-".repeat(40));
-    Ok(())
-}
+    let version = save_npc_memories(
+        data_dir,
+        npc_name,
+        &updated_memories,
+        formed.turn,
+        reason,
+        &game_manager.memory_policy,
+    )?;
 
-fn load_npc_memories(npc_name: &str) -> Result<MemorySystem> {
-    let npc_dir = std::path::Path::new("../data/npcs").join(npc_name);
-    let memory_path = npc_dir.join("memories.json");
-    
-    if memory_path.exists() {
-        let content = std::fs::read_to_string(&memory_path)?;
-        let memories: MemorySystem = serde_json::from_str(&content)?;
-        Ok(memories)
-    } else {
-        // Try to load from initial_memories.json
-        let initial_path = npc_dir.join("initial_memories.json");
-        if initial_path.exists() {
-            log::debug!("Loading initial memories for {}", npc_name);
-            let content = std::fs::read_to_string(&initial_path)?;
-            let memories: MemorySystem = serde_json::from_str(&content)?;
-            
-            // Save as memories.json for next time
-            let json = serde_json::to_string_pretty(&memories)?;
-            std::fs::write(&memory_path, json)?;
-            
-            Ok(memories)
-        } else {
-            // Initialize empty memory system
-            log::debug!("Creating new memory system for {}", npc_name);
-            Ok(MemorySystem {
-                self_memories: crate::npcs::memory::SelfMemories {
-                    immediate_context: String::new(),
-                    recent_events: Vec::new(),
-                    core_memories: Vec::new(),
-                },
-                relationships: std::collections::HashMap::new(),
-            })
-        }
+    // Things they witnessed become rumors they can pass on
+    for fact in &notable_facts {
+        log.info(format!("  👁️ [Learned] {}", fact));
     }
-}
+    record_witnessed(data_dir, npc_name, &notable_facts, formed.turn)?;
 
-fn save_npc_memories(npc_name: &str, memories: &MemorySystem) -> Result<()> {
-    let npc_dir = std::path::Path::new("../data/npcs").join(npc_name);
-    
-    // Create directory if it doesn't exist
-    std::fs::create_dir_all(&npc_dir)?;
-    
-    let memory_path = npc_dir.join("memories.json");
-    
-    let json = serde_json::to_string_pretty(memories)?;
-    std::fs::write(memory_path, json)?;
-    
-    Ok(())
+    Ok(version.version)
 }
 
 fn apply_memory_update(
     mut current: MemorySystem,
     update: MemoryUpdate,
-    _input: &MemoryUpdateInput,
+    world_time: DateTime<Utc>,
+    policy: &MemoryPolicy,
+    log: &mut BufferedLog,
 ) -> Result<MemorySystem> {
     // Update self memories
     current.self_memories.immediate_context = update.immediate_self_context.clone();
     let wrapped_state = wrap_text(&update.immediate_self_context, 70, "    ");
-    log::info!("  🎭 [Current State]\n{}", wrapped_state);
+    log.info(format!("  🎭 [Current State]\n{}", wrapped_state));
     
     if let Some(new_event) = update.new_self_memory {
         let wrapped_memory = wrap_text(&new_event, 70, "    ");
-        log::info!("  📝 [Personal Memory]\n{}", wrapped_memory);
+        log.info(format!("  📝 [Personal Memory]\n{}", wrapped_memory));
         current.self_memories.recent_events.push(new_event);
-        // Keep only last 10 events
-        if current.self_memories.recent_events.len() > 10 {
+        // Keep only the most recent events
+        if current.self_memories.recent_events.len() > policy.max_recent_events {
             let fading = current.self_memories.recent_events.remove(0);
-            log::info!("  🌫️ [Memory Fades] {}", fading);
+            metrics().memory_evictions.with_label_values(&["self"]).inc();
+            log.info(format!("  🌫️ [Memory Fades] {}", fading));
         }
     }
 
@@ -156,45 +310,50 @@ fn apply_memory_update(
         relationship.current_sentiment = rel_update.current_sentiment;
         
         if !rel_update.immediate_context.is_empty() {
-            log::info!("\n  🔄 [Relationship with {}]", other_npc.to_uppercase());
+            log.info(format!("\n  🔄 [Relationship with {}]", other_npc.to_uppercase()));
             let wrapped_context = wrap_text(&rel_update.immediate_context, 66, "      ");
-            log::info!("    - Context:\n{}", wrapped_context);
+            log.info(format!("    - Context:\n{}", wrapped_context));
         }
         
         if rel_update.current_sentiment != 0.0 {
             let sentiment_desc = if rel_update.current_sentiment > 0.0 { "positive" } else { "negative" };
-            log::info!("    - Sentiment: {} ({})", sentiment_desc, rel_update.current_sentiment);
+            log.info(format!("    - Sentiment: {} ({})", sentiment_desc, rel_update.current_sentiment));
         }
 
         // Add new memory if provided
         if let Some(new_memory) = rel_update.new_memory {
             let wrapped_mem = wrap_text(&new_memory.event, 66, "      ");
-            log::info!("    - New memory:\n{}", wrapped_mem);
-            relationship.recent_memories.push(new_memory);
+            log.info(format!("    - New memory:\n{}", wrapped_mem));
+            // The world clock, not the LLM, decides when it happened
+            relationship.recent_memories.push(new_memory.at(world_time));
             
-            // Handle memory limit (10 memories)
-            if relationship.recent_memories.len() > 10 {
+            // Handle the memory limit, pinned memories never fade
+            let fade_index = relationship.recent_memories.iter().position(|m| !m.pinned);
+            if relationship.recent_memories.len() > policy.max_relationship_memories
+                && let Some(fade_index) = fade_index
+            {
                 // Memory needs to fade
-                let fading_memory = relationship.recent_memories.remove(0);
+                let fading_memory = relationship.recent_memories.remove(fade_index);
+                metrics().memory_evictions.with_label_values(&["relationship"]).inc();
                 let wrapped_fade = wrap_text(&fading_memory.event, 66, "      ");
-                log::info!("    - Fading memory:\n{}", wrapped_fade);
+                log.info(format!("    - Fading memory:\n{}", wrapped_fade));
                 
                 // Apply long-term summary update if LLM decided it's needed
                 if let Some(new_summary) = rel_update.long_term_summary_update {
                     let wrapped_summary = wrap_text(&new_summary, 66, "      ");
-                    log::info!("    - Long-term view:\n{}", wrapped_summary);
+                    log.info(format!("    - Long-term view:\n{}", wrapped_summary));
                     relationship.long_term_summary = new_summary;
                 }
             }
         }
 
         // Handle potential core memory formation
-        if let Some(core_memory) = rel_update.potential_core_memory {
-            if !relationship.core_memories.contains(&core_memory) {
-                relationship.core_memories.push(core_memory.clone());
-                let wrapped_core = wrap_text(&core_memory, 66, "      ");
-                log::info!("    ✨ Core memory formed:\n{}", wrapped_core);
-            }
+        if let Some(core_memory) = rel_update.potential_core_memory
+            && !relationship.core_memories.contains(&core_memory)
+        {
+            relationship.core_memories.push(core_memory.clone());
+            let wrapped_core = wrap_text(&core_memory, 66, "      ");
+            log.info(format!("    ✨ Core memory formed:\n{}", wrapped_core));
         }
     }
 
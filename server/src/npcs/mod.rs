@@ -1,7 +1,21 @@
+pub mod gossip;
 pub mod intent;
+pub mod knowledge;
 pub mod memory;
+pub mod memory_history;
+pub mod memory_store;
 pub mod memory_update;
 pub mod registry;
 
-pub use intent::collect_intents;
-pub use memory_update::update_memories;
+pub use intent::{collect_intents, ContractResponses};
+pub use memory_update::{update_memories, update_memories_as_of, PendingMemories};
+
+/// Error context naming the NPC whose LLM call or update failed
+#[derive(Debug, Clone)]
+pub struct FailingNpc(pub String);
+
+impl std::fmt::Display for FailingNpc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed for NPC {}", self.0)
+    }
+}
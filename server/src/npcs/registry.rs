@@ -12,4 +12,10 @@ impl NpcRegistry {
     pub fn load_from_directory(_data_dir: &Path) -> Result<Self> {
         Ok(Self::new())
     }
+}
+
+impl Default for NpcRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
 }
\ No newline at end of file
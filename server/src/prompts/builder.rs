@@ -1,9 +1,46 @@
+use crate::data_dir::DataDir;
 use crate::game::contracts::ContractManager;
+use crate::game::items;
+use crate::llm::Prompt;
+use crate::npcs::knowledge::load_knowledge;
 use crate::npcs::memory::MemorySystem;
 use crate::prompts::loader::PromptLoader;
-use crate::types::{GameState, Npc, MemoryUpdateInput};
+use crate::types::{GameState, Intent, Npc, MemoryUpdateInput, TranscriptEntry};
 use anyhow::Result;
 
+/// Keep prompts short: NPCs only dwell on their most certain knowledge
+const MAX_FACTS_IN_PROMPT: usize = 10;
+
+/// How memory updates and interaction summaries answer, so both apply the same way
+const MEMORY_UPDATE_FORMAT: &str = r#"Output a JSON object with this structure:
+```json
+{
+  "immediate_self_context": "What I'm doing/feeling right now",
+  "new_self_memory": "Optional: significant personal event to remember",
+  "relationship_updates": {
+    "other_npc_name": {
+      "immediate_context": "Current situation with them",
+      "new_memory": {
+        "event": "What happened",
+        "emotional_impact": "frustrated/happy/angry/etc",
+        "importance": 0.7
+      },
+      "current_sentiment": -0.3,
+      "long_term_summary_update": "Optional: only if this changes your overall view",
+      "potential_core_memory": "Optional: only for truly defining moments"
+    }
+  },
+  "notable_facts": ["Optional: things you witnessed yourself that others might want to know"]
+}
+```
+
+Notes:
+- importance: 0.0-1.0 (0.9+ for potential core memories)
+- current_sentiment: -1.0 to 1.0 (negative=dislike, positive=like)
+- Only include relationship_updates for NPCs you interacted with
+- notable_facts are things you could tell others about later (where food is, what someone did); leave the list empty if nothing stood out
+- Be selective with core memories - they define relationships permanently"#;
+
 pub struct PromptBuilder {
     loader: PromptLoader,
 }
@@ -13,19 +50,35 @@ impl PromptBuilder {
         Self { loader }
     }
 
+    /// The data directory prompts, memories and transcripts are read from
+    pub fn data_dir(&self) -> &DataDir {
+        self.loader.data_dir()
+    }
+
     pub fn build_npc_intent_prompt(
         &self,
         npc: &Npc,
         game_state: &GameState,
-    ) -> Result<String> {
+    ) -> Result<Prompt> {
+        self.build_npc_intent_prompt_after(npc, game_state, &[])
+    }
+
+    /// The intent prompt for an NPC answering after others in their interaction
+    /// already have this turn, which they hear (but whose thoughts they don't)
+    pub fn build_npc_intent_prompt_after(
+        &self,
+        npc: &Npc,
+        game_state: &GameState,
+        earlier: &[Intent],
+    ) -> Result<Prompt> {
+        // 1. Base NPC instructions (response format, etc.) and 2. personality,
+        // the same every turn so providers can cache them
+        let prompt = Prompt::new()
+            .system(self.loader.load_npc_base()?)
+            .system(self.loader.load_personality(&npc.name)?);
+
         let mut sections = vec![];
 
-        // 1. Base NPC instructions (response format, etc.)
-        sections.push(self.loader.load_npc_base()?);
-        
-        // 2. Personality
-        sections.push(self.loader.load_personality(&npc.name)?);
-        
         // 3. Current memories
         let memories = self.loader.load_memories(&npc.name)?;
         sections.push(format!("## Your Current Memories\n\n```json\n{}\n```", memories));
@@ -33,38 +86,71 @@ impl PromptBuilder {
         // 4. Current state
         sections.push(self.format_current_state(npc, game_state));
         
-        // 5. Contract context if in one
-        if let Some(contract_id) = &npc.active_contract {
-            if let Ok(transcript) = ContractManager::read_contract_transcript(contract_id) {
-                sections.push(self.format_contract_context(&transcript));
+        // 5. Things they've witnessed or been told
+        if let Ok(knowledge) = load_knowledge(self.data_dir(), &npc.name) {
+            let facts = knowledge.believed_facts();
+            if !facts.is_empty() {
+                let lines: Vec<String> = facts
+                    .iter()
+                    .take(MAX_FACTS_IN_PROMPT)
+                    .map(|fact| format!("- {}", fact.describe()))
+                    .collect();
+                sections.push(format!("## Things You Know\n\n{}", lines.join("\n")));
             }
         }
         
-        // 6. GM's specific prompt or generic "What do you do next?"
-        let prompt = npc.next_prompt.as_ref()
-            .map(|p| p.clone())
+        // 6. What the NPC perceived last turn (never the GM's full reality)
+        if let Some(observation) = npc.last_observation.as_ref().filter(|o| !o.is_empty()) {
+            sections.push(format!("## What You Noticed\n\n{}", observation.describe()));
+        }
+        
+        // 7. Contract context if in one
+        if let Some(contract_id) = &npc.active_contract
+            && let Ok(transcript) = self.contracts().read_contract_transcript(contract_id)
+        {
+            sections.push(self.format_contract_context(&transcript));
+        }
+        if !earlier.is_empty() {
+            sections.push(format_just_now(earlier));
+        }
+        
+        // 8. GM's specific prompt or generic "What do you do next?"
+        let question = npc.next_prompt.clone()
             .unwrap_or_else(|| "What do you do next?".to_string());
-        sections.push(prompt);
 
-        Ok(sections.join("\n\n---\n\n"))
+        Ok(prompt.user(Prompt::join_sections(&sections)).user(question))
     }
 
-    pub fn build_gm_prompt(&self, input_json: &str) -> Result<String> {
-        let mut sections = vec![];
-        
-        // GM base instructions
-        sections.push(self.loader.load_gm_base()?);
-        
-        // Current game state and intents
-        sections.push(format!("## Current Input\n\n```json\n{}\n```", input_json));
-        
-        Ok(sections.join("\n\n---\n\n"))
+    pub fn build_gm_prompt(&self, input_json: &str) -> Result<Prompt> {
+        Ok(Prompt::new()
+            // GM base instructions
+            .system(self.loader.load_gm_base()?)
+            // Current game state and intents
+            .user(format!("## Current Input\n\n```json\n{}\n```", input_json)))
+    }
+
+    /// Ask the GM to fix a response that contradicts the world, as the next
+    /// exchange in the same conversation
+    pub fn build_gm_repair_prompt(&self, gm_prompt: &Prompt, previous_response: &str, problems: &[String]) -> Prompt {
+        let problem_list: Vec<String> = problems.iter().map(|p| format!("- {p}")).collect();
+        gm_prompt
+            .clone()
+            .assistant(format!("## Your Previous Response\n\n{}", previous_response.trim()))
+            .user(format!(
+                "## Problems With Your Response\n\n{}\n\nThese contradict the current world state. Return the complete corrected JSON response.",
+                problem_list.join("\n")
+            ))
+    }
+
+    fn contracts(&self) -> ContractManager {
+        ContractManager::new(self.data_dir().clone())
     }
 
     fn format_current_state(&self, npc: &Npc, game_state: &GameState) -> String {
         let mut state = String::from("## Current Situation\n\n");
         
         // NPC's own state
+        state.push_str(&format!("- {}\n", game_state.clock.describe()));
         state.push_str(&format!("- You are at: {:?}\n", npc.location));
         state.push_str(&format!("- You are: {}\n", npc.activity));
         
@@ -76,35 +162,44 @@ impl PromptBuilder {
             })
             .collect();
             
-        if !others_here.is_empty() {
+        let player_here = game_state.player
+            .as_ref()
+            .filter(|player| player.location == npc.location);
+            
+        if !others_here.is_empty() || player_here.is_some() {
             state.push_str("\nAlso here:\n");
             for (other_name, other_npc) in others_here {
                 state.push_str(&format!("- {} is {}\n", other_name, other_npc.activity));
             }
+            if let Some(player) = player_here {
+                state.push_str(&format!("- {} is {}\n", player.name, player.activity));
+            }
+        }
+        
+        // What they carry and what's lying around
+        let carried = items::carried_by(&game_state.items, &npc.name);
+        if !carried.is_empty() {
+            state.push_str("\nYou are carrying:\n");
+            for item in carried {
+                state.push_str(&format!("- {}\n", item.describe()));
+            }
+        }
+        
+        let lying = items::lying_at(&game_state.items, &npc.location);
+        if !lying.is_empty() {
+            state.push_str("\nYou can see here:\n");
+            for item in lying {
+                state.push_str(&format!("- {}\n", item.describe()));
+            }
         }
         
         state
     }
     
-    fn format_contract_context(&self, transcript: &[crate::types::TranscriptEntry]) -> String {
+    fn format_contract_context(&self, transcript: &[TranscriptEntry]) -> String {
         let mut context = String::from("## Ongoing Interaction\n\n");
         context.push_str("You are currently in an interaction with the following history:\n\n");
-        
-        for (i, entry) in transcript.iter().enumerate() {
-            let turn = i + 1;
-            context.push_str(&format!("### Turn {}\n", turn));
-            context.push_str(&format!("What happened: {}\n\n", entry.reality));
-            
-            for (participant, action) in &entry.details {
-                context.push_str(&format!("**{}**: {}", participant, action.action));
-                if let Some(dialogue) = &action.dialogue {
-                    context.push_str(&format!(" - Said: \"{}\"", dialogue));
-                }
-                context.push_str("\n");
-            }
-            context.push_str("\n");
-        }
-        
+        context.push_str(&format_transcript(transcript));
         context.push_str("Remember: You're continuing this interaction. Respond naturally to what just happened.");
         context
     }
@@ -113,60 +208,37 @@ impl PromptBuilder {
         &self,
         input: &MemoryUpdateInput,
         current_memories: &MemorySystem,
-    ) -> Result<String> {
-        let mut sections = vec![];
-        
+    ) -> Result<Prompt> {
         // Instructions for memory updates
-        sections.push(r#"## Memory Update Task
+        let instructions = format!("{}\n\n{}", r#"## Memory Update Task
 
 IMPORTANT: You should ONLY return a JSON response. Do not create, write, or modify any files. The server will handle all file operations.
 
 You need to update your memories based on what just happened. Consider:
-- Your intent vs what actually occurred
+- Your intent vs what you perceived actually occurring
+- You only know what you saw, heard or smelled yourself - don't assume anything else
 - Emotional impact and importance of events
-- Changes in relationships
-
-Output a JSON object with this structure:
-```json
-{
-  "immediate_self_context": "What I'm doing/feeling right now",
-  "new_self_memory": "Optional: significant personal event to remember",
-  "relationship_updates": {
-    "other_npc_name": {
-      "immediate_context": "Current situation with them",
-      "new_memory": {
-        "event": "What happened",
-        "timestamp": "2025-01-02T10:30:00Z",
-        "emotional_impact": "frustrated/happy/angry/etc",
-        "importance": 0.7
-      },
-      "current_sentiment": -0.3,
-      "long_term_summary_update": "Optional: only if this changes your overall view",
-      "potential_core_memory": "Optional: only for truly defining moments"
-    }
-  }
-}
-```
-
-Notes:
-- importance: 0.0-1.0 (0.9+ for potential core memories)
-- current_sentiment: -1.0 to 1.0 (negative=dislike, positive=like)
-- Only include relationship_updates for NPCs you interacted with
-- Be selective with core memories - they define relationships permanently"#.to_string());
+- Changes in relationships"#, MEMORY_UPDATE_FORMAT);
 
         // Current memory state
-        sections.push(format!("## Your Current Memories\n\n```json\n{}\n```", 
-            serde_json::to_string_pretty(current_memories)?));
+        let memories = format!("## Your Current Memories\n\n```json\n{}\n```", 
+            serde_json::to_string_pretty(current_memories)?);
         
         // What happened
+        let mut sections = vec![];
         sections.push(format!("## What Just Happened\n\nYou are: {}", input.npc_name));
-        sections.push(format!("You intended:\n- Thought: {}\n- Action: {}", 
-            input.intent.thought, input.intent.action));
-        if let Some(dialogue) = &input.intent.dialogue {
-            sections.push(format!("- You wanted to say: \"{}\"", dialogue));
+        match &input.intent {
+            Some(intent) => {
+                sections.push(format!("You intended:\n- Thought: {}\n- Action: {}", 
+                    intent.thought, intent.action));
+                if let Some(dialogue) = &intent.dialogue {
+                    sections.push(format!("- You wanted to say: \"{}\"", dialogue));
+                }
+            }
+            None => sections.push("You didn't get to act this turn.".to_string()),
         }
         
-        sections.push(format!("\nWhat actually happened:\n{}", input.reality));
+        sections.push(format!("\nWhat you perceived happening:\n{}", input.reality));
         
         if !input.other_npcs_present.is_empty() {
             sections.push(format!("\nOthers present: {}", input.other_npcs_present.join(", ")));
@@ -174,6 +246,75 @@ Notes:
         
         sections.push("\nNow update your memories based on this experience.".to_string());
         
-        Ok(sections.join("\n\n---\n\n"))
+        Ok(Prompt::new()
+            .system(instructions)
+            .user(memories)
+            .user(Prompt::join_sections(&sections)))
     }
-}
\ No newline at end of file
+
+    /// Ask an NPC what they remember of an interaction that just ended, with the
+    /// whole transcript in front of them rather than only its last turn
+    pub fn build_contract_summary_prompt(
+        &self,
+        npc_name: &str,
+        transcript: &[TranscriptEntry],
+        current_memories: &MemorySystem,
+    ) -> Result<Prompt> {
+        let instructions = format!("{}\n\n{}", r#"## Interaction Summary Task
+
+IMPORTANT: You should ONLY return a JSON response. Do not create, write, or modify any files. The server will handle all file operations.
+
+An interaction you were part of has just ended. Read it through from start to finish and decide what you remember of it as a whole. Consider:
+- How it started, how it went and how it ended, not just the last moment
+- What you learned about the others, and how you feel about them now
+- Whether it changed what you want to do next
+- You only know what you saw, heard or smelled yourself - don't assume anything else"#, MEMORY_UPDATE_FORMAT);
+
+        let memories = format!("## Your Current Memories\n\n```json\n{}\n```",
+            serde_json::to_string_pretty(current_memories)?);
+        let interaction = format!(
+            "## The Interaction\n\nYou are: {}\n\n{}What do you remember from this interaction?",
+            npc_name,
+            format_transcript(transcript)
+        );
+
+        Ok(Prompt::new()
+            .system(instructions)
+            .user(memories)
+            .user(interaction))
+    }
+}
+
+/// An interaction's transcript, one turn after another
+fn format_transcript(transcript: &[TranscriptEntry]) -> String {
+    let mut text = String::new();
+    for (i, entry) in transcript.iter().enumerate() {
+        let turn = i + 1;
+        text.push_str(&format!("### Turn {}\n", turn));
+        text.push_str(&format!("What happened: {}\n\n", entry.reality));
+        
+        for (participant, action) in &entry.details {
+            text.push_str(&format!("**{}**: {}", participant, action.action));
+            if let Some(dialogue) = &action.dialogue {
+                text.push_str(&format!(" - Said: \"{}\"", dialogue));
+            }
+            text.push('\n');
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// What the others in an interaction have done and said so far this turn
+fn format_just_now(earlier: &[Intent]) -> String {
+    let mut text = String::from("## Just Now\n\n");
+    for intent in earlier {
+        text.push_str(&format!("**{}**: {}", intent.npc, intent.action));
+        if let Some(dialogue) = &intent.dialogue {
+            text.push_str(&format!(" - Said: \"{}\"", dialogue));
+        }
+        text.push('\n');
+    }
+    text.push_str("\nIt's your turn to respond.");
+    text
+}
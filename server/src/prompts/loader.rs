@@ -1,43 +1,47 @@
 use anyhow::{Context, Result};
 use std::fs;
-use std::path::PathBuf;
+use crate::data_dir::DataDir;
 
 pub struct PromptLoader {
-    data_dir: PathBuf,
+    data_dir: DataDir,
 }
 
 impl PromptLoader {
-    pub fn new(data_dir: PathBuf) -> Self {
-        Self { data_dir }
+    pub fn new(data_dir: impl Into<DataDir>) -> Self {
+        Self { data_dir: data_dir.into() }
+    }
+
+    pub fn data_dir(&self) -> &DataDir {
+        &self.data_dir
     }
 
     pub fn load_npc_base(&self) -> Result<String> {
-        let path = self.data_dir.join("prompts/core/npc_base.md");
+        let path = self.data_dir.prompts_dir().join("core/npc_base.md");
         fs::read_to_string(&path)
             .with_context(|| format!("Failed to load NPC base prompt from {:?}", path))
     }
 
     pub fn load_personality(&self, npc_name: &str) -> Result<String> {
-        let path = self.data_dir.join(format!("npcs/{}/personality.md", npc_name));
+        let path = self.data_dir.npc_dir(npc_name).join("personality.md");
         fs::read_to_string(&path)
             .with_context(|| format!("Failed to load personality for {} from {:?}", npc_name, path))
     }
 
     pub fn load_gm_base(&self) -> Result<String> {
-        let path = self.data_dir.join("prompts/gm/gm_base.md");
+        let path = self.data_dir.prompts_dir().join("gm/gm_base.md");
         fs::read_to_string(&path)
             .with_context(|| format!("Failed to load GM base prompt from {:?}", path))
     }
 
     pub fn load_memories(&self, npc_name: &str) -> Result<String> {
-        let memory_path = self.data_dir.join(format!("npcs/{}/memories.json", npc_name));
+        let memory_path = self.data_dir.npc_dir(npc_name).join("memories.json");
         
         if memory_path.exists() {
             fs::read_to_string(&memory_path)
                 .with_context(|| format!("Failed to load memories for {} from {:?}", npc_name, memory_path))
         } else {
             // Try initial_memories.json as fallback
-            let initial_path = self.data_dir.join(format!("npcs/{}/initial_memories.json", npc_name));
+            let initial_path = self.data_dir.npc_dir(npc_name).join("initial_memories.json");
             if initial_path.exists() {
                 fs::read_to_string(&initial_path)
                     .with_context(|| format!("Failed to load initial memories for {} from {:?}", npc_name, initial_path))
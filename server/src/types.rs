@@ -1,3 +1,8 @@
+use crate::game::clock::WorldClock;
+use crate::game::events::WorldEvent;
+use crate::game::items::{Item, ItemSummary, ItemTransfer, RejectedTransfer};
+use crate::npcs::knowledge::KnownFact;
+use crate::api::Problem;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -26,8 +31,33 @@ fn default_delay() -> u64 {
 #[derive(Debug, Serialize)]
 pub struct ExecuteTurnResponse {
     pub turns_executed: u32,
-    pub last_turn_result: Option<GmResponse>,
+    pub last_turn_result: Option<TurnResult>,
     pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Problem>,  // Why execution stopped early, if it did
+}
+
+// Intents from NPCs that answered, and a problem for each that didn't
+#[derive(Debug, Serialize)]
+pub struct CollectIntentsResponse {
+    pub intents: Vec<Intent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<Problem>,
+}
+
+// Everything that happened in one turn: the GM's resolution plus how the memory phase went
+#[derive(Debug, Serialize)]
+pub struct TurnResult {
+    pub turn: u64,
+    #[serde(flatten)]
+    pub gm_response: GmResponse,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub world_events: Vec<WorldEvent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dropped_intents: Vec<IntentFailure>,  // NPCs who sat this turn out because their intent failed
+    pub memory_updates: Vec<MemoryUpdateOutcome>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player_narration: Option<String>,  // The GM's prompt for the player, if one has joined
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -39,8 +69,12 @@ pub enum Location {
 
 #[derive(Debug, Clone, Serialize)]
 pub struct GameState {
+    pub turn: u64,
     pub npcs: HashMap<String, Npc>,
     pub contracts: HashMap<String, Contract>,
+    pub player: Option<Player>,
+    pub items: HashMap<String, Item>,
+    pub clock: WorldClock,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -51,6 +85,39 @@ pub struct Npc {
     pub folder_path: String,
     pub active_contract: Option<String>,
     pub next_prompt: Option<String>,
+    pub last_observation: Option<Observation>,  // What this NPC perceived last turn
+}
+
+// The human-controlled character. Lives in the world like an NPC, but their intent
+// comes from the API and they keep no memories of their own
+#[derive(Debug, Clone, Serialize)]
+pub struct Player {
+    pub name: String,
+    pub location: Location,
+    pub activity: String,
+    pub active_contract: Option<String>,
+    pub narration: Option<String>,  // What the GM last told the player, their equivalent of next_prompt
+    pub last_observation: Option<Observation>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JoinPlayerRequest {
+    pub name: String,
+    #[serde(default = "default_player_location")]
+    pub location: Location,
+}
+
+fn default_player_location() -> Location {
+    Location::ForestClearing
+}
+
+// What the player wants to do this turn; the server fills in who's acting
+#[derive(Debug, Deserialize)]
+pub struct PlayerIntentRequest {
+    #[serde(default)]
+    pub thought: String,
+    pub action: String,
+    pub dialogue: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -73,31 +140,82 @@ pub struct Intent {
 pub struct GmInput {
     pub current_state: CurrentState,
     pub intents: Vec<Intent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub world_events: Vec<WorldEvent>,  // Happenings the GM must treat as fact
+    pub knowledge: HashMap<String, Vec<KnownFact>>,  // What each NPC believes they know
 }
 
 #[derive(Debug, Serialize)]
 pub struct CurrentState {
+    pub clock: WorldClock,
     pub npcs: HashMap<String, Npc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player: Option<Player>,
+    pub items: HashMap<String, ItemSummary>,  // Everything not yet consumed
     pub active_contracts: HashMap<String, Contract>,
 }
 
 // Data we get back from the GM
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GmResponse {
     pub reality: String,
     pub state_changes: Vec<StateChange>,
     pub contracts: Vec<ContractUpdate>,
     pub next_prompts: HashMap<String, String>,
+    #[serde(default)]
+    pub observations: HashMap<String, Observation>,  // What each NPC could perceive
+    #[serde(default)]
+    pub item_transfers: Vec<ItemTransfer>,
+    #[serde(skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub rejected_item_transfers: Vec<RejectedTransfer>,  // Filled in by the server, never the GM
+    #[serde(skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,  // Parts of the response the server dropped, and why
+    #[serde(skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub ended_contracts: Vec<Contract>,  // Archived by the server once the GM ended them
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// What a single NPC actually perceived of the turn, limited to their senses and location
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Observation {
+    pub saw: Option<String>,
+    pub heard: Option<String>,
+    pub smelled: Option<String>,
+}
+
+impl Observation {
+    pub fn is_empty(&self) -> bool {
+        [&self.saw, &self.heard, &self.smelled]
+            .iter()
+            .all(|sense| sense.as_deref().is_none_or(|s| s.trim().is_empty()))
+    }
+
+    /// Render the observation as prose for NPC prompts
+    pub fn describe(&self) -> String {
+        let senses = [
+            ("You saw", &self.saw),
+            ("You heard", &self.heard),
+            ("You smelled", &self.smelled),
+        ];
+        senses
+            .iter()
+            .filter_map(|(label, sense)| {
+                sense.as_deref()
+                    .filter(|s| !s.trim().is_empty())
+                    .map(|s| format!("{label}: {s}"))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StateChange {
     pub npc: String,
     pub location: Location,
     pub activity: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContractUpdate {
     pub id: String,
     pub participants: Vec<String>,
@@ -109,6 +227,18 @@ pub struct ContractUpdate {
 pub struct TranscriptEntry {
     pub reality: String,
     pub details: HashMap<String, NpcAction>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shared_information: Vec<SharedInformation>,
+}
+
+// Information the GM saw passed on in dialogue, e.g. gossip about a third NPC
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedInformation {
+    pub speaker: String,
+    #[serde(default)]
+    pub listeners: Vec<String>,  // Defaults to the other contract participants
+    pub rumor_id: Option<String>,  // Set when the speaker retells something they already knew
+    pub content: String,  // What was actually said, possibly distorted
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -121,7 +251,29 @@ pub struct NpcAction {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MemoryUpdateInput {
     pub npc_name: String,
-    pub intent: Intent,
-    pub reality: String,
+    #[serde(default)]
+    pub intent: Option<Intent>,  // None if the NPC didn't act, say their intent failed
+    pub reality: String,  // What this NPC perceived, not the GM's omniscient account
     pub other_npcs_present: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_contract: Option<String>,  // An interaction that ended this turn, summarized after the update
+}
+
+// Why an NPC produced no intent this turn
+#[derive(Debug, Clone, Serialize)]
+pub struct IntentFailure {
+    pub npc: String,
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub llm_excerpt: Option<String>,
 }
+
+// Result of updating one NPC's memories
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryUpdateOutcome {
+    pub npc: String,
+    pub version: Option<u32>,  // Memory version written, if the update succeeded
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub llm_excerpt: Option<String>,  // Start of the unparseable LLM response, if that's what failed
+}
\ No newline at end of file
@@ -0,0 +1,23 @@
+use serde_json::Value;
+
+/// Apply an RFC 7396 JSON merge patch: objects merge recursively,
+/// `null` removes a key and any other value replaces the target
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch_map) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(serde_json::Map::new());
+    }
+    let target_map = target.as_object_mut().expect("target was just made an object");
+
+    for (key, value) in patch_map {
+        if value.is_null() {
+            target_map.remove(key);
+        } else {
+            merge_patch(target_map.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
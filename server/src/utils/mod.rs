@@ -1,3 +1,5 @@
+pub mod json;
 pub mod text;
 
-pub use text::wrap_text;
\ No newline at end of file
+pub use json::merge_patch;
+pub use text::wrap_text;
@@ -2,7 +2,7 @@ use axum::{
     body::Body,
     http::{Request, StatusCode},
 };
-use server::{llm::LlmClient, game::GameStateManager, prompts::{PromptBuilder, PromptLoader}};
+use server::{llm::{LlmClient, LlmOptions, LlmResponse, LlmRole, MessageRole, Prompt, Usage}, game::{turn::TurnMode, GameStateManager}, prompts::{PromptBuilder, PromptLoader}};
 use std::sync::Arc;
 use tower::ServiceExt;
 use serde_json::{json, Value};
@@ -22,16 +22,73 @@ impl MockLlmClient {
 
 #[async_trait::async_trait]
 impl LlmClient for MockLlmClient {
-    async fn query(&self, _prompt: String, _working_dir: &std::path::Path) -> anyhow::Result<String> {
+    async fn query(&self, _prompt: Prompt, _working_dir: &std::path::Path, _options: &LlmOptions) -> anyhow::Result<LlmResponse> {
         let mut responses = self.responses.lock().unwrap();
-        if let Some(response) = responses.pop() {
-            Ok(response)
-        } else {
-            Ok(r#"{"thought": "test", "action": "test", "dialogue": null}"#.to_string())
+        let text = responses
+            .pop()
+            .unwrap_or_else(|| r#"{"thought": "test", "action": "test", "dialogue": null}"#.to_string());
+        Ok(LlmResponse::new(text).with_usage(Usage {
+            provider: "mock".to_string(),
+            model: "mock-model".to_string(),
+            prompt_tokens: 1000,
+            completion_tokens: 100,
+            latency_ms: 5,
+        }))
+    }
+}
+
+// Mock LLM client that also records every prompt it receives, and the options it came with
+struct RecordingLlmClient {
+    inner: MockLlmClient,
+    prompts: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    options: std::sync::Arc<std::sync::Mutex<Vec<LlmOptions>>>,
+}
+
+impl RecordingLlmClient {
+    fn new(responses: Vec<String>) -> Self {
+        Self {
+            inner: MockLlmClient::new(responses),
+            prompts: std::sync::Arc::new(std::sync::Mutex::new(Vec::new())),
+            options: std::sync::Arc::new(std::sync::Mutex::new(Vec::new())),
         }
     }
 }
 
+#[async_trait::async_trait]
+impl LlmClient for RecordingLlmClient {
+    async fn query(&self, prompt: Prompt, working_dir: &std::path::Path, options: &LlmOptions) -> anyhow::Result<LlmResponse> {
+        self.prompts.lock().unwrap().push(prompt.render());
+        self.options.lock().unwrap().push(options.clone());
+        self.inner.query(prompt, working_dir, options).await
+    }
+}
+
+// A memory file for an NPC who remembers nothing yet
+const EMPTY_MEMORIES: &str = r#"{
+    "self_memories": {"immediate_context": "", "recent_events": [], "core_memories": []},
+    "relationships": {}
+}"#;
+
+// Write the prompt and personality files a full turn needs
+fn setup_test_data_dir(name: &str) -> std::path::PathBuf {
+    let test_data_dir = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&test_data_dir);
+    
+    let prompts_dir = test_data_dir.join("prompts");
+    std::fs::create_dir_all(prompts_dir.join("core")).unwrap();
+    std::fs::create_dir_all(prompts_dir.join("gm")).unwrap();
+    std::fs::write(prompts_dir.join("core/npc_base.md"), "Test").unwrap();
+    std::fs::write(prompts_dir.join("gm/gm_base.md"), "Test").unwrap();
+    
+    let npcs_dir = test_data_dir.join("npcs");
+    std::fs::create_dir_all(npcs_dir.join("bear")).unwrap();
+    std::fs::create_dir_all(npcs_dir.join("wolf")).unwrap();
+    std::fs::write(npcs_dir.join("bear/personality.md"), "Test bear").unwrap();
+    std::fs::write(npcs_dir.join("wolf/personality.md"), "Test wolf").unwrap();
+    
+    test_data_dir
+}
+
 // Helper to create test app
 async fn create_test_app() -> axum::Router {
     // Create test data directory
@@ -40,26 +97,27 @@ async fn create_test_app() -> axum::Router {
     
     // Create minimal prompt files
     let prompts_dir = test_data_dir.join("prompts");
-    std::fs::create_dir_all(&prompts_dir.join("core")).unwrap();
-    std::fs::create_dir_all(&prompts_dir.join("gm")).unwrap();
+    std::fs::create_dir_all(prompts_dir.join("core")).unwrap();
+    std::fs::create_dir_all(prompts_dir.join("gm")).unwrap();
     std::fs::write(prompts_dir.join("core/npc_base.md"), "Test NPC base").unwrap();
     std::fs::write(prompts_dir.join("gm/gm_base.md"), "Test GM base").unwrap();
     
     // Create NPC directories
     let npcs_dir = test_data_dir.join("npcs");
-    std::fs::create_dir_all(&npcs_dir.join("bear")).unwrap();
-    std::fs::create_dir_all(&npcs_dir.join("wolf")).unwrap();
+    std::fs::create_dir_all(npcs_dir.join("bear")).unwrap();
+    std::fs::create_dir_all(npcs_dir.join("wolf")).unwrap();
     std::fs::write(npcs_dir.join("bear/personality.md"), "Test bear personality").unwrap();
     std::fs::write(npcs_dir.join("wolf/personality.md"), "Test wolf personality").unwrap();
-    std::fs::write(npcs_dir.join("bear/memories.json"), "{}").unwrap();
-    std::fs::write(npcs_dir.join("wolf/memories.json"), "{}").unwrap();
+    std::fs::write(npcs_dir.join("bear/memories.json"), EMPTY_MEMORIES).unwrap();
+    std::fs::write(npcs_dir.join("wolf/memories.json"), EMPTY_MEMORIES).unwrap();
     
-    let game_manager = GameStateManager::new();
+    let game_manager = GameStateManager::new().with_data_dir(test_data_dir.clone());
     let llm_client: Arc<dyn LlmClient> = Arc::new(MockLlmClient::new(vec![]));
     let prompt_loader = PromptLoader::new(test_data_dir);
     let prompt_builder = PromptBuilder::new(prompt_loader);
     
     let app_state = Arc::new(server::AppState {
+        config: Default::default(),
         game_manager,
         llm_client,
         prompt_builder,
@@ -133,24 +191,25 @@ async fn test_collect_intents_endpoint() {
     
     // Set up directories
     let prompts_dir = test_data_dir.join("prompts");
-    std::fs::create_dir_all(&prompts_dir.join("core")).unwrap();
-    std::fs::create_dir_all(&prompts_dir.join("gm")).unwrap();
+    std::fs::create_dir_all(prompts_dir.join("core")).unwrap();
+    std::fs::create_dir_all(prompts_dir.join("gm")).unwrap();
     std::fs::write(prompts_dir.join("core/npc_base.md"), "Test").unwrap();
     std::fs::write(prompts_dir.join("gm/gm_base.md"), "Test").unwrap();
     
     let npcs_dir = test_data_dir.join("npcs");
-    std::fs::create_dir_all(&npcs_dir.join("bear")).unwrap();
-    std::fs::create_dir_all(&npcs_dir.join("wolf")).unwrap();
+    std::fs::create_dir_all(npcs_dir.join("bear")).unwrap();
+    std::fs::create_dir_all(npcs_dir.join("wolf")).unwrap();
     std::fs::write(npcs_dir.join("bear/personality.md"), "Test").unwrap();
     std::fs::write(npcs_dir.join("wolf/personality.md"), "Test").unwrap();
-    std::fs::write(npcs_dir.join("bear/memories.json"), "{}").unwrap();
-    std::fs::write(npcs_dir.join("wolf/memories.json"), "{}").unwrap();
+    std::fs::write(npcs_dir.join("bear/memories.json"), EMPTY_MEMORIES).unwrap();
+    std::fs::write(npcs_dir.join("wolf/memories.json"), EMPTY_MEMORIES).unwrap();
     
-    let game_manager = GameStateManager::new();
+    let game_manager = GameStateManager::new().with_data_dir(test_data_dir.clone());
     let prompt_loader = PromptLoader::new(test_data_dir);
     let prompt_builder = PromptBuilder::new(prompt_loader);
     
     let app_state = Arc::new(server::AppState {
+        config: Default::default(),
         game_manager,
         llm_client: Arc::new(mock_client),
         prompt_builder,
@@ -172,13 +231,15 @@ async fn test_collect_intents_endpoint() {
     assert_eq!(response.status(), StatusCode::OK);
     
     let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
-    let intents: Vec<Value> = serde_json::from_slice(&body).unwrap();
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    let intents = json["intents"].as_array().unwrap();
     
-    // Should have 2 intents
+    // Should have 2 intents and nobody failing
     assert_eq!(intents.len(), 2);
+    assert!(json.get("failures").is_none());
     
     // Check structure
-    for intent in &intents {
+    for intent in intents {
         assert!(intent["npc"].is_string());
         assert!(intent["thought"].is_string());
         assert!(intent["action"].is_string());
@@ -207,16 +268,17 @@ async fn test_resolve_intents_endpoint() {
     std::fs::create_dir_all(&test_data_dir).unwrap();
     
     let prompts_dir = test_data_dir.join("prompts");
-    std::fs::create_dir_all(&prompts_dir.join("core")).unwrap();
-    std::fs::create_dir_all(&prompts_dir.join("gm")).unwrap();
+    std::fs::create_dir_all(prompts_dir.join("core")).unwrap();
+    std::fs::create_dir_all(prompts_dir.join("gm")).unwrap();
     std::fs::write(prompts_dir.join("core/npc_base.md"), "Test").unwrap();
     std::fs::write(prompts_dir.join("gm/gm_base.md"), "Test").unwrap();
     
-    let game_manager = GameStateManager::new();
+    let game_manager = GameStateManager::new().with_data_dir(test_data_dir.clone());
     let prompt_loader = PromptLoader::new(test_data_dir);
     let prompt_builder = PromptBuilder::new(prompt_loader);
     
     let app_state = Arc::new(server::AppState {
+        config: Default::default(),
         game_manager,
         llm_client: Arc::new(mock_client),
         prompt_builder,
@@ -255,6 +317,42 @@ async fn test_resolve_intents_endpoint() {
     assert!(gm_response["next_prompts"].is_object());
 }
 
+#[tokio::test]
+async fn test_resolving_intents_advances_the_turn_for_scheduled_events() {
+    let gm_response = json!({
+        "reality": "Thunder rolls as Bear fishes",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {"bear": "Thunder! What now?"}
+    }).to_string();
+    let llm_client = Arc::new(RecordingLlmClient::new(vec![gm_response]));
+    let prompts = Arc::clone(&llm_client.prompts);
+
+    let test_data_dir = setup_test_data_dir("two_animals_test_resolve_turn");
+    std::fs::write(test_data_dir.join("world_events.json"), json!({
+        "events": [{
+            "id": "first_thunder",
+            "description": "Thunder rolls over the forest",
+            "conditions": [{"type": "turn", "at": 1}]
+        }]
+    }).to_string()).unwrap();
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client,
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state.clone());
+
+    let intents = json!([{"npc": "bear", "thought": "Hungry", "action": "Fish", "dialogue": null}]);
+    let (status, body) = post_json(&app, "/turn/resolve", intents).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    assert_eq!(app_state.game_manager.current_turn(), 1);
+    let gm_prompt = prompts.lock().unwrap().iter().find(|p| p.contains("\"world_events\"")).cloned();
+    assert!(gm_prompt.unwrap().contains("Thunder rolls over the forest"));
+}
+
 #[tokio::test]
 async fn test_memory_update_endpoint() {
     let memory_response = json!({
@@ -269,14 +367,15 @@ async fn test_memory_update_endpoint() {
     std::fs::create_dir_all(&test_data_dir).unwrap();
     
     let npcs_dir = test_data_dir.join("npcs");
-    std::fs::create_dir_all(&npcs_dir.join("bear")).unwrap();
-    std::fs::write(npcs_dir.join("bear/memories.json"), "{}").unwrap();
+    std::fs::create_dir_all(npcs_dir.join("bear")).unwrap();
+    std::fs::write(npcs_dir.join("bear/memories.json"), EMPTY_MEMORIES).unwrap();
     
-    let game_manager = GameStateManager::new();
+    let game_manager = GameStateManager::new().with_data_dir(test_data_dir.clone());
     let prompt_loader = PromptLoader::new(test_data_dir);
     let prompt_builder = PromptBuilder::new(prompt_loader);
     
     let app_state = Arc::new(server::AppState {
+        config: Default::default(),
         game_manager,
         llm_client: Arc::new(mock_client),
         prompt_builder,
@@ -340,7 +439,10 @@ async fn test_execute_full_turn_endpoint() {
             "activity": "fishing"
         }],
         "contracts": [],
-        "next_prompts": {}
+        "next_prompts": {
+            "bear": "The river is cold around your paws. What now?",
+            "wolf": "Bear is fishing downstream. What now?"
+        }
     }).to_string();
     
     let bear_intent = json!({
@@ -371,14 +473,14 @@ async fn test_execute_full_turn_endpoint() {
     
     // Set up all required directories and files
     let prompts_dir = test_data_dir.join("prompts");
-    std::fs::create_dir_all(&prompts_dir.join("core")).unwrap();
-    std::fs::create_dir_all(&prompts_dir.join("gm")).unwrap();
+    std::fs::create_dir_all(prompts_dir.join("core")).unwrap();
+    std::fs::create_dir_all(prompts_dir.join("gm")).unwrap();
     std::fs::write(prompts_dir.join("core/npc_base.md"), "Test").unwrap();
     std::fs::write(prompts_dir.join("gm/gm_base.md"), "Test").unwrap();
     
     let npcs_dir = test_data_dir.join("npcs");
-    std::fs::create_dir_all(&npcs_dir.join("bear")).unwrap();
-    std::fs::create_dir_all(&npcs_dir.join("wolf")).unwrap();
+    std::fs::create_dir_all(npcs_dir.join("bear")).unwrap();
+    std::fs::create_dir_all(npcs_dir.join("wolf")).unwrap();
     std::fs::write(npcs_dir.join("bear/personality.md"), "Test bear").unwrap();
     std::fs::write(npcs_dir.join("wolf/personality.md"), "Test wolf").unwrap();
     
@@ -400,11 +502,12 @@ async fn test_execute_full_turn_endpoint() {
         }
     }"#).unwrap();
     
-    let game_manager = GameStateManager::new();
+    let game_manager = GameStateManager::new().with_data_dir(test_data_dir.clone());
     let prompt_loader = PromptLoader::new(test_data_dir);
     let prompt_builder = PromptBuilder::new(prompt_loader);
     
     let app_state = Arc::new(server::AppState {
+        config: Default::default(),
         game_manager,
         llm_client: Arc::new(mock_client),
         prompt_builder,
@@ -444,4 +547,1848 @@ async fn test_execute_full_turn_endpoint() {
     let last_turn = &turn_result["last_turn_result"];
     assert!(last_turn["reality"].is_string());
     assert!(last_turn["state_changes"].is_array());
-}
\ No newline at end of file
+    
+    // Both NPCs' memory updates are reported in the turn result
+    let memory_updates = last_turn["memory_updates"].as_array().unwrap();
+    assert_eq!(memory_updates.len(), 2);
+}
+
+#[tokio::test]
+async fn test_memory_update_endpoint_reports_failing_npc() {
+    // The LLM answers with something that isn't a memory update
+    let mock_client = MockLlmClient::new(vec!["I'd rather not".to_string()]);
+    
+    let test_data_dir = std::env::temp_dir().join("two_animals_test_memory_failure");
+    std::fs::create_dir_all(&test_data_dir).unwrap();
+    
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client: Arc::new(mock_client),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    
+    let app = server::create_router(app_state);
+    
+    let memory_updates = json!([{
+        "npc_name": "bear",
+        "intent": {
+            "npc": "bear",
+            "thought": "Hungry",
+            "action": "Fish",
+            "dialogue": null
+        },
+        "reality": "Bear caught a fish",
+        "other_npcs_present": []
+    }]);
+    
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/turn/memories")
+                .header("content-type", "application/json")
+                .body(Body::from(memory_updates.to_string()))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let problem: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(problem["kind"], "npc_failures");
+    assert_eq!(problem["failures"][0]["npc"], "bear");
+    assert_eq!(problem["failures"][0]["kind"], "llm_invalid_response");
+    assert_eq!(problem["failures"][0]["llm_excerpt"], "I'd rather not");
+}
+
+#[tokio::test]
+async fn test_resolve_endpoint_reports_unparseable_gm_response() {
+    let mock_client = MockLlmClient::new(vec!["The bear and wolf stare at each other.".to_string()]);
+    
+    let test_data_dir = setup_test_data_dir("two_animals_test_resolve_failure");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client: Arc::new(mock_client),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state);
+    
+    let intents = json!([{
+        "npc": "bear",
+        "thought": "Hungry",
+        "action": "Fish",
+        "dialogue": null
+    }]);
+    
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/turn/resolve")
+                .header("content-type", "application/json")
+                .body(Body::from(intents.to_string()))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    
+    // A bad GM answer is an upstream failure, not a crash
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let problem: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(problem["kind"], "llm_invalid_response");
+    assert_eq!(problem["llm_excerpt"], "The bear and wolf stare at each other.");
+}
+
+#[tokio::test]
+async fn test_malformed_request_body_returns_problem() {
+    let app = create_test_app().await;
+    
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/turn/resolve")
+                .header("content-type", "application/json")
+                .body(Body::from("not json"))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let problem: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(problem["kind"], "bad_request");
+}
+
+#[tokio::test]
+async fn test_body_without_json_content_type_is_unsupported() {
+    let app = create_test_app().await;
+    
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/turn/resolve")
+                .header("content-type", "text/plain")
+                .body(Body::from("[]"))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let problem: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(problem["kind"], "unsupported_media_type");
+    assert!(problem["message"].as_str().unwrap().contains("Content-Type"), "{problem}");
+}
+
+#[tokio::test]
+async fn test_wrong_method_gets_a_problem_body() {
+    let app = create_test_app().await;
+
+    for (method, uri, allowed) in [("DELETE", "/state", "GET,HEAD"), ("POST", "/metrics", "GET,HEAD")] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().method(method).uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED, "{method} {uri}");
+        assert_eq!(response.headers()["allow"], allowed, "{method} {uri}");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let problem: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem["kind"], "method_not_allowed", "{method} {uri}");
+    }
+}
+
+#[tokio::test]
+async fn test_memories_endpoint_unknown_npc() {
+    let app = create_test_app().await;
+    
+    let response = app
+        .oneshot(Request::builder().uri("/npcs/fox/memories").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_replace_memories_rejects_invalid_values() {
+    let app = create_test_app().await;
+    
+    // Sentiment outside -1.0..=1.0 must be rejected before anything is saved
+    let invalid_memories = json!({
+        "self_memories": {
+            "immediate_context": "Resting",
+            "recent_events": [],
+            "core_memories": []
+        },
+        "relationships": {
+            "wolf": {
+                "immediate_context": "",
+                "recent_memories": [],
+                "long_term_summary": "Neighbours",
+                "core_memories": [],
+                "current_sentiment": 3.0,
+                "overall_bond": 0.0
+            }
+        }
+    });
+    
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/npcs/bear/memories")
+                .header("content-type", "application/json")
+                .body(Body::from(invalid_memories.to_string()))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let problem: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(problem["kind"], "validation_failed");
+    assert!(problem["details"][0].as_str().unwrap().contains("current_sentiment"));
+}
+
+#[tokio::test]
+async fn test_memory_edits_are_saved_to_the_memory_file() {
+    let test_data_dir = setup_test_data_dir("two_animals_test_memory_edits");
+    std::fs::write(test_data_dir.join("npcs/bear/memories.json"), EMPTY_MEMORIES).unwrap();
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client: Arc::new(MockLlmClient::new(vec![])),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir.clone())),
+    });
+    let app = server::create_router(app_state);
+    let saved = || -> Value {
+        serde_json::from_str(&std::fs::read_to_string(test_data_dir.join("npcs/bear/memories.json")).unwrap()).unwrap()
+    };
+
+    // PUT replaces everything
+    let (status, _) = send_json(&app, "PUT", "/npcs/bear/memories", json!({
+        "self_memories": {"immediate_context": "Fishing", "recent_events": [], "core_memories": ["Born by the river"]},
+        "relationships": {
+            "wolf": {
+                "immediate_context": "",
+                "recent_memories": [],
+                "long_term_summary": "Neighbours",
+                "core_memories": [],
+                "current_sentiment": 0.2,
+                "overall_bond": 0.1
+            }
+        }
+    })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(saved()["self_memories"]["immediate_context"], "Fishing");
+    assert_eq!(saved()["relationships"]["wolf"]["long_term_summary"], "Neighbours");
+
+    // PATCH merges into what's there
+    let (status, _) = send_json(&app, "PATCH", "/npcs/bear/memories", json!({
+        "self_memories": {"immediate_context": "Napping"}
+    })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(saved()["self_memories"]["immediate_context"], "Napping");
+    assert_eq!(saved()["self_memories"]["core_memories"][0], "Born by the river");
+
+    // Self events can be added and removed
+    let (status, _) = post_json(&app, "/npcs/bear/memories/self/events", json!({"event": "Caught a salmon"})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(saved()["self_memories"]["recent_events"], json!(["Caught a salmon"]));
+    let (status, _) = send(&app, "DELETE", "/npcs/bear/memories/self/events/0").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(saved()["self_memories"]["recent_events"], json!([]));
+
+    // Relationship memories can be added, pinned, unpinned and removed
+    let (status, _) = post_json(&app, "/npcs/bear/memories/relationships/wolf/memories", json!({
+        "event": "Wolf shared a fish",
+        "timestamp": "2025-04-01T06:00:00Z",
+        "emotional_impact": "grateful",
+        "importance": 0.6
+    })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(saved()["relationships"]["wolf"]["recent_memories"][0]["event"], "Wolf shared a fish");
+    let (status, _) = send(&app, "PUT", "/npcs/bear/memories/relationships/wolf/memories/0/pin").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(saved()["relationships"]["wolf"]["recent_memories"][0]["pinned"], true);
+    let (status, _) = send(&app, "DELETE", "/npcs/bear/memories/relationships/wolf/memories/0/pin").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(saved()["relationships"]["wolf"]["recent_memories"][0]["pinned"].is_null());
+    let (status, _) = send(&app, "DELETE", "/npcs/bear/memories/relationships/wolf/memories/0").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(saved()["relationships"]["wolf"]["recent_memories"], json!([]));
+
+    // And whole relationships removed
+    let (status, _) = send(&app, "DELETE", "/npcs/bear/memories/relationships/wolf").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(saved()["relationships"].as_object().unwrap().is_empty());
+}
+
+#[test]
+fn test_merge_patch_merges_and_removes_keys() {
+    let mut target = json!({
+        "self_memories": {"immediate_context": "Fishing", "core_memories": ["Home"]},
+        "relationships": {"wolf": {"current_sentiment": 0.1}}
+    });
+    let patch = json!({
+        "self_memories": {"immediate_context": "Sleeping"},
+        "relationships": {"wolf": null}
+    });
+    
+    server::utils::merge_patch(&mut target, &patch);
+    
+    assert_eq!(target["self_memories"]["immediate_context"], "Sleeping");
+    assert_eq!(target["self_memories"]["core_memories"][0], "Home");
+    assert!(target["relationships"].as_object().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_memory_prompts_only_contain_npc_observations() {
+    let memory_response = json!({
+        "immediate_self_context": "Watching",
+        "new_self_memory": null,
+        "relationship_updates": {}
+    }).to_string();
+    
+    // Wolf sneaks off to the deep forest; Bear only hears rustling
+    let gm_response = json!({
+        "reality": "Wolf secretly buries a stolen fish in the deep forest",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {},
+        "observations": {
+            "bear": {"saw": null, "heard": "Rustling leaves in the distance", "smelled": null},
+            "wolf": {"saw": "Soft earth under the ferns", "heard": null, "smelled": "The fish"}
+        }
+    }).to_string();
+    
+    let bear_intent = json!({"npc": "bear", "thought": "Quiet day", "action": "Rest", "dialogue": null}).to_string();
+    let wolf_intent = json!({"npc": "wolf", "thought": "Hide it", "action": "Bury the fish", "dialogue": null}).to_string();
+    
+    let client = Arc::new(RecordingLlmClient::new(vec![
+        memory_response.clone(),
+        memory_response,
+        gm_response,
+        wolf_intent,
+        bear_intent,
+    ]));
+    let prompts = Arc::clone(&client.prompts);
+    
+    let test_data_dir = setup_test_data_dir("two_animals_test_observations");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client: client,
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    
+    let response = server::create_router(app_state)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/turn/execute")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({"delay_ms": 0}).to_string()))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    let prompts = prompts.lock().unwrap();
+    let memory_prompts: Vec<&String> = prompts
+        .iter()
+        .filter(|p| p.contains("Memory Update Task"))
+        .collect();
+    assert_eq!(memory_prompts.len(), 2);
+    
+    for prompt in memory_prompts {
+        assert!(!prompt.contains("secretly buries"), "memory prompt leaked the GM's reality");
+        if prompt.contains("You are: bear") {
+            assert!(prompt.contains("Rustling leaves in the distance"));
+            assert!(!prompt.contains("Soft earth under the ferns"));
+        } else {
+            assert!(prompt.contains("Soft earth under the ferns"));
+        }
+    }
+}
+async fn post_json(app: &axum::Router, uri: &str, body: Value) -> (StatusCode, Value) {
+    send_json(app, "POST", uri, body).await
+}
+
+async fn send_json(app: &axum::Router, method: &str, uri: &str, body: Value) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn send(app: &axum::Router, method: &str, uri: &str) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(Request::builder().method(method).uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_player_intent_is_resolved_and_narrated() {
+    let memory_response = json!({
+        "immediate_self_context": "Watching the stranger",
+        "new_self_memory": null,
+        "relationship_updates": {}
+    }).to_string();
+    
+    let gm_response = json!({
+        "reality": "A fox wanders into the clearing and greets Bear and Wolf",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {
+            "fox": "Bear lifts his head from the river and sniffs the air in your direction.",
+            "bear": "A fox has trotted into the clearing.",
+            "wolf": "A fox has trotted into the clearing."
+        }
+    }).to_string();
+    
+    let intent = |npc: &str| json!({
+        "npc": npc,
+        "thought": "Who's that?",
+        "action": "Watch the newcomer",
+        "dialogue": null
+    }).to_string();
+    
+    // Popped in reverse: NPC intents, GM, then memory updates
+    let llm_client = Arc::new(RecordingLlmClient::new(vec![
+        memory_response.clone(),
+        memory_response,
+        gm_response,
+        intent("wolf"),
+        intent("bear"),
+    ]));
+    let prompts = Arc::clone(&llm_client.prompts);
+    
+    let test_data_dir = setup_test_data_dir("two_animals_test_player");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone())
+            .with_player_intent_timeout(std::time::Duration::from_secs(5)),
+        llm_client,
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state);
+    
+    let (status, player) = post_json(&app, "/player", json!({"name": "Fox"})).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(player["name"], "fox");
+    
+    let (status, _) = post_json(&app, "/player/intent", json!({
+        "action": "Trot into the clearing",
+        "dialogue": "Hello, neighbours!"
+    })).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    
+    let (status, result) = post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::OK);
+    
+    // The GM saw the player's intent and the player got their narration back
+    let gm_prompt = prompts.lock().unwrap().iter().find(|p| p.contains("Trot into the clearing")).cloned();
+    assert!(gm_prompt.is_some());
+    let last_turn = &result["last_turn_result"];
+    assert_eq!(
+        last_turn["player_narration"],
+        "Bear lifts his head from the river and sniffs the air in your direction."
+    );
+    
+    // Only the NPCs keep memories
+    assert_eq!(last_turn["memory_updates"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_turn_goes_on_without_player_after_timeout() {
+    let test_data_dir = setup_test_data_dir("two_animals_test_player_timeout");
+    let gm_response = json!({
+        "reality": "The forest is quiet",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {
+            "bear": "The river is cold around your paws. What now?",
+            "wolf": "Bear is fishing downstream. What now?"
+        }
+    }).to_string();
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone())
+            .with_player_intent_timeout(std::time::Duration::from_millis(20)),
+        llm_client: Arc::new(MockLlmClient::new(vec![
+            gm_response,
+            json!({"npc": "wolf", "thought": "Quiet", "action": "Rest", "dialogue": null}).to_string(),
+            json!({"npc": "bear", "thought": "Quiet", "action": "Rest", "dialogue": null}).to_string(),
+        ])),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state);
+    
+    let (status, _) = post_json(&app, "/player", json!({"name": "fox"})).await;
+    assert_eq!(status, StatusCode::CREATED);
+    
+    let (status, result) = post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(result["turns_executed"], 1);
+}
+
+#[tokio::test]
+async fn test_turn_without_player_prompt_has_no_player_narration() {
+    let test_data_dir = setup_test_data_dir("two_animals_test_player_no_prompt");
+    let gm_response = json!({
+        "reality": "The forest is quiet",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {
+            "bear": "The river is cold around your paws. What now?",
+            "wolf": "Bear is fishing downstream. What now?"
+        }
+    }).to_string();
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone())
+            .with_player_intent_timeout(std::time::Duration::from_millis(20)),
+        llm_client: Arc::new(MockLlmClient::new(vec![
+            gm_response,
+            json!({"npc": "wolf", "thought": "Quiet", "action": "Rest", "dialogue": null}).to_string(),
+            json!({"npc": "bear", "thought": "Quiet", "action": "Rest", "dialogue": null}).to_string(),
+        ])),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state.clone());
+
+    let (status, _) = post_json(&app, "/player", json!({"name": "fox"})).await;
+    assert_eq!(status, StatusCode::CREATED);
+    // What the GM told the player on some earlier turn
+    app_state.game_manager.state.lock().unwrap().set_npc_prompt("fox", "Bear waves at you.".to_string());
+
+    let (status, result) = post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(result["last_turn_result"]["player_narration"].is_null(), "{result}");
+}
+
+#[tokio::test]
+async fn test_player_cannot_take_an_npc_name() {
+    let app = create_test_app().await;
+    
+    let (status, problem) = post_json(&app, "/player", json!({"name": "bear"})).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(problem["kind"], "conflict");
+    
+    let (status, _) = post_json(&app, "/player/intent", json!({"action": "Wave"})).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_gm_intervention_reaches_gm_as_fact() {
+    let gm_response = json!({
+        "reality": "Rain pours down on the forest",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {
+            "bear": "The river is cold around your paws. What now?",
+            "wolf": "Bear is fishing downstream. What now?"
+        }
+    }).to_string();
+    let intent = |npc: &str| json!({"npc": npc, "thought": "Wet", "action": "Shelter", "dialogue": null}).to_string();
+    
+    let llm_client = Arc::new(RecordingLlmClient::new(vec![gm_response, intent("wolf"), intent("bear")]));
+    let prompts = Arc::clone(&llm_client.prompts);
+    
+    let test_data_dir = setup_test_data_dir("two_animals_test_world_events");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client,
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state);
+    
+    let (status, event) = post_json(&app, "/world/events", json!({
+        "description": "A storm rolls in over the forest"
+    })).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(event["id"], "intervention_t1_1");
+    
+    let (status, result) = post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::OK);
+    
+    let gm_prompt = prompts.lock().unwrap().iter().find(|p| p.contains("\"world_events\"")).cloned();
+    assert!(gm_prompt.unwrap().contains("A storm rolls in over the forest"));
+    assert_eq!(result["last_turn_result"]["world_events"][0]["id"], "intervention_t1_1");
+    
+    // Interventions fire once
+    let response = app
+        .oneshot(Request::builder().uri("/world/events").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let overview: Value = serde_json::from_slice(&body).unwrap();
+    assert!(overview["pending"].as_array().unwrap().is_empty());
+    assert_eq!(overview["history"][0]["turn"], 1);
+}
+
+#[tokio::test]
+async fn test_gm_item_transfers_are_validated() {
+    let gm_response = json!({
+        "reality": "Bear scoops a trout from the shallows while Wolf paws at nothing",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {},
+        "item_transfers": [
+            {"item": "fish_1", "action": "pick_up", "actor": "bear"},
+            // The berries grow in the deep forest, far from Wolf
+            {"item": "berries_1", "action": "consume", "actor": "wolf"},
+            {"item": "fish_1", "action": "give", "actor": "bear", "target": "wolf", "new_state": "half-eaten"}
+        ]
+    }).to_string();
+    
+    let test_data_dir = setup_test_data_dir("two_animals_test_items");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client: Arc::new(MockLlmClient::new(vec![gm_response])),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state);
+    
+    let (status, resolution) = post_json(&app, "/turn/resolve", json!([])).await;
+    assert_eq!(status, StatusCode::OK);
+    
+    let rejected = resolution["rejected_item_transfers"].as_array().unwrap();
+    assert_eq!(rejected.len(), 1);
+    assert_eq!(rejected[0]["item"], "berries_1");
+    
+    let response = app
+        .oneshot(Request::builder().uri("/state").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let state: Value = serde_json::from_slice(&body).unwrap();
+    let fish = &state["items"]["fish_1"];
+    assert_eq!(fish["holder"], json!({"type": "carried", "by": "wolf"}));
+    assert_eq!(fish["state"], "half-eaten");
+    assert_eq!(fish["history"].as_array().unwrap().len(), 2);
+    assert_eq!(state["items"]["berries_1"]["holder"]["type"], "location");
+}
+
+#[tokio::test]
+async fn test_turn_advances_world_clock_into_prompts() {
+    let gm_response = json!({
+        "reality": "A quiet morning",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {
+            "bear": "The river is cold around your paws. What now?",
+            "wolf": "Bear is fishing downstream. What now?"
+        }
+    }).to_string();
+    let intent = |npc: &str| json!({"npc": npc, "thought": "Sleepy", "action": "Yawn", "dialogue": null}).to_string();
+    
+    let llm_client = Arc::new(RecordingLlmClient::new(vec![gm_response, intent("wolf"), intent("bear")]));
+    let prompts = Arc::clone(&llm_client.prompts);
+    
+    let test_data_dir = setup_test_data_dir("two_animals_test_clock");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()).with_turn_duration_minutes(90),
+        llm_client,
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state);
+    
+    let (status, _) = post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::OK);
+    
+    let response = app
+        .oneshot(Request::builder().uri("/state").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let state: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(state["clock"]["time"], "2025-04-01T07:30:00Z");
+    assert_eq!(state["clock"]["time_of_day"], "morning");
+    
+    // NPCs and the GM both learn what time it is
+    let prompts = prompts.lock().unwrap();
+    assert!(prompts.iter().any(|p| p.contains("It is morning")));
+    assert!(prompts.iter().any(|p| p.contains("\"time_of_day\": \"morning\"")));
+}
+
+#[tokio::test]
+async fn test_llm_calls_are_sampled_by_role_and_npc() {
+    let gm_response = json!({
+        "reality": "A quiet morning",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {"bear": "What now?", "wolf": "What now?"}
+    }).to_string();
+    let intent = |npc: &str| json!({"npc": npc, "thought": "Sleepy", "action": "Yawn", "dialogue": null}).to_string();
+
+    let llm_client = Arc::new(RecordingLlmClient::new(vec![gm_response, intent("wolf"), intent("bear")]));
+    let options = Arc::clone(&llm_client.options);
+
+    let mut config = server::Config::default();
+    config.llm.sampling.seed = Some(7);
+    config.llm.sampling.npcs.insert("wolf".to_string(), LlmOptions { temperature: Some(1.2), ..Default::default() });
+    config.llm.sampling.gm.max_tokens = Some(2000);
+
+    let test_data_dir = setup_test_data_dir("two_animals_test_sampling");
+    let app_state = Arc::new(server::AppState {
+        game_manager: GameStateManager::new()
+            .with_data_dir(test_data_dir.clone())
+            .with_sampling(config.llm.sampling.clone()),
+        config,
+        llm_client,
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state);
+
+    let (status, _) = post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let options = options.lock().unwrap();
+    assert!(options.iter().all(|o| o.seed == Some(7)), "{options:?}");
+    // Wolf's own temperature, Bear's from the intent default, the GM's and the memory keeper's
+    for temperature in [1.2, 0.9, 0.2, 0.3] {
+        assert!(options.iter().any(|o| o.temperature == Some(temperature)), "no call at {temperature}: {options:?}");
+    }
+    let gm_options = options.iter().find(|o| o.temperature == Some(0.2)).unwrap();
+    assert_eq!(gm_options.max_tokens, Some(2000));
+    assert!(options.iter().filter(|o| o.temperature != Some(0.2)).all(|o| o.max_tokens.is_none()));
+}
+
+#[tokio::test]
+async fn test_prompts_keep_instructions_apart_from_the_turn() {
+    let test_data_dir = setup_test_data_dir("two_animals_test_prompt_messages");
+    let prompt_builder = PromptBuilder::new(PromptLoader::new(test_data_dir));
+    let game_manager = GameStateManager::new();
+    let game_state = game_manager.get_state();
+    let mut wolf = game_state.npcs["wolf"].clone();
+    wolf.next_prompt = Some("Bear is fishing downstream. What now?".to_string());
+
+    let prompt = prompt_builder.build_npc_intent_prompt(&wolf, &game_state).unwrap();
+    let roles: Vec<MessageRole> = prompt.messages.iter().map(|m| m.role).collect();
+    assert_eq!(roles, [MessageRole::System, MessageRole::System, MessageRole::User, MessageRole::User]);
+    assert_eq!(prompt.messages[1].content, "Test wolf");
+    assert!(prompt.messages[2].content.contains("## Your Current Memories"));
+    assert_eq!(prompt.messages[3].content, "Bear is fishing downstream. What now?");
+    // Flattened for the CLI, it reads as one document in the same order
+    let rendered = prompt.render();
+    assert!(rendered.starts_with("Test\n\n---\n\nTest wolf\n\n---\n\n## Your Current Memories"));
+    assert!(rendered.ends_with("\n\n---\n\nBear is fishing downstream. What now?"));
+
+    // A repair carries on the GM's conversation
+    let gm_prompt = prompt_builder.build_gm_prompt("{}").unwrap();
+    let repair = prompt_builder.build_gm_repair_prompt(&gm_prompt, "{\"reality\": \"\"}", &["Deer doesn't exist".to_string()]);
+    let roles: Vec<MessageRole> = repair.messages.iter().map(|m| m.role).collect();
+    assert_eq!(roles, [MessageRole::System, MessageRole::User, MessageRole::Assistant, MessageRole::User]);
+    assert!(repair.messages[3].content.contains("- Deer doesn't exist"));
+}
+
+#[tokio::test]
+async fn test_gm_response_contradicting_world_is_repaired() {
+    // Moves a creature that doesn't exist and forgets about Wolf
+    let broken_response = json!({
+        "reality": "Bear and a deer drink from the river",
+        "state_changes": [{"npc": "deer", "location": "DeepForest", "activity": "fleeing"}],
+        "contracts": [],
+        "next_prompts": {"bear": "The water is cold."}
+    }).to_string();
+    let repaired_response = json!({
+        "reality": "Bear drinks while Wolf watches",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {"bear": "The water is cold.", "wolf": "Bear is drinking."}
+    }).to_string();
+    
+    let llm_client = Arc::new(RecordingLlmClient::new(vec![repaired_response, broken_response]));
+    let prompts = Arc::clone(&llm_client.prompts);
+    
+    let test_data_dir = setup_test_data_dir("two_animals_test_gm_repair");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client,
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state);
+    
+    let intents = json!([
+        {"npc": "bear", "thought": "Thirsty", "action": "Drink", "dialogue": null},
+        {"npc": "wolf", "thought": "Watchful", "action": "Watch Bear", "dialogue": null}
+    ]);
+    let (status, resolution) = post_json(&app, "/turn/resolve", intents).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resolution["reality"], "Bear drinks while Wolf watches");
+    assert!(resolution.get("warnings").is_none());
+    
+    // The GM was told exactly what was wrong
+    let prompts = prompts.lock().unwrap();
+    assert_eq!(prompts.len(), 2);
+    assert!(prompts[1].contains("state_changes: unknown character \"deer\""));
+    assert!(prompts[1].contains("wolf acted this turn"));
+}
+
+#[tokio::test]
+async fn test_gm_response_still_invalid_after_repairs_is_corrected() {
+    // An unknown contract update the GM never fixes
+    let broken_response = json!({
+        "reality": "Bear keeps talking to nobody",
+        "state_changes": [],
+        "contracts": [{"id": "conv_missing", "participants": ["bear"], "action": "update", "transcript_entry": null}],
+        "next_prompts": {"bear": "Nobody answers.", "owl": "Hoo?"}
+    }).to_string();
+    
+    let test_data_dir = setup_test_data_dir("two_animals_test_gm_corrected");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client: Arc::new(MockLlmClient::new(vec![
+            broken_response.clone(),
+            broken_response.clone(),
+            broken_response,
+        ])),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state);
+    
+    let intents = json!([{"npc": "bear", "thought": "Chatty", "action": "Talk", "dialogue": "Hello?"}]);
+    let (status, resolution) = post_json(&app, "/turn/resolve", intents).await;
+    assert_eq!(status, StatusCode::OK);
+    
+    // The valid part is applied, the rest dropped with warnings
+    assert!(resolution["contracts"].as_array().unwrap().is_empty());
+    assert!(resolution["next_prompts"].get("owl").is_none());
+    assert_eq!(resolution["next_prompts"]["bear"], "Nobody answers.");
+    assert_eq!(resolution["warnings"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_failed_turn_leaves_world_untouched() {
+    let intent = |npc: &str| json!({"npc": npc, "thought": "Calm", "action": "Rest", "dialogue": null}).to_string();
+    let llm_client = Arc::new(MockLlmClient::new(vec![
+        "The GM rambles without any JSON".to_string(),
+        intent("wolf"),
+        intent("bear"),
+    ]));
+    
+    let test_data_dir = setup_test_data_dir("two_animals_test_failed_turn");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client,
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state.clone());
+    let before = app_state.game_manager.get_state();
+    
+    let (status, _) = post_json(&app, "/world/events", json!({"description": "Thunder rumbles"})).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    
+    let (status, problem) = post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(problem["kind"], "llm_invalid_response");
+    
+    // Neither the turn counter, the clock nor the world events moved
+    let after = app_state.game_manager.get_state();
+    assert_eq!(after.turn, before.turn);
+    assert_eq!(after.clock.time, before.clock.time);
+    let overview = app_state.game_manager.world_events.overview(&app_state.game_manager.data_dir).unwrap();
+    assert_eq!(overview.pending.len(), 1);
+    assert!(overview.history.is_empty());
+}
+
+#[tokio::test]
+async fn test_contracts_are_written_under_the_configured_data_dir() {
+    let gm_response = json!({
+        "reality": "Bear greets Wolf",
+        "state_changes": [],
+        "contracts": [{
+            "id": "new",
+            "participants": ["bear", "wolf"],
+            "action": "create",
+            "transcript_entry": {"reality": "Bear greets Wolf", "details": {}}
+        }],
+        "next_prompts": {"bear": "Wolf looks at you.", "wolf": "Bear says hello."}
+    }).to_string();
+    
+    let test_data_dir = setup_test_data_dir("two_animals_test_data_dir_contracts");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client: Arc::new(MockLlmClient::new(vec![gm_response])),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir.clone())),
+    });
+    let app = server::create_router(app_state.clone());
+    
+    let intents = json!([
+        {"npc": "bear", "thought": "Friendly", "action": "Greet Wolf", "dialogue": "Hello"},
+        {"npc": "wolf", "thought": "Wary", "action": "Listen", "dialogue": null}
+    ]);
+    let (status, _) = post_json(&app, "/turn/resolve", intents).await;
+    assert_eq!(status, StatusCode::OK);
+    
+    let state = app_state.game_manager.get_state();
+    let contract = state.contracts.values().next().unwrap();
+    let transcript = std::path::Path::new(&contract.transcript_file);
+    assert!(transcript.starts_with(test_data_dir.join("contracts")));
+    assert!(transcript.exists());
+}
+
+#[tokio::test]
+async fn test_admin_config_endpoint_shows_effective_config() {
+    let app = create_test_app().await;
+    
+    let response = app
+        .oneshot(Request::builder().uri("/admin/config").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let config: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(config["server"]["bind"], "0.0.0.0:3000");
+    assert_eq!(config["memory"]["max_relationship_memories"], 10);
+}
+
+#[tokio::test]
+async fn test_worlds_run_independently_and_can_be_paused_and_deleted() {
+    let gm_response = json!({
+        "reality": "A quiet meadow",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {"bear": "Bees hum. What now?", "wolf": "Grass sways. What now?"}
+    }).to_string();
+    let intent = |npc: &str| json!({"npc": npc, "thought": "Calm", "action": "Rest", "dialogue": null}).to_string();
+    
+    let test_data_dir = setup_test_data_dir("two_animals_test_worlds_template");
+    std::fs::write(test_data_dir.join("npcs/bear/memories.json"), EMPTY_MEMORIES).unwrap();
+    let worlds_dir = std::env::temp_dir().join("two_animals_test_worlds");
+    let _ = std::fs::remove_dir_all(&worlds_dir);
+    let mut config = server::Config::default();
+    config.paths.worlds_dir = worlds_dir.clone();
+    
+    let app_state = Arc::new(server::AppState {
+        config,
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client: Arc::new(MockLlmClient::new(vec![gm_response, intent("wolf"), intent("bear")])),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state.clone());
+    
+    // A new world starts from the template's design, not its memories
+    let (status, world) = post_json(&app, "/worlds", json!({"id": "meadow"})).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(world["template"], "default");
+    assert_eq!(world["npcs"], json!(["bear", "wolf"]));
+    assert!(worlds_dir.join("meadow/npcs/bear/personality.md").exists());
+    assert!(!worlds_dir.join("meadow/npcs/bear/memories.json").exists());
+    
+    let (status, _) = post_json(&app, "/worlds", json!({"id": "meadow"})).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = post_json(&app, "/worlds", json!({"id": "Not a/valid id"})).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    
+    let (status, worlds) = send(&app, "GET", "/worlds").await;
+    assert_eq!(status, StatusCode::OK);
+    let ids: Vec<&str> = worlds.as_array().unwrap().iter().map(|w| w["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["default", "meadow"]);
+    
+    // A turn in the meadow leaves the default world where it was
+    let (status, _) = post_json(&app, "/worlds/meadow/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, world) = send(&app, "GET", "/worlds/meadow").await;
+    assert_eq!(world["turn"], 1);
+    let (_, state) = send(&app, "GET", "/worlds/meadow/state").await;
+    assert_eq!(state["turn"], 1);
+    assert_eq!(app_state.game_manager.current_turn(), 0);
+    
+    // A paused world refuses turns until it's resumed
+    let (status, world) = post_json(&app, "/worlds/meadow/pause", json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(world["paused"], true);
+    let (status, problem) = post_json(&app, "/worlds/meadow/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(problem["kind"], "conflict");
+    
+    let (status, _) = send(&app, "DELETE", "/worlds/default").await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send(&app, "DELETE", "/worlds/meadow").await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(!worlds_dir.join("meadow").exists());
+    let (status, _) = send(&app, "GET", "/worlds/meadow/state").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_world_copies_wait_for_the_template_and_clean_up_after_failures() {
+    let test_data_dir = setup_test_data_dir("two_animals_test_worlds_copy_template");
+    let worlds_dir = std::env::temp_dir().join("two_animals_test_worlds_copy");
+    let _ = std::fs::remove_dir_all(&worlds_dir);
+    let mut config = server::Config::default();
+    config.paths.worlds_dir = worlds_dir.clone();
+
+    let app_state = Arc::new(server::AppState {
+        config,
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client: Arc::new(MockLlmClient::new(vec![])),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir.clone())),
+    });
+    let app = server::create_router(app_state.clone());
+
+    // A file that can't be copied fails the copy, and nothing of it is left behind
+    let dangling = test_data_dir.join("npcs/bear/portrait.png");
+    std::os::unix::fs::symlink(test_data_dir.join("missing.png"), &dangling).unwrap();
+    let (status, _) = post_json(&app, "/worlds", json!({"id": "meadow"})).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(!worlds_dir.join("meadow").exists());
+    std::fs::remove_file(&dangling).unwrap();
+
+    // While the template is mid-turn the copy waits, and its id stays taken
+    let turn_guard = app_state.game_manager.turn_lock.lock().await;
+    let creating = tokio::spawn({
+        let app = app.clone();
+        async move { post_json(&app, "/worlds", json!({"id": "meadow"})).await }
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert!(!creating.is_finished());
+    assert!(!worlds_dir.join("meadow").exists());
+    let (status, _) = post_json(&app, "/worlds", json!({"id": "meadow"})).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send(&app, "GET", "/worlds/meadow").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    drop(turn_guard);
+
+    let (status, world) = creating.await.unwrap();
+    assert_eq!(status, StatusCode::CREATED, "{world}");
+    assert!(worlds_dir.join("meadow/npcs/bear/personality.md").exists());
+
+    // Worlds can be copied from any world, and resumed after a pause
+    let (status, world) = post_json(&app, "/worlds", json!({"id": "glade", "template": "meadow"})).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(world["template"], "meadow");
+    let (status, world) = post_json(&app, "/worlds/glade/pause", json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(world["paused"], true);
+    let (status, world) = post_json(&app, "/worlds/glade/resume", json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(world["paused"], false);
+    let (status, state) = send(&app, "GET", "/worlds/glade/state").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(state["npcs"]["bear"].is_object(), "{state}");
+
+    let (status, _) = send(&app, "DELETE", "/worlds/glade").await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(!worlds_dir.join("glade").exists());
+    assert!(worlds_dir.join("meadow").exists());
+
+    // A world whose files can't be removed stays reachable, paused, for another try
+    std::fs::remove_dir_all(worlds_dir.join("meadow")).unwrap();
+    let (status, _) = send(&app, "DELETE", "/worlds/meadow").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    let (status, world) = send(&app, "GET", "/worlds/meadow").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(world["paused"], true);
+}
+
+#[tokio::test]
+async fn test_usage_is_accounted_per_turn_npc_and_role() {
+    let gm_response = json!({
+        "reality": "A quiet morning",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {"bear": "Birds sing. What now?", "wolf": "Wind blows. What now?"}
+    }).to_string();
+    let intent = |npc: &str| json!({"npc": npc, "thought": "Calm", "action": "Rest", "dialogue": null}).to_string();
+    
+    let test_data_dir = setup_test_data_dir("two_animals_test_usage");
+    let prices = [(
+        "mock-model".to_string(),
+        server::config::ModelPrice { prompt_per_million: 1.0, completion_per_million: 10.0 },
+    )].into();
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()).with_usage_prices(prices),
+        llm_client: Arc::new(MockLlmClient::new(vec![gm_response, intent("wolf"), intent("bear")])),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state);
+    
+    let (status, _) = post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::OK);
+    
+    let (status, usage) = send(&app, "GET", "/metrics/usage").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(usage["by_role"]["intent"]["calls"], 2);
+    assert_eq!(usage["by_role"]["gm"]["calls"], 1);
+    assert!(usage["by_npc"]["bear"]["calls"].as_u64().unwrap() >= 1);
+    assert!(usage["by_npc"].get("gm").is_none());
+    
+    // Every call is 1000 prompt tokens at $1/M plus 100 completion tokens at $10/M
+    let calls = usage["total"]["calls"].as_u64().unwrap();
+    assert_eq!(usage["by_turn"]["1"]["calls"], calls);
+    let cost = usage["total"]["cost_usd"].as_f64().unwrap();
+    assert!((cost - calls as f64 * 0.002).abs() < 1e-9);
+    assert!(usage["unpriced_models"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_prometheus_metrics_endpoint() {
+    let test_data_dir = setup_test_data_dir("two_animals_test_prometheus");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client: Arc::new(MockLlmClient::new(vec![
+            "The GM mumbles without any JSON".to_string(),
+            json!({"npc": "wolf", "thought": "Calm", "action": "Rest", "dialogue": null}).to_string(),
+            json!({"npc": "bear", "thought": "Calm", "action": "Rest", "dialogue": null}).to_string(),
+        ])),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state);
+    
+    let (status, _) = send(&app, "GET", "/npcs/bear/memories").await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    
+    let response = app
+        .oneshot(Request::builder().uri("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let metrics = String::from_utf8(body.to_vec()).unwrap();
+    
+    // Routes are labelled by their pattern, not the NPC in the path
+    assert!(metrics.contains(r#"two_animals_http_requests_total{method="GET",route="/npcs/{name}/memories",status="200"}"#));
+    assert!(metrics.contains(r#"two_animals_turns_total{outcome="failed"}"#));
+    assert!(metrics.contains(r#"two_animals_llm_parse_failures_total{expected="GmResponse"}"#));
+    assert!(metrics.contains(r#"two_animals_llm_call_duration_seconds_bucket{provider="mock",role="intent""#));
+}
+
+// Collects what a tracing subscriber writes, for looking at the log lines
+#[derive(Clone, Default)]
+struct CapturedLogs(Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_llm_calls_are_traced_inside_turn_and_phase_spans() {
+    use tracing_subscriber::layer::SubscriberExt;
+    
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::registry().with(
+        tracing_subscriber::fmt::layer()
+            .json()
+            .with_span_list(true)
+            .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+            .with_writer(move || writer.clone()),
+    );
+    let _guard = tracing::subscriber::set_default(subscriber);
+    
+    let gm_response = json!({
+        "reality": "A quiet morning",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {"bear": "Birds sing. What now?", "wolf": "Wind blows. What now?"}
+    }).to_string();
+    let intent = |npc: &str| json!({"npc": npc, "thought": "Calm", "action": "Rest", "dialogue": null}).to_string();
+    let test_data_dir = setup_test_data_dir("two_animals_test_tracing");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client: Arc::new(MockLlmClient::new(vec![gm_response, intent("wolf"), intent("bear")])),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state);
+    let (status, _) = post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::OK);
+    
+    let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<Value> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    
+    // Each finished LLM call reports its role, NPC, provider and tokens, nested in its turn and phase
+    let bear_intent = lines
+        .iter()
+        .find(|line| line["span"]["name"] == "llm_call" && line["span"]["npc"] == "bear" && line["span"]["role"] == "intent")
+        .expect("no llm_call span closed for the bear's intent");
+    assert_eq!(bear_intent["span"]["provider"], "mock");
+    assert_eq!(bear_intent["span"]["prompt_tokens"], 1000);
+    let parents: Vec<&str> = bear_intent["spans"].as_array().unwrap().iter().map(|s| s["name"].as_str().unwrap()).collect();
+    assert_eq!(parents, ["turn", "intents"]);
+    assert_eq!(bear_intent["spans"][0]["turn"], 1);
+    
+    assert!(lines.iter().any(|line| line["span"]["role"] == "gm" && line["spans"][1]["name"] == "gm"));
+    assert!(lines.iter().any(|line| line["span"]["role"] == "memory" && line["spans"][1]["name"] == "memories"));
+}
+
+#[tokio::test]
+async fn test_turn_events_are_published_as_they_happen() {
+    let gm_response = json!({
+        "reality": "A quiet morning",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {"bear": "Birds sing. What now?", "wolf": "Wind blows. What now?"}
+    }).to_string();
+    let intent = |npc: &str| json!({"npc": npc, "thought": "Sleepy", "action": "Yawn", "dialogue": null}).to_string();
+    let test_data_dir = setup_test_data_dir("two_animals_test_feed");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client: Arc::new(MockLlmClient::new(vec![gm_response, intent("wolf"), intent("bear")])),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state.clone());
+    
+    // The SSE endpoint stays open, so only its headers are checked
+    let response = app.clone()
+        .oneshot(Request::builder().uri("/turn/events").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    
+    let mut feed = app_state.game_manager.feed.subscribe();
+    let (status, _) = post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::OK);
+    
+    let mut events = Vec::new();
+    while let Ok(event) = feed.try_recv() {
+        events.push(serde_json::to_value(&event).unwrap());
+    }
+    assert_eq!(events.first().unwrap(), &json!({"type": "turn_started", "turn": 1}));
+    assert_eq!(events.last().unwrap(), &json!({"type": "turn_completed", "turn": 1}));
+    let bear_delta = events.iter().find(|e| e["type"] == "intent_delta" && e["npc"] == "bear").unwrap();
+    assert!(bear_delta["text"].as_str().unwrap().contains("Sleepy"));
+    assert!(events.iter().any(|e| e["type"] == "intent_ready" && e["npc"] == "wolf" && e["action"] == "Yawn"));
+}
+
+// Streams a response that rambles on without ever getting to JSON
+struct RamblingLlmClient {
+    chunks_sent: Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl LlmClient for RamblingLlmClient {
+    async fn query(&self, _prompt: Prompt, _working_dir: &std::path::Path, _options: &LlmOptions) -> anyhow::Result<LlmResponse> {
+        unreachable!("intents are streamed")
+    }
+    
+    async fn query_stream(&self, _prompt: Prompt, _working_dir: &std::path::Path, _options: &LlmOptions) -> anyhow::Result<server::llm::LlmStream> {
+        use futures::StreamExt;
+        let chunks_sent = Arc::clone(&self.chunks_sent);
+        Ok(futures::stream::iter(0..100)
+            .map(move |_| {
+                chunks_sent.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(server::llm::LlmChunk { text: "Well, let me think about this for a while. ".to_string(), usage: None, cache_key: None })
+            })
+            .boxed())
+    }
+}
+
+#[tokio::test]
+async fn test_intent_stream_without_json_is_cut_off_early() {
+    let chunks_sent = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let test_data_dir = setup_test_data_dir("two_animals_test_stream_abort");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client: Arc::new(RamblingLlmClient { chunks_sent: Arc::clone(&chunks_sent) }),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state);
+    
+    let (status, problem) = post_json(&app, "/turn/collect", json!({})).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    let failures = problem["failures"].as_array().unwrap();
+    assert_eq!(failures.len(), 2);
+    assert_eq!(failures[0]["kind"], "llm_invalid_response");
+    assert!(failures[0]["message"].as_str().unwrap().contains("stopped it early"));
+    
+    // Both NPCs gave up long before the 100 chunks each would have sent
+    assert!(chunks_sent.load(std::sync::atomic::Ordering::SeqCst) < 40);
+}
+
+#[tokio::test]
+async fn test_contract_participant_without_intent_still_summarizes_it() {
+    let test_data_dir = setup_test_data_dir("two_animals_test_contract_no_intent");
+    let game_manager = GameStateManager::new().with_data_dir(test_data_dir.clone());
+    let opening = serde_json::from_value(json!({"reality": "Bear greets Wolf at the river", "details": {}})).unwrap();
+    let contract = game_manager
+        .contract_manager()
+        .create_contract(vec!["bear".to_string(), "wolf".to_string()], Some(opening))
+        .unwrap();
+    {
+        let mut state = game_manager.state.lock().unwrap();
+        state.set_npc_contract("bear", Some(contract.id.clone()));
+        state.set_npc_contract("wolf", Some(contract.id.clone()));
+        state.add_contract(contract.clone());
+    }
+
+    let llm_client = Arc::new(ByRoleLlmClient {
+        failing_intent: Some("wolf"),
+        ..ByRoleLlmClient::new(json!({
+            "reality": "Bear gives up waiting and wanders off",
+            "state_changes": [],
+            "contracts": [{
+                "id": contract.id,
+                "participants": [],
+                "action": "end",
+                "transcript_entry": {"reality": "Bear gives up waiting and wanders off", "details": {}}
+            }],
+            "next_prompts": {"bear": "What now?", "wolf": "Bear has gone. What now?"}
+        }))
+    });
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager,
+        llm_client: llm_client.clone(),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state.clone());
+
+    let (status, body) = post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let last_turn = &body["last_turn_result"];
+    assert_eq!(last_turn["dropped_intents"][0]["npc"], "wolf", "{last_turn}");
+
+    // Wolf never acted, but was there for all of it
+    let memory_updates = last_turn["memory_updates"].as_array().unwrap();
+    let npcs: Vec<&str> = memory_updates.iter().map(|update| update["npc"].as_str().unwrap()).collect();
+    assert_eq!(npcs, ["bear", "wolf"], "{last_turn}");
+    assert!(memory_updates.iter().all(|update| update["error"].is_null()), "{memory_updates:?}");
+
+    let memory_prompts = llm_client.prompts_for(LlmRole::Memory);
+    let wolf_update = memory_prompts.iter().find(|p| p.contains("You are: wolf")).unwrap();
+    assert!(wolf_update.contains("You didn't get to act this turn."));
+    assert!(wolf_update.contains("Bear has gone. What now?"));
+    let summaries: Vec<&String> = memory_prompts.iter().filter(|p| p.contains("## Interaction Summary Task")).collect();
+    assert_eq!(summaries.len(), 2);
+    assert!(summaries.iter().all(|summary| summary.contains("Bear gives up waiting and wanders off")));
+}
+
+// Mock LLM client that answers by role, told apart by the default temperatures, and
+// records every prompt. Memory updates and the GM can be made slower than anything
+// else, and one NPC's intents can be made to fail.
+struct ByRoleLlmClient {
+    gm_response: Value,
+    memory_delay: std::time::Duration,
+    gm_delay: std::time::Duration,
+    failing_intent: Option<&'static str>,
+    failing_summary: bool,
+    prompts: Arc<std::sync::Mutex<Vec<(LlmRole, String)>>>,
+}
+
+impl ByRoleLlmClient {
+    fn new(gm_response: Value) -> Self {
+        Self {
+            gm_response,
+            memory_delay: std::time::Duration::ZERO,
+            gm_delay: std::time::Duration::ZERO,
+            failing_intent: None,
+            failing_summary: false,
+            prompts: Arc::new(std::sync::Mutex::new(Vec::new())),
+        }
+    }
+
+    fn prompts_for(&self, role: LlmRole) -> Vec<String> {
+        self.prompts.lock().unwrap().iter().filter(|(r, _)| *r == role).map(|(_, p)| p.clone()).collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmClient for ByRoleLlmClient {
+    async fn query(&self, prompt: Prompt, _working_dir: &std::path::Path, options: &LlmOptions) -> anyhow::Result<LlmResponse> {
+        let role = match options.temperature {
+            Some(0.9) => LlmRole::Intent,
+            Some(0.3) => LlmRole::Memory,
+            _ => LlmRole::Gm,
+        };
+        let rendered = prompt.render();
+        self.prompts.lock().unwrap().push((role, rendered.clone()));
+        let text = match role {
+            LlmRole::Intent => {
+                let npc = if rendered.contains("Test bear") { "bear" } else { "wolf" };
+                if self.failing_intent == Some(npc) {
+                    anyhow::bail!("{npc} lost their train of thought");
+                }
+                json!({"npc": npc, "thought": "Wet", "action": "Shake off", "dialogue": format!("{npc} was here")})
+            }
+            LlmRole::Memory => {
+                tokio::time::sleep(self.memory_delay).await;
+                if self.failing_summary && rendered.contains("## Interaction Summary Task") {
+                    anyhow::bail!("the interaction is a blur");
+                }
+                json!({"immediate_self_context": "Still dripping from the river", "new_self_memory": null, "relationship_updates": {}})
+            }
+            _ => {
+                tokio::time::sleep(self.gm_delay).await;
+                self.gm_response.clone()
+            }
+        };
+        Ok(LlmResponse::new(text.to_string()))
+    }
+}
+
+#[tokio::test]
+async fn test_turns_see_last_turns_memories_in_either_mode() {
+    for mode in [TurnMode::Pipelined, TurnMode::Strict] {
+        let llm_client = Arc::new(ByRoleLlmClient {
+            memory_delay: std::time::Duration::from_millis(50),
+            ..ByRoleLlmClient::new(json!({
+                "reality": "Both animals shake off",
+                "state_changes": [],
+                "contracts": [],
+                "next_prompts": {"bear": "What now?", "wolf": "What now?"}
+            }))
+        });
+        let test_data_dir = setup_test_data_dir(&format!("two_animals_test_turn_mode_{mode:?}"));
+        let app_state = Arc::new(server::AppState {
+            config: Default::default(),
+            game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()).with_turn_mode(mode),
+            llm_client: llm_client.clone(),
+            prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir.clone())),
+        });
+        let app = server::create_router(app_state);
+
+        let (status, body) = post_json(&app, "/turn/execute", json!({"repeat": 3, "delay_ms": 0})).await;
+        assert_eq!(status, StatusCode::OK, "{mode:?}: {body}");
+        assert_eq!(body["turns_executed"], 3);
+        // The last turn's memories are written before the run returns
+        let last_turn = &body["last_turn_result"];
+        assert_eq!(last_turn["turn"], 3);
+        let memory_updates = last_turn["memory_updates"].as_array().unwrap();
+        assert_eq!(memory_updates.len(), 2, "{mode:?}: {last_turn}");
+        assert!(memory_updates.iter().all(|update| update["error"].is_null()));
+
+        // Every intent after the first turn's waited for its NPC's memories
+        let intent_prompts = llm_client.prompts_for(LlmRole::Intent);
+        assert_eq!(intent_prompts.len(), 6);
+        let remembering = intent_prompts.iter().filter(|p| p.contains("Still dripping from the river")).count();
+        assert_eq!(remembering, 4, "{mode:?}");
+
+        let memories = std::fs::read_to_string(test_data_dir.join("npcs/bear/memories.json")).unwrap();
+        assert!(memories.contains("Still dripping from the river"));
+    }
+}
+
+#[tokio::test]
+async fn test_rolled_back_turn_keeps_what_arrived_while_it_ran() {
+    let llm_client = Arc::new(ByRoleLlmClient {
+        gm_delay: std::time::Duration::from_millis(50),
+        ..ByRoleLlmClient::new(json!("The GM has lost the plot"))
+    });
+    let test_data_dir = setup_test_data_dir("two_animals_test_rollback_arrivals");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client,
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state.clone());
+
+    let failing_turn = tokio::spawn({
+        let app = app.clone();
+        async move { post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await }
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+
+    // Both arrive mid-turn and wait for it, rather than being rolled back with it
+    let (status, _) = post_json(&app, "/player", json!({"name": "fox"})).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = post_json(&app, "/world/events", json!({"description": "A storm rolls in"})).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let (status, _) = failing_turn.await.unwrap();
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+
+    assert!(app_state.game_manager.player().is_some());
+    let events = app_state.game_manager.world_events.overview(&app_state.game_manager.data_dir).unwrap();
+    assert_eq!(events.pending.len(), 1);
+}
+
+#[tokio::test]
+async fn test_rolled_back_turn_does_not_replace_a_newer_player_intent() {
+    let llm_client = Arc::new(ByRoleLlmClient {
+        gm_delay: std::time::Duration::from_millis(50),
+        ..ByRoleLlmClient::new(json!("The GM has lost the plot"))
+    });
+    let test_data_dir = setup_test_data_dir("two_animals_test_rollback_player_intent");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()),
+        llm_client,
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state.clone());
+    let (status, _) = post_json(&app, "/player", json!({"name": "fox"})).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = post_json(&app, "/player/intent", json!({"action": "Sniff the air"})).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    let failing_turn = tokio::spawn({
+        let app = app.clone();
+        async move { post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await }
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+    let (status, _) = post_json(&app, "/player/intent", json!({"action": "Run for the hills"})).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let (status, _) = failing_turn.await.unwrap();
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+
+    let waiting = app_state.game_manager.player_input.take().unwrap();
+    assert_eq!(waiting.action, "Run for the hills");
+}
+
+#[tokio::test]
+async fn test_memories_can_be_edited_between_pipelined_turns() {
+    let llm_client = Arc::new(ByRoleLlmClient::new(json!({
+        "reality": "Both animals shake off",
+        "state_changes": [],
+        "contracts": [],
+        "next_prompts": {"bear": "What now?", "wolf": "What now?"}
+    })));
+    let test_data_dir = setup_test_data_dir("two_animals_test_pipelined_edits");
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager: GameStateManager::new().with_data_dir(test_data_dir.clone()).with_turn_mode(TurnMode::Pipelined),
+        llm_client,
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir.clone())),
+    });
+    let app = server::create_router(app_state);
+
+    let running = tokio::spawn({
+        let app = app.clone();
+        async move { post_json(&app, "/turn/execute", json!({"repeat": 3, "delay_ms": 200})).await }
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    // Mid-run, between turns, the edit goes straight through
+    let edit = post_json(&app, "/npcs/bear/memories/self/events", json!({"event": "Heard thunder far away"}));
+    let (status, body) = tokio::time::timeout(std::time::Duration::from_millis(100), edit)
+        .await
+        .expect("the edit waited for the run to end");
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(!running.is_finished());
+
+    let (status, body) = running.await.unwrap();
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["turns_executed"], 3);
+    let memories = std::fs::read_to_string(test_data_dir.join("npcs/bear/memories.json")).unwrap();
+    assert!(memories.contains("Heard thunder far away"));
+    assert!(memories.contains("Still dripping from the river"));
+}
+
+/// Resolve one turn in which Bear and Wolf are in a contract and the GM sends
+/// `contracts`, returning the GM response and the world afterwards
+async fn resolve_contract_actions(
+    name: &str,
+    contracts: impl FnOnce(&str) -> Value,
+) -> (Value, server::types::GameState) {
+    let test_data_dir = setup_test_data_dir(name);
+    let game_manager = GameStateManager::new().with_data_dir(test_data_dir.clone());
+    let contract = game_manager
+        .contract_manager()
+        .create_contract(vec!["bear".to_string(), "wolf".to_string()], None)
+        .unwrap();
+    {
+        let mut state = game_manager.state.lock().unwrap();
+        state.set_npc_contract("bear", Some(contract.id.clone()));
+        state.set_npc_contract("wolf", Some(contract.id.clone()));
+        state.add_contract(contract.clone());
+    }
+
+    let gm_response = json!({
+        "reality": "Bear and Wolf stop chatting and start a race",
+        "state_changes": [],
+        "contracts": contracts(&contract.id),
+        "next_prompts": {"bear": "Run!", "wolf": "Run!"}
+    }).to_string();
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager,
+        llm_client: Arc::new(MockLlmClient::new(vec![gm_response; 3])),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state.clone());
+
+    let intents = json!([
+        {"npc": "bear", "thought": "", "action": "Race", "dialogue": null},
+        {"npc": "wolf", "thought": "", "action": "Race", "dialogue": null}
+    ]);
+    let (status, body) = post_json(&app, "/turn/resolve", intents).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    (body, app_state.game_manager.get_state())
+}
+
+#[tokio::test]
+async fn test_contract_can_end_and_another_begin_in_either_order() {
+    let end = |id: &str| json!({"id": id, "participants": [], "action": "end", "transcript_entry": null});
+    let create = json!({
+        "id": "race",
+        "participants": ["bear", "wolf"],
+        "action": "create",
+        "transcript_entry": {"reality": "Bear and Wolf race to the river", "details": {}}
+    });
+
+    for (name, create_first) in [("two_animals_test_end_then_create", false), ("two_animals_test_create_then_end", true)] {
+        let (body, state) = resolve_contract_actions(name, |id| {
+            if create_first { json!([create, end(id)]) } else { json!([end(id), create]) }
+        }).await;
+
+        assert!(body.get("warnings").is_none(), "{body}");
+        assert_eq!(state.contracts.len(), 1);
+        let new_id = state.contracts.keys().next().unwrap();
+        for npc in ["bear", "wolf"] {
+            assert_eq!(state.npcs[npc].active_contract.as_ref(), Some(new_id), "{npc} when create_first={create_first}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_new_contract_cannot_list_a_participant_twice() {
+    let (body, state) = resolve_contract_actions("two_animals_test_contract_twice", |id| json!([
+        {"id": id, "participants": [], "action": "end", "transcript_entry": null},
+        {"id": "solo", "participants": ["bear", "bear"], "action": "create", "transcript_entry": null}
+    ])).await;
+
+    let warnings = body["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1, "{body}");
+    assert!(warnings[0].as_str().unwrap().contains("\"bear\" is listed twice"));
+    assert!(state.contracts.is_empty());
+    assert!(state.npcs.values().all(|npc| npc.active_contract.is_none()));
+}
+
+#[tokio::test]
+async fn test_contract_cannot_be_updated_and_ended_in_one_response() {
+    let (body, state) = resolve_contract_actions("two_animals_test_contract_update_and_end", |id| json!([
+        {"id": id, "participants": [], "action": "end", "transcript_entry": null},
+        {"id": id, "participants": [], "action": "update", "transcript_entry": {"reality": "Wolf waves goodbye", "details": {}}}
+    ])).await;
+
+    let warnings = body["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1, "{body}");
+    assert!(warnings[0].as_str().unwrap().contains("ends in the same response"), "{body}");
+    assert!(state.contracts.is_empty());
+}
+
+#[test]
+fn test_contracts_created_together_get_distinct_ids() {
+    let contracts = server::game::contracts::ContractManager::new(server::data_dir::DataDir::new(
+        setup_test_data_dir("two_animals_test_contract_ids"),
+    ));
+    let ids: std::collections::HashSet<String> = (0..50)
+        .map(|_| contracts.create_contract(vec!["bear".to_string()], None).unwrap().id)
+        .collect();
+    assert_eq!(ids.len(), 50);
+}
+
+#[tokio::test]
+async fn test_gm_rumor_id_outside_the_rumor_format_is_dropped() {
+    let (body, state) = resolve_contract_actions("two_animals_test_bad_rumor_id", |id| json!([{
+        "id": id,
+        "participants": [],
+        "action": "update",
+        "transcript_entry": {
+            "reality": "Wolf tells Bear a secret",
+            "details": {},
+            "shared_information": [{"speaker": "wolf", "rumor_id": "../../config", "content": "The hunters left"}]
+        }
+    }])).await;
+
+    let warnings = body["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1, "{body}");
+    assert!(warnings[0].as_str().unwrap().contains("invalid rumor_id \"../../config\""));
+
+    // Still heard, but as something Wolf claims rather than a known rumor
+    assert_eq!(state.contracts.len(), 1);
+    let rumors = server::npcs::gossip::list_rumors(&server::data_dir::DataDir::new(
+        std::env::temp_dir().join("two_animals_test_bad_rumor_id"),
+    ))
+    .unwrap();
+    assert_eq!(rumors.len(), 1);
+    assert!(rumors[0].id.starts_with("rumor_"));
+    assert_eq!(rumors[0].tellings[0].to, "bear");
+}
+
+#[tokio::test]
+async fn test_rumor_trace_rejects_ids_that_are_not_rumor_ids() {
+    let app = create_test_app().await;
+
+    for uri in ["/rumors/..%2F..%2Fconfig", "/rumors/npc_state"] {
+        let (status, body) = send(&app, "GET", uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}: {body}");
+        assert_eq!(body["kind"], "bad_request");
+    }
+    let (status, _) = send(&app, "GET", "/rumors/rumor_t1_wolf_1").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_contract_participants_take_turns_and_summarize_when_it_ends() {
+    let test_data_dir = setup_test_data_dir("two_animals_test_contract_turns");
+    let game_manager = GameStateManager::new().with_data_dir(test_data_dir.clone());
+    let opening = serde_json::from_value(json!({"reality": "Bear greets Wolf at the river", "details": {}})).unwrap();
+    let contract = game_manager
+        .contract_manager()
+        .create_contract(vec!["bear".to_string(), "wolf".to_string()], Some(opening))
+        .unwrap();
+    {
+        let mut state = game_manager.state.lock().unwrap();
+        state.set_npc_contract("bear", Some(contract.id.clone()));
+        state.set_npc_contract("wolf", Some(contract.id.clone()));
+        state.add_contract(contract.clone());
+    }
+
+    let llm_client = Arc::new(ByRoleLlmClient::new(json!({
+        "reality": "Wolf walks off, leaving Bear the river",
+        "state_changes": [],
+        "contracts": [{
+            "id": contract.id,
+            "participants": [],
+            "action": "end",
+            "transcript_entry": {"reality": "Wolf walks off, leaving Bear the river", "details": {}}
+        }],
+        "next_prompts": {"bear": "What now?", "wolf": "What now?"}
+    })));
+    let app_state = Arc::new(server::AppState {
+        config: Default::default(),
+        game_manager,
+        llm_client: llm_client.clone(),
+        prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+    });
+    let app = server::create_router(app_state.clone());
+
+    let (status, body) = post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // Wolf goes first on turn 1, and Bear answers what Wolf just said
+    let intent_prompts = llm_client.prompts_for(LlmRole::Intent);
+    assert_eq!(intent_prompts.len(), 2);
+    assert!(intent_prompts[0].contains("Test wolf") && !intent_prompts[0].contains("## Just Now"));
+    assert!(intent_prompts[1].contains("Test bear"));
+    assert!(intent_prompts[1].contains("## Just Now\n\n**wolf**: Shake off - Said: \"wolf was here\""));
+    assert!(!intent_prompts[1].contains("Wet"), "thoughts stay private");
+
+    // Each participant looks back over the whole interaction once it's over
+    let summaries: Vec<String> = llm_client
+        .prompts_for(LlmRole::Memory)
+        .into_iter()
+        .filter(|p| p.contains("## Interaction Summary Task"))
+        .collect();
+    assert_eq!(summaries.len(), 2);
+    for summary in &summaries {
+        assert!(summary.contains("Bear greets Wolf at the river"));
+        assert!(summary.contains("Wolf walks off, leaving Bear the river"));
+    }
+    let memory_updates = body["last_turn_result"]["memory_updates"].as_array().unwrap();
+    assert!(memory_updates.iter().all(|update| update["error"].is_null()), "{memory_updates:?}");
+
+    // The contract is archived and both are free again
+    let state = app_state.game_manager.get_state();
+    assert!(state.contracts.is_empty());
+    assert!(state.npcs.values().all(|npc| npc.active_contract.is_none()));
+}
+
+#[tokio::test]
+async fn test_ended_contract_keeps_the_turns_memories_without_a_summary() {
+    // One interaction was never written down, the other can't be summarized
+    for (name, opening, failing_summary) in [
+        ("two_animals_test_summary_no_transcript", None, false),
+        ("two_animals_test_summary_fails", Some("Bear greets Wolf at the river"), true),
+    ] {
+        let test_data_dir = setup_test_data_dir(name);
+        let game_manager = GameStateManager::new().with_data_dir(test_data_dir.clone());
+        let opening = opening.map(|reality| serde_json::from_value(json!({"reality": reality, "details": {}})).unwrap());
+        let contract = game_manager
+            .contract_manager()
+            .create_contract(vec!["bear".to_string(), "wolf".to_string()], opening)
+            .unwrap();
+        {
+            let mut state = game_manager.state.lock().unwrap();
+            state.set_npc_contract("bear", Some(contract.id.clone()));
+            state.set_npc_contract("wolf", Some(contract.id.clone()));
+            state.add_contract(contract.clone());
+        }
+
+        let llm_client = Arc::new(ByRoleLlmClient {
+            failing_summary,
+            ..ByRoleLlmClient::new(json!({
+                "reality": "Wolf walks off, leaving Bear the river",
+                "state_changes": [],
+                "contracts": [{"id": contract.id, "participants": [], "action": "end", "transcript_entry": null}],
+                "next_prompts": {"bear": "What now?", "wolf": "What now?"}
+            }))
+        });
+        let app_state = Arc::new(server::AppState {
+            config: Default::default(),
+            game_manager,
+            llm_client: llm_client.clone(),
+            prompt_builder: PromptBuilder::new(PromptLoader::new(test_data_dir)),
+        });
+        let app = server::create_router(app_state.clone());
+
+        let (status, body) = post_json(&app, "/turn/execute", json!({"delay_ms": 0})).await;
+        assert_eq!(status, StatusCode::OK, "{name}: {body}");
+
+        let summaries = llm_client
+            .prompts_for(LlmRole::Memory)
+            .into_iter()
+            .filter(|p| p.contains("## Interaction Summary Task"))
+            .count();
+        assert_eq!(summaries, if failing_summary { 2 } else { 0 }, "{name}");
+        let memory_updates = body["last_turn_result"]["memory_updates"].as_array().unwrap();
+        assert_eq!(memory_updates.len(), 2, "{name}");
+        for update in memory_updates {
+            assert!(update["error"].is_null(), "{name}: {update}");
+            // The turn's own update, saved on top of the starting memories
+            assert_eq!(update["version"], 2, "{name}: {update}");
+        }
+        let memories = server::npcs::memory_store::load_npc_memories(&app_state.game_manager.data_dir, "bear").unwrap();
+        assert_eq!(memories.self_memories.immediate_context, "Still dripping from the river");
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use server::llm::{CachingLlmClient, LlmOptions, LlmResponse, LlmRole, Prompt, ResponseCache, Usage, UsageLedger};
+use server::LlmClient;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Answers with how many times it has been asked
+#[derive(Default)]
+struct CountingLlmClient {
+    calls: AtomicUsize,
+}
+
+#[async_trait]
+impl LlmClient for CountingLlmClient {
+    async fn query(&self, _prompt: Prompt, _working_dir: &Path, _options: &LlmOptions) -> Result<LlmResponse> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        Ok(LlmResponse::new(format!(r#"{{"call": {call}}}"#)).with_usage(Usage {
+            provider: "counting".to_string(),
+            model: "counting-model".to_string(),
+            prompt_tokens: 1000,
+            completion_tokens: 100,
+            latency_ms: 5,
+        }))
+    }
+}
+
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn caching(inner: &Arc<CountingLlmClient>, cache: ResponseCache, fingerprint: &str) -> CachingLlmClient {
+    CachingLlmClient::new(Arc::clone(inner) as Arc<dyn LlmClient>, cache, fingerprint)
+}
+
+// Ask like a caller that could parse every response
+async fn ask(client: &CachingLlmClient, prompt: &str) -> LlmResponse {
+    let response = client.query(prompt.into(), Path::new("."), &LlmOptions::default()).await.unwrap();
+    client.accept(&response);
+    response
+}
+
+#[tokio::test]
+async fn test_identical_prompts_are_answered_from_disk() {
+    let dir = cache_dir("two_animals_test_cache_hits");
+    let inner = Arc::new(CountingLlmClient::default());
+    let client = caching(&inner, ResponseCache::new(&dir, Duration::from_secs(60), 1 << 20), "counting t=0.7");
+
+    let first = ask(&client, "What now?").await;
+    let again = ask(&client, "What now?").await;
+    let other = ask(&client, "What next?").await;
+
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    assert_eq!(again.text, first.text);
+    assert_ne!(other.text, first.text);
+    // A hit costs nothing
+    assert_eq!((again.usage.provider.as_str(), again.usage.prompt_tokens), ("cache", 0));
+    assert_eq!(again.usage.model, "counting-model");
+
+    // The cache outlives the client, and a different model or sampling doesn't share it
+    let reopened = caching(&inner, ResponseCache::new(&dir, Duration::from_secs(60), 1 << 20), "counting t=0.7");
+    assert_eq!(ask(&reopened, "What now?").await.text, first.text);
+    let resampled = caching(&inner, ResponseCache::new(&dir, Duration::from_secs(60), 1 << 20), "counting t=0.2");
+    assert_ne!(ask(&resampled, "What now?").await.text, first.text);
+    // Nor do the same prompt's calls with other options
+    let seeded = LlmOptions { seed: Some(7), ..Default::default() };
+    let response = client.query("What now?".into(), Path::new("."), &seeded).await.unwrap();
+    client.accept(&response);
+    assert_ne!(response.text, first.text);
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 4);
+
+    let metrics = server::metrics::metrics().render();
+    assert!(metrics.contains(r#"two_animals_llm_cache_lookups_total{result="hit"}"#));
+    assert!(metrics.contains(r#"two_animals_llm_cache_lookups_total{result="miss"}"#));
+}
+
+#[tokio::test]
+async fn test_streamed_responses_are_cached() {
+    let dir = cache_dir("two_animals_test_cache_stream");
+    let inner = Arc::new(CountingLlmClient::default());
+    let client = caching(&inner, ResponseCache::new(&dir, Duration::from_secs(60), 1 << 20), "counting");
+
+    let chunks: Vec<_> = client
+        .query_stream("Stream it".into(), Path::new("."), &LlmOptions::default())
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    let mut streamed = LlmResponse::default();
+    for chunk in chunks {
+        let chunk = chunk.unwrap();
+        streamed.text.push_str(&chunk.text);
+        streamed.cache_key = chunk.cache_key.or(streamed.cache_key);
+    }
+    client.accept(&streamed);
+
+    assert_eq!(ask(&client, "Stream it").await.text, streamed.text);
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_responses_the_caller_rejects_are_not_replayed() {
+    let dir = cache_dir("two_animals_test_cache_rejected");
+    let inner = Arc::new(CountingLlmClient::default());
+    let client = caching(&inner, ResponseCache::new(&dir, Duration::from_secs(60), 1 << 20), "counting");
+
+    // Say the first answer couldn't be parsed: it's asked for again, not served from disk
+    let unparsed = client.query("What now?".into(), Path::new("."), &LlmOptions::default()).await.unwrap();
+    let again = ask(&client, "What now?").await;
+    assert_ne!(again.text, unparsed.text);
+    assert_eq!(ask(&client, "What now?").await.text, again.text);
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_expired_and_evicted_responses_are_asked_for_again() {
+    let inner = Arc::new(CountingLlmClient::default());
+
+    let expiring = caching(&inner, ResponseCache::new(cache_dir("two_animals_test_cache_ttl"), Duration::ZERO, 1 << 20), "counting");
+    ask(&expiring, "What now?").await;
+    ask(&expiring, "What now?").await;
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+
+    // Room for a single response, so each new one pushes the last out
+    let dir = cache_dir("two_animals_test_cache_size");
+    let tiny = caching(&inner, ResponseCache::new(&dir, Duration::from_secs(60), 300), "counting");
+    ask(&tiny, "First").await;
+    ask(&tiny, "Second").await;
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    ask(&tiny, "Second").await;
+    ask(&tiny, "First").await;
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 5);
+
+    // Counted as it grows, the cache never goes over its limit
+    let dir = cache_dir("two_animals_test_cache_bounded");
+    let bounded = caching(&inner, ResponseCache::new(&dir, Duration::from_secs(60), 2000), "counting");
+    for n in 0..40 {
+        ask(&bounded, &format!("Question {n}")).await;
+        let bytes: u64 = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().metadata().unwrap().len()).sum();
+        assert!(bytes <= 2000, "{bytes} bytes after {n} responses");
+    }
+    assert!(std::fs::read_dir(&dir).unwrap().count() > 1);
+}
+
+#[test]
+fn test_cache_hits_are_counted_apart_from_call_latency() {
+    let ledger = UsageLedger::new(Default::default());
+    let hit = Usage {
+        provider: "cache".to_string(),
+        model: "counting-model".to_string(),
+        ..Usage::default()
+    };
+    ledger.record(1, LlmRole::GmRepair, None, &hit);
+
+    let metrics = server::metrics::metrics().render();
+    assert!(metrics.contains(r#"two_animals_llm_cache_hits_total{role="gm_repair"} 1"#), "{metrics}");
+    assert!(!metrics.contains(r#"provider="cache""#), "{metrics}");
+    // Still shows up in the usage report, at no cost
+    assert_eq!(ledger.report().total.calls, 1);
+}
@@ -0,0 +1,38 @@
+use chrono::Timelike;
+use server::game::clock::{TimeOfDay, WorldClock};
+
+#[test]
+fn test_clock_advances_through_the_day() {
+    let mut clock = WorldClock::new(60);
+    assert_eq!(clock.time.hour(), 6);
+    assert_eq!(clock.time_of_day, TimeOfDay::Dawn);
+
+    for turn in 1..=6 {
+        clock.advance(turn);
+    }
+    assert_eq!(clock.time.hour(), 12);
+    assert_eq!(clock.time_of_day, TimeOfDay::Afternoon);
+
+    for turn in 7..=16 {
+        clock.advance(turn);
+    }
+    assert_eq!(clock.time.hour(), 22);
+    assert_eq!(clock.time_of_day, TimeOfDay::Night);
+}
+
+#[test]
+fn test_weather_replays_for_the_same_turns() {
+    let mut first = WorldClock::new(120);
+    let mut second = WorldClock::new(120);
+    let mut changed = false;
+
+    for turn in 1..=50 {
+        first.advance(turn);
+        second.advance(turn);
+        assert_eq!(first.weather, second.weather);
+        changed |= first.weather != WorldClock::new(120).weather;
+    }
+
+    // Over 100 in-world hours the weather should turn at least once
+    assert!(changed);
+}
@@ -0,0 +1,132 @@
+use server::config::{Config, Overrides};
+use server::game::turn::TurnMode;
+use server::llm::LlmRole;
+
+fn valid_data_dir() -> String {
+    let dir = std::env::temp_dir().join("two_animals_test_config_data");
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.to_string_lossy().to_string()
+}
+
+#[test]
+fn test_config_file_is_layered_under_overrides() {
+    let config = Config::from_toml(&format!(r#"
+        [server]
+        bind = "127.0.0.1:4000"
+
+        [llm]
+        provider = "ollama"
+        model = "llama3.2:latest"
+
+        [llm.ollama]
+        top_p = 0.8
+
+        [memory]
+        max_relationship_memories = 5
+
+        [paths]
+        data_dir = "{}"
+    "#, valid_data_dir())).unwrap();
+
+    // Values the file leaves out keep their defaults
+    assert_eq!(config.llm.ollama.url, "http://localhost:11434");
+    assert_eq!(config.llm.ollama.top_p, 0.8);
+    assert_eq!(config.memory.max_recent_events, 10);
+    assert_eq!(config.memory.max_relationship_memories, 5);
+
+    let overrides = Overrides {
+        model: Some("qwen2.5:7b".to_string()),
+        turn_minutes: Some(15),
+        turn_mode: Some(TurnMode::Strict),
+        ..Default::default()
+    };
+    let config = config.with_overrides(&overrides);
+    assert_eq!(config.server.bind, "127.0.0.1:4000");
+    assert_eq!(config.llm.model.as_deref(), Some("qwen2.5:7b"));
+    assert_eq!(config.turn.duration_minutes, 15);
+    assert_eq!(config.turn.mode, TurnMode::Strict);
+    assert!(config.validate().is_empty());
+}
+
+#[test]
+fn test_invalid_config_reports_every_problem() {
+    let config = Config::from_toml(r#"
+        [server]
+        bind = "everywhere"
+
+        [llm]
+        provider = "gpt"
+
+        [llm.ollama]
+        top_p = 1.5
+
+        [llm.limits.ollama]
+        max_in_flight = 0
+
+        [memory]
+        max_recent_events = 0
+
+        [paths]
+        data_dir = "/definitely/not/here"
+    "#).unwrap();
+
+    let problems = config.validate();
+    assert_eq!(problems.len(), 6, "{problems:?}");
+    for key in ["server.bind", "llm.provider", "llm.ollama.top_p", "llm.limits.ollama.max_in_flight", "memory.max_recent_events", "paths.data_dir"] {
+        assert!(problems.iter().any(|p| p.starts_with(key)), "no problem reported for {key}");
+    }
+}
+
+#[test]
+fn test_worlds_dir_inside_data_dir_is_rejected() {
+    let data_dir = std::env::temp_dir().join("two_animals_config_nested_worlds");
+    std::fs::create_dir_all(&data_dir).unwrap();
+    let mut config = Config::from_toml("[llm]\nprovider = \"ollama\"\n").unwrap();
+    config.paths.data_dir = data_dir.clone();
+
+    for inside in [data_dir.join("worlds"), data_dir.join("npcs/../worlds"), data_dir.clone()] {
+        config.paths.worlds_dir = inside.clone();
+        let problems = config.validate();
+        assert!(problems.iter().any(|p| p.starts_with("paths.worlds_dir")), "{inside:?}: {problems:?}");
+    }
+    config.paths.worlds_dir = data_dir.join("../two_animals_config_nested_worlds_elsewhere");
+    assert!(config.validate().is_empty(), "{:?}", config.validate());
+}
+
+#[test]
+fn test_unknown_config_keys_are_rejected() {
+    let error = Config::from_toml("[memory]\nmax_memories = 5\n").unwrap_err();
+    assert!(format!("{error:#}").contains("max_memories"));
+}
+
+#[test]
+fn test_example_config_is_valid() {
+    let config = Config::from_file(std::path::Path::new("config.example.toml")).unwrap();
+    assert!(config.validate().is_empty(), "{:?}", config.validate());
+}
+
+#[test]
+fn test_sampling_options_layer_from_npc_to_role_to_seed() {
+    let config = Config::from_toml(r#"
+        [llm.sampling]
+        seed = 42
+
+        [llm.sampling.intent]
+        top_p = 0.8
+
+        [llm.sampling.npcs.wolf]
+        temperature = 1.1
+        seed = 7
+    "#).unwrap();
+    let sampling = &config.llm.sampling;
+
+    let wolf = sampling.options_for(LlmRole::Intent, Some("wolf"));
+    assert_eq!((wolf.temperature, wolf.top_p, wolf.seed), (Some(1.1), Some(0.8), Some(7)));
+    let bear = sampling.options_for(LlmRole::Intent, Some("bear"));
+    assert_eq!((bear.temperature, bear.top_p, bear.seed), (Some(0.9), Some(0.8), Some(42)));
+    // An NPC's options are for its intents; the GM and memory keeper keep their own
+    let gm = sampling.options_for(LlmRole::Gm, None);
+    assert_eq!((gm.temperature, gm.top_p, gm.seed), (Some(0.2), None, Some(42)));
+    let memory = sampling.options_for(LlmRole::Memory, Some("wolf"));
+    assert_eq!((memory.temperature, memory.seed), (Some(0.3), Some(42)));
+}
@@ -0,0 +1,136 @@
+use server::data_dir::DataDir;
+use server::npcs::gossip::{is_valid_rumor_id, load_rumor, record_witnessed, spread, trace_rumor};
+use server::npcs::knowledge::{load_knowledge, FactSource};
+use server::npcs::memory::{MemorySystem, RelationshipMemory, SelfMemories};
+use server::types::SharedInformation;
+use std::collections::HashMap;
+
+/// A world where bear feels `feeling` (-1.0 to 1.0) towards wolf
+fn gossip_data_dir(name: &str, feeling: f32) -> DataDir {
+    let root = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("npcs/wolf")).unwrap();
+    std::fs::create_dir_all(root.join("npcs/bear")).unwrap();
+
+    let mut relationships = HashMap::new();
+    relationships.insert("wolf".to_string(), RelationshipMemory {
+        immediate_context: String::new(),
+        recent_memories: vec![],
+        long_term_summary: String::new(),
+        core_memories: vec![],
+        current_sentiment: feeling,
+        overall_bond: feeling,
+    });
+    let memories = MemorySystem {
+        self_memories: SelfMemories {
+            immediate_context: String::new(),
+            recent_events: vec![],
+            core_memories: vec![],
+        },
+        relationships,
+    };
+    std::fs::write(root.join("npcs/bear/memories.json"), serde_json::to_string(&memories).unwrap()).unwrap();
+    DataDir::new(root)
+}
+
+fn wolf_says(rumor_id: Option<String>, content: &str) -> SharedInformation {
+    SharedInformation {
+        speaker: "wolf".to_string(),
+        listeners: vec![],
+        rumor_id,
+        content: content.to_string(),
+    }
+}
+
+fn participants() -> Vec<String> {
+    vec!["wolf".to_string(), "bear".to_string()]
+}
+
+#[test]
+fn test_spreading_something_unlearned_starts_a_claim() {
+    let data_dir = gossip_data_dir("gossip_claim_test", 1.0);
+
+    let shared = wolf_says(Some("rumor_made_up".to_string()), "The river is poisoned");
+    spread(&data_dir, &shared, &participants(), 4).unwrap();
+
+    let wolf_fact = load_knowledge(&data_dir, "wolf").unwrap().facts[0].clone();
+    assert_eq!(wolf_fact.source, FactSource::Claimed);
+    assert!(wolf_fact.confidence < 1.0);
+    assert_eq!(wolf_fact.retellings, 0);
+
+    let bear_fact = load_knowledge(&data_dir, "bear").unwrap().get(&wolf_fact.rumor_id).cloned().unwrap();
+    assert_eq!(bear_fact.source, FactSource::ToldBy { npc: "wolf".to_string(), trust: 1.0 });
+    assert!(bear_fact.believed);
+    assert!(bear_fact.confidence < wolf_fact.confidence);
+    assert_eq!(bear_fact.retellings, 1);
+
+    let rumor = load_rumor(&data_dir, &wolf_fact.rumor_id).unwrap().unwrap();
+    assert_eq!(rumor.origin, "wolf");
+    assert_eq!(rumor.first_turn, 4);
+    assert_eq!(rumor.tellings.len(), 1);
+    assert_eq!(rumor.tellings[0].to, "bear");
+}
+
+#[test]
+fn test_retelling_something_witnessed_keeps_the_rumor() {
+    let data_dir = gossip_data_dir("gossip_retelling_test", 1.0);
+    let rumor_id = record_witnessed(&data_dir, "wolf", &["Hunters camp by the lake".to_string()], 2)
+        .unwrap()
+        .remove(0);
+
+    spread(&data_dir, &wolf_says(Some(rumor_id.clone()), "Hunters camp by the lake"), &participants(), 3).unwrap();
+
+    let wolf_knowledge = load_knowledge(&data_dir, "wolf").unwrap();
+    assert_eq!(wolf_knowledge.facts.len(), 1);
+    assert_eq!(wolf_knowledge.facts[0].source, FactSource::Witnessed);
+
+    let bear_fact = load_knowledge(&data_dir, "bear").unwrap().get(&rumor_id).cloned().unwrap();
+    assert!(bear_fact.believed);
+    assert!((bear_fact.confidence - 0.9).abs() < 1e-6);
+    assert_eq!(bear_fact.fidelity, 1.0);
+}
+
+#[test]
+fn test_distrusted_speaker_is_heard_but_not_believed() {
+    let data_dir = gossip_data_dir("gossip_distrust_test", -1.0);
+
+    spread(&data_dir, &wolf_says(None, "I saw a golden salmon"), &participants(), 1).unwrap();
+
+    let bear_fact = load_knowledge(&data_dir, "bear").unwrap().facts[0].clone();
+    assert!(!bear_fact.believed);
+    let rumor = load_rumor(&data_dir, &bear_fact.rumor_id).unwrap().unwrap();
+    assert!(!rumor.tellings[0].believed);
+}
+
+#[test]
+fn test_trace_rumor_lists_every_holder() {
+    let data_dir = gossip_data_dir("gossip_trace_test", 1.0);
+    let rumor_id = record_witnessed(&data_dir, "wolf", &["Hunters camp by the lake".to_string()], 2)
+        .unwrap()
+        .remove(0);
+    spread(&data_dir, &wolf_says(Some(rumor_id.clone()), "Hunters are at the lake"), &participants(), 3).unwrap();
+
+    let npcs = participants();
+    let trace = trace_rumor(&data_dir, &rumor_id, &npcs).unwrap().unwrap();
+    assert_eq!(trace.rumor.original_content, "Hunters camp by the lake");
+    assert_eq!(trace.rumor.tellings.len(), 1);
+    assert_eq!(trace.holders.keys().collect::<Vec<_>>(), vec!["bear", "wolf"]);
+    assert_eq!(trace.holders["bear"].content, "Hunters are at the lake");
+    assert!(trace.holders["bear"].fidelity < 1.0);
+
+    assert!(trace_rumor(&data_dir, "rumor_unknown", &npcs).unwrap().is_none());
+}
+
+#[test]
+fn test_only_generated_rumor_ids_are_valid() {
+    let data_dir = gossip_data_dir("gossip_rumor_id_test", 1.0);
+    let rumor_id = record_witnessed(&data_dir, "wolf", &["Hunters camp by the lake".to_string()], 2)
+        .unwrap()
+        .remove(0);
+    assert!(is_valid_rumor_id(&rumor_id));
+
+    for id in ["", "rumor_", "../rumor_x", "rumor_../../config", "rumor_a/b", "npc_state"] {
+        assert!(!is_valid_rumor_id(id), "{id}");
+    }
+    assert!(load_rumor(&data_dir, "rumor_../../npcs/wolf/memories").unwrap().is_none());
+}
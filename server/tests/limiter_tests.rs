@@ -0,0 +1,100 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::future::join_all;
+use server::config::LlmLimits;
+use server::llm::{LimitedLlmClient, LlmOptions, LlmResponse, Prompt, Usage};
+use server::LlmClient;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// Takes a second to answer, remembering how many calls it had at once
+#[derive(Default)]
+struct SlowLlmClient {
+    in_flight: AtomicUsize,
+    most_in_flight: AtomicUsize,
+}
+
+#[async_trait]
+impl LlmClient for SlowLlmClient {
+    async fn query(&self, _prompt: Prompt, _working_dir: &Path, _options: &LlmOptions) -> Result<LlmResponse> {
+        let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.most_in_flight.fetch_max(now, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        Ok(LlmResponse::new("{}").with_usage(Usage {
+            provider: "slow".to_string(),
+            model: "slow-model".to_string(),
+            prompt_tokens: 1000,
+            completion_tokens: 100,
+            latency_ms: 1000,
+        }))
+    }
+}
+
+fn limited(limits: LlmLimits) -> (Arc<SlowLlmClient>, LimitedLlmClient) {
+    let inner = Arc::new(SlowLlmClient::default());
+    let client = LimitedLlmClient::new(Arc::clone(&inner) as Arc<dyn LlmClient>, "slow", &limits);
+    (inner, client)
+}
+
+async fn query_all(client: &LimitedLlmClient, calls: usize) -> Vec<Instant> {
+    join_all((0..calls).map(|_| async {
+        client.query("Think".into(), Path::new("."), &LlmOptions::default()).await.unwrap();
+        Instant::now()
+    }))
+    .await
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_calls_queue_for_an_in_flight_slot() {
+    let (inner, client) = limited(LlmLimits { max_in_flight: 2, ..Default::default() });
+    let started = Instant::now();
+
+    let finished = query_all(&client, 6).await;
+
+    assert_eq!(inner.most_in_flight.load(Ordering::SeqCst), 2);
+    // Three rounds of two one-second calls
+    let last = finished.iter().max().unwrap().duration_since(started);
+    assert!(last >= Duration::from_secs(3) && last < Duration::from_secs(4), "{last:?}");
+    let metrics = server::metrics::metrics().render();
+    assert!(metrics.contains(r#"two_animals_llm_queue_wait_seconds_count{provider="slow"}"#));
+    assert!(metrics.contains(r#"two_animals_llm_queued_calls{provider="slow"} 0"#));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_requests_per_minute_spread_calls_out() {
+    let (_, client) = limited(LlmLimits {
+        max_in_flight: 10,
+        requests_per_minute: Some(2),
+        tokens_per_minute: None,
+    });
+    let started = Instant::now();
+
+    let finished = query_all(&client, 4).await;
+
+    // Two right away, then one more every 30 seconds
+    let mut waits: Vec<u64> = finished.iter().map(|at| at.duration_since(started).as_secs()).collect();
+    waits.sort();
+    assert_eq!(waits, [1, 1, 31, 61]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_tokens_per_minute_count_what_calls_really_took() {
+    let (_, client) = limited(LlmLimits {
+        max_in_flight: 10,
+        requests_per_minute: None,
+        tokens_per_minute: Some(1000),
+    });
+    let started = Instant::now();
+
+    // The first call takes 1100 tokens, 100 more than a minute's allowance
+    client.query("Think".into(), Path::new("."), &LlmOptions::default()).await.unwrap();
+    client.query("Think".into(), Path::new("."), &LlmOptions::default()).await.unwrap();
+
+    // So the second waits for the debt to be paid back, 100 tokens at 1000 a minute
+    let took = Instant::now().duration_since(started);
+    assert!(took >= Duration::from_secs(8) && took < Duration::from_secs(9), "{took:?}");
+}
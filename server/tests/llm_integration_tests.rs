@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod llm_integration_tests {
-    use server::{llm::{ClaudeClient, OllamaClient}, LlmClient};
+    use server::{llm::{ClaudeClient, LlmOptions, OllamaClient}, LlmClient};
     use serde_json::Value;
     use std::path::Path;
     use std::sync::Arc;
@@ -54,9 +54,10 @@ Current situation: You are hungry and want to find food in the forest.
 What do you do?
 "#;
 
-        let response = client.query(test_prompt.to_string(), Path::new("/tmp"))
+        let response = client.query(test_prompt.into(), Path::new("/tmp"), &LlmOptions::default())
             .await
-            .expect("LLM query failed");
+            .expect("LLM query failed")
+            .text;
         
         // Try to parse the response as JSON
         let parsed: Result<Value, _> = serde_json::from_str(&response);
@@ -98,9 +99,10 @@ You are a Game Master. Respond with ONLY a JSON object in this exact format:
 Current situation: Bear wants to fish, Wolf wants to hunt. They meet at the DeepForest.
 "#;
 
-        let response = client.query(test_prompt.to_string(), Path::new("/tmp"))
+        let response = client.query(test_prompt.into(), Path::new("/tmp"), &LlmOptions::default())
             .await
-            .expect("LLM query failed");
+            .expect("LLM query failed")
+            .text;
         
         let parsed: Result<Value, _> = serde_json::from_str(&response);
         assert!(parsed.is_ok(), "LLM response was not valid JSON: {}", response);
@@ -143,9 +145,10 @@ You are: bear
 You intended to fish but wolf blocked your path.
 "#;
 
-        let response = client.query(test_prompt.to_string(), Path::new("/tmp"))
+        let response = client.query(test_prompt.into(), Path::new("/tmp"), &LlmOptions::default())
             .await
-            .expect("LLM query failed");
+            .expect("LLM query failed")
+            .text;
         
         let parsed: Result<Value, _> = serde_json::from_str(&response);
         assert!(parsed.is_ok(), "LLM response was not valid JSON: {}", response);
@@ -0,0 +1,170 @@
+use chrono::Utc;
+use server::config::MemoryPolicy;
+use server::data_dir::DataDir;
+use server::npcs::memory::{Memory, MemorySystem, RelationshipMemory, SelfMemories};
+use server::npcs::memory_history::{diff_versions, MemoryVersion};
+use server::npcs::memory_store::{list_memory_versions, load_memory_version, load_npc_memories, save_npc_memories};
+use std::collections::HashMap;
+
+fn version(version: u32, memories: MemorySystem) -> MemoryVersion {
+    MemoryVersion {
+        version,
+        turn: version as u64,
+        timestamp: Utc::now(),
+        reason: "test".to_string(),
+        memories,
+    }
+}
+
+fn bear_memories(wolf_sentiment: f32, wolf_memories: Vec<Memory>, wolf_core: Vec<String>) -> MemorySystem {
+    let mut relationships = HashMap::new();
+    relationships.insert("wolf".to_string(), RelationshipMemory {
+        immediate_context: "Wolf is nearby".to_string(),
+        recent_memories: wolf_memories,
+        long_term_summary: "Neighbours".to_string(),
+        core_memories: wolf_core,
+        current_sentiment: wolf_sentiment,
+        overall_bond: 0.3,
+    });
+    
+    MemorySystem {
+        self_memories: SelfMemories {
+            immediate_context: "Fishing".to_string(),
+            recent_events: vec!["Caught a salmon".to_string()],
+            core_memories: vec![],
+        },
+        relationships,
+    }
+}
+
+#[test]
+fn test_diff_reports_sentiment_memories_and_core_memories() {
+    let growl = Memory {
+        event: "Wolf growled at me".to_string(),
+        timestamp: Utc::now(),
+        emotional_impact: "scared".to_string(),
+        importance: 0.9,
+        pinned: false,
+    };
+    
+    let before = version(1, bear_memories(0.2, vec![], vec![]));
+    let after = version(2, bear_memories(-0.8, vec![growl], vec!["The day Wolf turned on me".to_string()]));
+    
+    let diff = diff_versions(&before, &after);
+    
+    assert!(diff.self_memories.immediate_context.is_none());
+    assert!(diff.relationships_added.is_empty());
+    
+    let wolf = &diff.relationship_changes["wolf"];
+    let sentiment = wolf.sentiment.as_ref().expect("sentiment changed");
+    assert_eq!(sentiment.from, 0.2);
+    assert_eq!(sentiment.to, -0.8);
+    assert_eq!(wolf.memories_added.len(), 1);
+    assert!(wolf.memories_faded.is_empty());
+    assert_eq!(wolf.core_memories_formed, vec!["The day Wolf turned on me".to_string()]);
+    assert!(wolf.overall_bond.is_none());
+}
+
+#[test]
+fn test_diff_of_identical_versions_is_empty() {
+    let memories = bear_memories(0.1, vec![], vec![]);
+    let diff = diff_versions(&version(1, memories.clone()), &version(2, memories));
+    
+    assert!(diff.relationship_changes.is_empty());
+    assert!(diff.self_memories.events_added.is_empty());
+    assert!(diff.self_memories.events_removed.is_empty());
+}
+
+#[test]
+fn test_trust_follows_relationship() {
+    use server::npcs::knowledge::trust_from_relationship;
+    
+    let friendly = bear_memories(0.8, vec![], vec![]);
+    let mut hostile = bear_memories(-0.9, vec![], vec![]);
+    hostile.relationships.get_mut("wolf").unwrap().overall_bond = -0.9;
+    
+    let friendly_trust = trust_from_relationship(friendly.relationships.get("wolf"));
+    let hostile_trust = trust_from_relationship(hostile.relationships.get("wolf"));
+    let stranger_trust = trust_from_relationship(None);
+    
+    assert!(friendly_trust > stranger_trust);
+    assert!(hostile_trust < stranger_trust);
+    assert!(hostile_trust < 0.35, "a hostile NPC shouldn't be believed");
+    assert_eq!(stranger_trust, 0.5);
+}
+
+#[test]
+fn test_fidelity_drops_as_retellings_drift() {
+    use server::npcs::knowledge::fidelity;
+    
+    let original = "Wolf stole a fish from the river";
+    assert_eq!(fidelity(original, "Wolf stole a fish from the river!"), 1.0);
+    
+    let retold = fidelity(original, "Wolf stole three fish from the river");
+    let distorted = fidelity(original, "Bear says Wolf raided the whole salmon run");
+    assert!(retold < 1.0);
+    assert!(distorted < retold);
+}
+
+fn npc_data_dir(name: &str) -> DataDir {
+    let root = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("npcs/bear")).unwrap();
+    let initial = bear_memories(0.2, vec![], vec![]);
+    std::fs::write(
+        root.join("npcs/bear/initial_memories.json"),
+        serde_json::to_string(&initial).unwrap(),
+    ).unwrap();
+    DataDir::new(root)
+}
+
+#[test]
+fn test_loading_memories_writes_nothing() {
+    let data_dir = npc_data_dir("memory_store_load_test");
+
+    let memories = load_npc_memories(&data_dir, "bear").unwrap();
+
+    assert_eq!(memories.relationships["wolf"].current_sentiment, 0.2);
+    assert!(!data_dir.npc_dir("bear").join("memories.json").exists());
+    assert!(list_memory_versions(&data_dir, "bear").unwrap().is_empty());
+}
+
+#[test]
+fn test_first_save_records_the_initial_memories() {
+    let data_dir = npc_data_dir("memory_store_first_save_test");
+
+    let saved = save_npc_memories(
+        &data_dir,
+        "bear",
+        &bear_memories(-0.5, vec![], vec![]),
+        3,
+        "turn memory update",
+        &MemoryPolicy::default(),
+    ).unwrap();
+
+    assert_eq!(saved.version, 2);
+    let versions = list_memory_versions(&data_dir, "bear").unwrap();
+    let reasons: Vec<_> = versions.iter().map(|v| (v.version, v.reason.as_str())).collect();
+    assert_eq!(reasons, vec![(1, "initial memories"), (2, "turn memory update")]);
+
+    let initial = load_memory_version(&data_dir, "bear", 1).unwrap().unwrap();
+    assert_eq!(initial.turn, 0);
+    assert_eq!(initial.memories.relationships["wolf"].current_sentiment, 0.2);
+    assert_eq!(load_npc_memories(&data_dir, "bear").unwrap().relationships["wolf"].current_sentiment, -0.5);
+}
+
+#[test]
+fn test_old_versions_are_pruned_past_the_cap() {
+    let data_dir = npc_data_dir("memory_store_prune_test");
+    let policy = MemoryPolicy { max_memory_versions: 3, ..MemoryPolicy::default() };
+
+    for turn in 1..=4 {
+        save_npc_memories(&data_dir, "bear", &bear_memories(0.1, vec![], vec![]), turn, "edit", &policy).unwrap();
+    }
+
+    let versions: Vec<_> = list_memory_versions(&data_dir, "bear").unwrap()
+        .iter()
+        .map(|v| v.version)
+        .collect();
+    assert_eq!(versions, vec![3, 4, 5]);
+}
@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod ollama_integration_tests {
-    use server::{llm::OllamaClient, LlmClient, Intent, GmResponse, npcs::memory::MemoryUpdate};
+    use server::{llm::{LlmOptions, OllamaClient}, LlmClient, Intent, GmResponse, npcs::memory::MemoryUpdate};
     use serde_json::Value;
     use std::path::Path;
 
@@ -32,10 +32,16 @@ Respond with ONLY a JSON object containing a single field 'status' with value 'o
 Example: {"status": "ok"}
 "#;
 
-        let response = client.query(test_prompt.to_string(), Path::new("/tmp"))
+        let response = client.query(test_prompt.into(), Path::new("/tmp"), &LlmOptions::default())
             .await
             .expect("Ollama query failed");
         
+        // Ollama reports how many tokens went in and out
+        assert_eq!(response.usage.provider, "ollama");
+        assert!(response.usage.prompt_tokens > 0);
+        assert!(response.usage.completion_tokens > 0);
+        let response = response.text;
+        
         println!("Response: {}", response);
         
         // Try to parse the response as JSON
@@ -73,9 +79,10 @@ Current situation: You are hungry and want to find food in the forest.
 What do you do?
 "#;
 
-        let response = client.query(test_prompt.to_string(), Path::new("/tmp"))
+        let response = client.query(test_prompt.into(), Path::new("/tmp"), &LlmOptions::default())
             .await
-            .expect("Ollama query failed");
+            .expect("Ollama query failed")
+            .text;
         
         println!("Response: {}", response);
         
@@ -123,9 +130,10 @@ You are a Game Master. Respond with ONLY a JSON object in this exact format:
 Current situation: Bear wants to fish, Wolf wants to hunt. They meet at the ForestClearing.
 "#;
 
-        let response = client.query(test_prompt.to_string(), Path::new("/tmp"))
+        let response = client.query(test_prompt.into(), Path::new("/tmp"), &LlmOptions::default())
             .await
-            .expect("Ollama query failed");
+            .expect("Ollama query failed")
+            .text;
         
         println!("GM Response: {}", response);
         
@@ -202,9 +210,10 @@ You are a Game Master. Respond with ONLY a JSON object that includes a contract
 Ensure the transcript_entry is an object with reality and details fields.
 "#;
 
-        let response = client.query(test_prompt.to_string(), Path::new("/tmp"))
+        let response = client.query(test_prompt.into(), Path::new("/tmp"), &LlmOptions::default())
             .await
-            .expect("Ollama query failed");
+            .expect("Ollama query failed")
+            .text;
         
         println!("Contract Response: {}", response);
         
@@ -248,9 +257,10 @@ You are: bear
 You intended to fish but wolf blocked your path.
 "#;
 
-        let response = client.query(test_prompt.to_string(), Path::new("/tmp"))
+        let response = client.query(test_prompt.into(), Path::new("/tmp"), &LlmOptions::default())
             .await
-            .expect("Ollama query failed");
+            .expect("Ollama query failed")
+            .text;
         
         println!("Memory Response: {}", response);
         
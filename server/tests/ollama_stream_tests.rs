@@ -0,0 +1,132 @@
+use axum::{routing::post, Router};
+use futures::StreamExt;
+use server::config::Config;
+use server::llm::{LlmOptions, LlmRole, Prompt};
+use server::{LlmClient, OllamaClient};
+use std::path::Path;
+
+// Stands in for Ollama's /api/chat, answering with a stream of JSON lines
+async fn fake_ollama(body: &'static str) -> String {
+    let app = Router::new().route("/api/chat", post(move || async move { body }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
+
+#[tokio::test]
+async fn test_ollama_stream_yields_pieces_then_usage() {
+    let url = fake_ollama(concat!(
+        r#"{"message": {"role": "assistant", "content": "{\"thought\": "}, "done": false}"#, "\n",
+        r#"{"message": {"role": "assistant", "content": "\"Hungry\""}, "done": false}"#, "\n",
+        "\n",
+        r#"{"message": {"role": "assistant", "content": "}"}, "done": true, "prompt_eval_count": 42, "eval_count": 7}"#,
+    )).await;
+    let client = OllamaClient::with_url("test-model", url);
+    
+    let chunks: Vec<_> = client
+        .query_stream("Think".into(), Path::new("."), &LlmOptions::default())
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    let chunks: Vec<_> = chunks.into_iter().map(Result::unwrap).collect();
+    
+    let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+    assert_eq!(texts, [r#"{"thought": "#, r#""Hungry""#, "}"]);
+    assert!(chunks[..2].iter().all(|c| c.usage.is_none()));
+    let usage = chunks[2].usage.as_ref().unwrap();
+    assert_eq!(usage.provider, "ollama");
+    assert_eq!(usage.model, "test-model");
+    assert_eq!((usage.prompt_tokens, usage.completion_tokens), (42, 7));
+}
+
+#[tokio::test]
+async fn test_ollama_stream_reports_errors_sent_midway() {
+    let url = fake_ollama(concat!(
+        r#"{"message": {"role": "assistant", "content": "{"}, "done": false}"#, "\n",
+        r#"{"error": "model ran out of memory"}"#, "\n",
+    )).await;
+    let client = OllamaClient::with_url("test-model", url);
+    
+    let mut stream = client.query_stream("Think".into(), Path::new("."), &LlmOptions::default()).await.unwrap();
+    assert_eq!(stream.next().await.unwrap().unwrap().text, "{");
+    let error = stream.next().await.unwrap().unwrap_err();
+    assert!(error.to_string().contains("model ran out of memory"));
+}
+
+// Stands in for Ollama's /api/chat, answering with the request it was sent
+async fn echo_ollama() -> String {
+    let app = Router::new().route(
+        "/api/chat",
+        post(|axum::Json(request): axum::Json<serde_json::Value>| async move {
+            let message = serde_json::json!({"role": "assistant", "content": request.to_string()});
+            axum::Json(serde_json::json!({"message": message, "done": true}))
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
+
+#[tokio::test]
+async fn test_ollama_request_carries_messages_and_options() {
+    // Answers with the request it was sent
+    let client = OllamaClient::with_url("test-model", echo_ollama().await).with_top_p(0.9);
+
+    let options = LlmOptions {
+        temperature: Some(0.1),
+        max_tokens: Some(64),
+        seed: Some(42),
+        stop: vec!["\n\n".to_string()],
+        ..Default::default()
+    };
+    let prompt = Prompt::new().system("You are a wolf").user("What now?");
+    let response = client.query(prompt, Path::new("."), &options).await.unwrap();
+    let request: serde_json::Value = serde_json::from_str(&response.text).unwrap();
+    assert_eq!(request["messages"], serde_json::json!([
+        {"role": "system", "content": "You are a wolf"},
+        {"role": "user", "content": "What now?"},
+    ]));
+    let sent = &request["options"];
+    assert_eq!(sent["temperature"].as_f64().unwrap() as f32, 0.1);
+    assert_eq!(sent["top_p"].as_f64().unwrap() as f32, 0.9);
+    assert_eq!(sent["num_predict"], 64);
+    assert_eq!(sent["seed"], 42);
+    assert_eq!(sent["stop"], serde_json::json!(["\n\n"]));
+
+    // Unset options aren't sent at all
+    let response = client.query("Think".into(), Path::new("."), &LlmOptions::default()).await.unwrap();
+    let request: serde_json::Value = serde_json::from_str(&response.text).unwrap();
+    let sent = &request["options"];
+    assert!(sent.get("seed").is_none() && sent.get("num_predict").is_none() && sent.get("stop").is_none());
+    assert!(sent.get("temperature").is_none(), "the model's own temperature applies");
+}
+
+#[tokio::test]
+async fn test_ollama_is_sent_each_roles_sampling() {
+    let config = Config::from_toml(r#"
+        [llm.ollama]
+        top_p = 0.5
+
+        [llm.sampling.memory]
+        temperature = 0.4
+        top_p = 0.7
+    "#).unwrap();
+    let client = OllamaClient::with_url("test-model", echo_ollama().await).with_top_p(config.llm.ollama.top_p);
+
+    for (role, temperature, top_p) in [
+        (LlmRole::Gm, 0.2, 0.5),
+        (LlmRole::GmRepair, 0.2, 0.5),
+        (LlmRole::Intent, 0.9, 0.5),
+        (LlmRole::Memory, 0.4, 0.7),
+    ] {
+        let options = config.llm.sampling.options_for(role, None);
+        let response = client.query("Think".into(), Path::new("."), &options).await.unwrap();
+        let request: serde_json::Value = serde_json::from_str(&response.text).unwrap();
+        let sent = &request["options"];
+        assert_eq!(sent["temperature"].as_f64().unwrap() as f32, temperature, "{role:?}");
+        assert_eq!(sent["top_p"].as_f64().unwrap() as f32, top_p, "{role:?}");
+    }
+}
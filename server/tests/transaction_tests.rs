@@ -0,0 +1,50 @@
+use server::game::transaction::{atomically, write_file};
+use server::GameStateManager;
+
+fn setup_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[tokio::test]
+async fn test_failed_transaction_restores_files_and_state() {
+    let dir = setup_dir("two_animals_test_transaction_rollback");
+    let existing = dir.join("memories.json");
+    let created = dir.join("conv_new.json");
+    std::fs::write(&existing, "before").unwrap();
+    let game_manager = GameStateManager::new();
+
+    let result: anyhow::Result<()> = atomically(&game_manager, async {
+        game_manager.begin_turn();
+        write_file(&existing, "during")?;
+        write_file(&existing, "during again")?;
+        write_file(&created, "[]")?;
+        anyhow::bail!("contract file could not be written")
+    })
+    .await;
+
+    assert!(result.is_err());
+    assert_eq!(std::fs::read_to_string(&existing).unwrap(), "before");
+    assert!(!created.exists());
+    assert_eq!(game_manager.current_turn(), 0);
+}
+
+#[tokio::test]
+async fn test_successful_transaction_keeps_its_changes() {
+    let dir = setup_dir("two_animals_test_transaction_commit");
+    let path = dir.join("knowledge.json");
+    let game_manager = GameStateManager::new();
+
+    atomically(&game_manager, async {
+        game_manager.begin_turn();
+        write_file(&path, "{}")?;
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}");
+    assert_eq!(game_manager.current_turn(), 1);
+}